@@ -7,6 +7,7 @@ use std::{
 };
 
 mod attacks;
+mod lz4;
 mod magics;
 mod maps;
 
@@ -14,7 +15,13 @@ const BASE_URL: &str = "https://github.com/codedeliveryservice/RecklessNetworks/
 const NETWORK_NAME: &str = "v60-7f587dfb.nnue";
 
 fn main() {
+    let no_net = env::var_os("CARGO_FEATURE_NO_NET").is_some();
+    if !no_net && !Path::new("networks").join(NETWORK_NAME).exists() && env::var("EVALFILE").is_err() {
+        download_network();
+    }
+
     generate_model_env();
+    generate_architecture_env();
     generate_attack_maps();
     generate_compiler_info();
     generate_engine_version();
@@ -24,10 +31,6 @@ fn main() {
         generate_syzygy_binding();
     }
 
-    if !Path::new("networks").join(NETWORK_NAME).exists() && env::var("EVALFILE").is_err() {
-        download_network();
-    }
-
     println!("cargo:rerun-if-env-changed=EVALFILE");
     println!("cargo:rerun-if-changed=.git/HEAD");
     println!("cargo:rerun-if-changed=.git/logs/HEAD");
@@ -62,7 +65,54 @@ fn generate_model_env() {
         path = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
     }
 
+    // Only `no-net` builds go without a network, using a handcrafted evaluation, see `evaluation::handcrafted_eval`
+    let bytes = match env::var_os("CARGO_FEATURE_NO_NET") {
+        Some(_) => Vec::new(),
+        None => std::fs::read(&path).unwrap_or_else(|e| panic!("Failed to read the network '{}': {e}", path.display())),
+    };
+
+    // The network is mapped from disk at runtime instead of being embedded, see `nnue::embedded`
+    if env::var_os("CARGO_FEATURE_MMAP_NET").is_none() {
+        let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+        let compressed = if bytes.is_empty() { Vec::new() } else { lz4::compress(&bytes) };
+        std::fs::write(out_dir.join("network.lz4"), compressed).unwrap();
+    }
+
+    // The embedded network is compared against this checksum on request, see `nnue::print_checksum`
+    let checksum = network_checksum(&bytes);
+
+    println!("cargo:rerun-if-changed={}", path.display());
     println!("cargo:rustc-env=MODEL={}", path.display());
+    println!("cargo:rustc-env=MODEL_CHECKSUM={checksum:016x}");
+}
+
+/// Hidden layer sizes of the network, which can be overridden to build against experimental networks.
+fn generate_architecture_env() {
+    for (name, default) in [("L1_SIZE", 768), ("L2_SIZE", 16), ("L3_SIZE", 32)] {
+        let size =
+            env::var(name).map_or(default, |size| size.parse().unwrap_or_else(|_| panic!("Invalid {name}: '{size}'")));
+
+        println!("cargo:rerun-if-env-changed={name}");
+        println!("cargo:rustc-env=NNUE_{name}={size}");
+    }
+}
+
+/// 64-bit FNV-1a over little-endian words, matching `nnue::update_checksum`.
+fn network_checksum(bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let mut hash = 0xCBF2_9CE4_8422_2325;
+    let mut words = bytes.chunks_exact(8);
+
+    for word in &mut words {
+        hash = (hash ^ u64::from_le_bytes(word.try_into().unwrap())).wrapping_mul(PRIME);
+    }
+
+    for &byte in words.remainder() {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+    }
+
+    hash
 }
 
 fn generate_attack_maps() {
@@ -0,0 +1,79 @@
+//! Compressor for the [LZ4 block format][lz4], used to shrink the embedded network.
+//! The matching decompressor lives in `src/nnue/embedded.rs`.
+//!
+//! [lz4]: https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md
+
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 16;
+
+/// The last match must start at least 12 bytes before the end of the block...
+const MATCH_START_LIMIT: usize = 12;
+/// ...and the last 5 bytes are always literals.
+const LAST_LITERALS: usize = 5;
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2);
+    let mut table = vec![0usize; 1 << HASH_BITS];
+
+    let mut anchor = 0;
+    let mut position = 0;
+
+    while position + MATCH_START_LIMIT < input.len() {
+        let sequence = read_u32(input, position);
+        let hash = (sequence.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+
+        // Positions are stored off by one so that zero marks an empty slot
+        let candidate = std::mem::replace(&mut table[hash], position + 1);
+
+        if candidate == 0 || position - (candidate - 1) > MAX_OFFSET || read_u32(input, candidate - 1) != sequence {
+            position += 1;
+            continue;
+        }
+
+        let candidate = candidate - 1;
+        let mut length = MIN_MATCH;
+        while position + length < input.len() - LAST_LITERALS && input[candidate + length] == input[position + length] {
+            length += 1;
+        }
+
+        write_sequence(&mut output, &input[anchor..position], Some((position - candidate, length)));
+
+        position += length;
+        anchor = position;
+    }
+
+    write_sequence(&mut output, &input[anchor..], None);
+    output
+}
+
+fn write_sequence(output: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_length = matched.map_or(0, |(_, length)| length - MIN_MATCH);
+
+    output.push(((literals.len().min(15) as u8) << 4) | match_length.min(15) as u8);
+    write_length(output, literals.len());
+    output.extend_from_slice(literals);
+
+    if let Some((offset, _)) = matched {
+        output.extend_from_slice(&(offset as u16).to_le_bytes());
+        write_length(output, match_length);
+    }
+}
+
+/// Lengths of 15 or more continue in extra bytes after the token, each adding up to 255.
+fn write_length(output: &mut Vec<u8>, length: usize) {
+    if length < 15 {
+        return;
+    }
+
+    let mut remaining = length - 15;
+    while remaining >= 255 {
+        output.push(255);
+        remaining -= 255;
+    }
+    output.push(remaining as u8);
+}
+
+fn read_u32(input: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes(input[position..position + 4].try_into().unwrap())
+}
@@ -40,6 +40,26 @@ struct InternalState {
     checking_squares: [Bitboard; PieceType::NUM],
 }
 
+/// Why a position is drawn under the rules of chess, as opposed to the search heuristics.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+}
+
+impl std::fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Stalemate => write!(f, "stalemate"),
+            Self::InsufficientMaterial => write!(f, "insufficient material"),
+            Self::FiftyMoveRule => write!(f, "fifty-move rule"),
+            Self::ThreefoldRepetition => write!(f, "threefold repetition"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Board {
     pieces: [Bitboard; PieceType::NUM],
@@ -68,6 +88,11 @@ impl Board {
         self.halfmove_number / 2
     }
 
+    /// The number of plies played since the start of the game, as given by the fullmove number.
+    pub const fn game_ply(&self) -> usize {
+        self.halfmove_number.saturating_sub(2)
+    }
+
     pub fn side_to_move(&self) -> Color {
         Color::new((self.halfmove_number & 1) as u8)
     }
@@ -284,6 +309,46 @@ impl Board {
         self.fiftymove_clock() >= 100 && (!self.in_check() || self.has_legal_moves())
     }
 
+    /// Checks if neither side can possibly checkmate: bare kings, a single minor piece, or
+    /// only bishops on squares of the same color. Unlike `draw_by_material`, positions that
+    /// are merely hard to win, such as two knights against a bare king, don't count.
+    pub fn insufficient_material(&self) -> bool {
+        let minors = self.pieces(PieceType::Knight) | self.pieces(PieceType::Bishop);
+        if self.occupancies() != (minors | self.pieces(PieceType::King)) {
+            return false;
+        }
+
+        let bishops = self.pieces(PieceType::Bishop);
+        minors.popcount() <= 1
+            || self.pieces(PieceType::Knight).is_empty()
+                && ((bishops & Bitboard::LIGHT_SQUARES).is_empty() || (bishops & !Bitboard::LIGHT_SQUARES).is_empty())
+    }
+
+    /// Checks if the position has occurred at least twice before.
+    pub const fn threefold_repetition(&self) -> bool {
+        self.state.repetition < 0
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && !self.has_legal_moves()
+    }
+
+    /// Returns why the game is drawn in this position, if it is. Repetitions and the fifty-move
+    /// rule count as soon as a draw could be claimed.
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        if self.is_stalemate() {
+            Some(DrawReason::Stalemate)
+        } else if self.insufficient_material() {
+            Some(DrawReason::InsufficientMaterial)
+        } else if self.draw_by_fifty_move_rule() {
+            Some(DrawReason::FiftyMoveRule)
+        } else if self.threefold_repetition() {
+            Some(DrawReason::ThreefoldRepetition)
+        } else {
+            None
+        }
+    }
+
     /// Checks if the position is a known draw by material, fifty-move or repetition.
     pub fn is_draw(&self, ply: isize) -> bool {
         self.draw_by_material() || self.draw_by_fifty_move_rule() || self.draw_by_repetition(ply as i32)
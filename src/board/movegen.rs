@@ -18,6 +18,9 @@ impl super::Board {
         !list.is_empty()
     }
 
+    /// Generates the legal moves of the position. Pins and checks are resolved by masks during
+    /// generation, and en passant captures that would expose the king are dropped when the board
+    /// is updated, so the moves need no further legality check.
     pub fn generate_all_moves(&self) -> MoveList {
         let mut list = MoveList::new();
         self.append_all_moves(&mut list);
@@ -33,6 +36,92 @@ impl super::Board {
         self.generate_moves(list, MovegenKind::Quiet);
     }
 
+    /// Appends the quiet moves that check the opponent's king, directly or by discovering an
+    /// attack of a slider. Checks by castling or under-promotion are left out.
+    pub fn append_quiet_check_moves(&self, list: &mut MoveList) {
+        let stm = self.side_to_move();
+        let empty = !self.occupancies();
+        let king_sq = self.king_square(stm);
+        let their_king = self.king_square(!stm);
+        let discoverers = self.discoverers();
+
+        // Any move off the line to the opponent's king discovers the check of the slider behind
+        let checks = |from: Square, pt: PieceType| {
+            let discovering =
+                if discoverers.contains(from) { !ray_pass(their_king, from) } else { Bitboard::default() };
+            self.checking_squares(pt) | discovering
+        };
+
+        if discoverers.contains(king_sq) {
+            let targets = king_attacks(king_sq) & !self.all_threats() & empty & checks(king_sq, PieceType::King);
+            list.push_setwise(king_sq, targets, MoveKind::Normal);
+        }
+
+        if self.checkers().is_multiple() {
+            return;
+        }
+
+        let target = if self.in_check() { between(king_sq, self.checkers().lsb()) & empty } else { empty };
+        let pinned = self.pinned(stm);
+
+        for knight in self.colored_pieces(stm, PieceType::Knight) & !pinned {
+            list.push_setwise(
+                knight,
+                knight_attacks(knight) & target & checks(knight, PieceType::Knight),
+                MoveKind::Normal,
+            );
+        }
+
+        for pt in [PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            for from in self.colored_pieces(stm, pt) {
+                let mut targets = match pt {
+                    PieceType::Bishop => bishop_attacks(from, !empty),
+                    PieceType::Rook => rook_attacks(from, !empty),
+                    _ => queen_attacks(from, !empty),
+                } & target
+                    & checks(from, pt);
+
+                if pinned.contains(from) {
+                    targets &= ray_pass(king_sq, from);
+                }
+                list.push_setwise(from, targets, MoveKind::Normal);
+            }
+        }
+
+        // Pushes stay on the file of the pawn, so only pawns off the file of the king discover a check
+        let up = Square::UP[stm];
+        let pawns = self.colored_pieces(stm, PieceType::Pawn) & (!pinned | Bitboard::file(king_sq.file()));
+        let discovering = pawns & discoverers & !Bitboard::file(their_king.file());
+
+        let single_pushes = pawns.shift(up) & empty & !Bitboard::BOTH_HOME_ROWS;
+        let double_pushes = (single_pushes & Bitboard::THIRD_RANK[stm]).shift(up) & empty;
+        let single_checks = self.checking_squares(PieceType::Pawn) | discovering.shift(up);
+        let double_checks = self.checking_squares(PieceType::Pawn) | discovering.shift(up).shift(up);
+
+        list.push_pawns_setwise(up, single_pushes & target & single_checks, MoveKind::Normal);
+        list.push_pawns_setwise(up * 2, double_pushes & target & double_checks, MoveKind::DoublePush);
+    }
+
+    /// Pieces of the side to move that alone block one of its sliders from the opponent's king.
+    fn discoverers(&self) -> Bitboard {
+        let stm = self.side_to_move();
+        let their_king = self.king_square(!stm);
+
+        let diagonal =
+            self.pieces2(PieceType::Bishop, PieceType::Queen) & bishop_attacks(their_king, Bitboard::default());
+        let orthogonal =
+            self.pieces2(PieceType::Rook, PieceType::Queen) & rook_attacks(their_king, Bitboard::default());
+
+        let mut discoverers = Bitboard::default();
+        for slider in (diagonal | orthogonal) & self.colors(stm) {
+            let blockers = between(their_king, slider) & self.occupancies();
+            if blockers.popcount() == 1 {
+                discoverers |= blockers & self.colors(stm);
+            }
+        }
+        discoverers
+    }
+
     pub fn append_noisy_moves(&self, list: &mut MoveList) {
         self.generate_moves(list, MovegenKind::Noisy);
     }
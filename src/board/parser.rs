@@ -65,6 +65,10 @@ impl Board {
         Ok(board)
     }
 
+    /// Parses castling rights in standard, X-FEN or Shredder-FEN notation.
+    ///
+    /// In X-FEN `K` and `Q` refer to the outermost rook on the corresponding side
+    /// of the king, while a file letter names the castling rook explicitly.
     fn set_castling(&mut self, rights: &str) {
         for right in rights.chars() {
             if !matches!(right.to_ascii_uppercase(), 'A'..='H' | 'K' | 'Q') {
@@ -73,17 +77,19 @@ impl Board {
 
             let color = if right.is_uppercase() { Color::White } else { Color::Black };
             let king_from = self.king_square(color);
-            let mut search_step = right.to_ascii_uppercase() as i8 - b'A' as i8 - king_from.file() as i8;
+            let rooks = self.colored_pieces(color, PieceType::Rook);
 
-            if right.eq_ignore_ascii_case(&'K') {
-                search_step = Square::RIGHT;
-            }
-            if right.eq_ignore_ascii_case(&'Q') {
-                search_step = Square::LEFT;
+            let candidates = match right.to_ascii_uppercase() {
+                'K' => ray_pass(king_from, king_from.shift(Square::RIGHT)) & rooks,
+                'Q' => ray_pass(king_from, king_from.shift(Square::LEFT)) & rooks,
+                file => Square::from_rank_file(king_from.rank() as u8, file as u8 - b'A').to_bb() & rooks,
+            };
+
+            if candidates.is_empty() {
+                continue;
             }
 
-            let rook_from =
-                (ray_pass(king_from, king_from.shift(search_step)) & self.colored_pieces(color, PieceType::Rook)).lsb();
+            let rook_from = if right.eq_ignore_ascii_case(&'K') { candidates.msb() } else { candidates.lsb() };
 
             let king_side = (rook_from > king_from) as usize;
 
@@ -88,6 +88,32 @@ impl super::Board {
         stm != self.side_to_move()
     }
 
+    /// Returns the exact static exchange evaluation of a move, the largest threshold that passes
+    /// [`see`](Self::see). The result lies between losing the moving piece and keeping all that
+    /// the move wins, so a binary search over that range takes only a few threshold tests.
+    ///
+    /// Castling is valued at zero.
+    pub fn see_value(&self, mv: Move) -> i32 {
+        if mv.is_castling() {
+            return 0;
+        }
+
+        let moved = if mv.is_promotion() { mv.promo_piece_type().value() } else { self.piece_on(mv.from()).value() };
+        let mut low = self.move_value(mv) - moved;
+        let mut high = self.move_value(mv);
+
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            if self.see(mv, mid) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        low
+    }
+
     fn move_value(&self, mv: Move) -> i32 {
         let capture = self.type_on(mv.capture_sq());
         let mut value = capture.value();
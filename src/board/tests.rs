@@ -1,12 +1,15 @@
 use std::sync::Once;
 
-use super::{Board, NullBoardObserver};
-use crate::lookup;
+use super::{Board, DrawReason, NullBoardObserver};
+use crate::{
+    lookup,
+    types::{Color, MAX_PLY, MoveList, PieceType, parse_san, to_san},
+};
 
 static LUT_INITIALIZED: Once = Once::new();
 
 fn prepare_lut() {
-    LUT_INITIALIZED.call_once(|| lookup::initialize());
+    LUT_INITIALIZED.call_once(lookup::initialize);
 }
 
 macro_rules! assert_perft {
@@ -73,10 +76,29 @@ fn to_fen_emits_shredder_castling_for_frc() {
     }
 }
 
+#[test]
+fn xfen_castling_uses_outermost_rook() {
+    prepare_lut();
+
+    // With two rooks on the same side of the king, X-FEN `K`/`Q` refer to the outermost
+    // rook, while a Shredder file letter selects the named rook.
+    assert_eq!(frc_board("4k3/8/8/8/8/8/8/R2RK2R w KQ - 0 1").to_fen(), "4k3/8/8/8/8/8/8/R2RK2R w HA - 0 1");
+    assert_eq!(frc_board("4k3/8/8/8/8/8/8/R2RKR1R w FD - 0 1").to_fen(), "4k3/8/8/8/8/8/8/R2RKR1R w FD - 0 1");
+}
+
 fn assert_hash_consistent(board: &Board) {
     let mut recomputed = board.clone();
     recomputed.update_hash_keys();
     assert_eq!(board.hash(), recomputed.hash(), "incremental hash diverged from recomputation:\n{board}");
+    assert_eq!(board.pawn_key(), recomputed.pawn_key(), "incremental pawn key diverged from recomputation:\n{board}");
+
+    for color in [Color::White, Color::Black] {
+        assert_eq!(
+            board.non_pawn_key(color),
+            recomputed.non_pawn_key(color),
+            "incremental {color:?} non-pawn key diverged from recomputation:\n{board}"
+        );
+    }
 }
 
 fn hash_perft(board: &mut Board, depth: usize) {
@@ -138,3 +160,155 @@ fn halfmove_clock_saturates_instead_of_overflowing() {
     assert_eq!(board.fiftymove_clock(), 255);
     let _ = board.hash();
 }
+
+#[test]
+fn san_round_trips_for_all_legal_moves() {
+    prepare_lut();
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        for entry in board.generate_all_moves().iter() {
+            let san = to_san(&board, entry.mv);
+            assert_eq!(parse_san(&board, &san), Some(entry.mv), "{fen}: {san}");
+        }
+    }
+}
+
+#[test]
+fn quiet_checks_are_the_checking_quiet_moves() {
+    prepare_lut();
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1",
+        "7k/8/8/8/8/2P5/1B6/K7 w - - 0 1",
+        "7k/8/8/8/8/8/1K6/B7 w - - 0 1",
+        "3qk3/8/8/8/3B4/8/3P4/3K4 b - - 0 1",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        let mut checks = MoveList::new();
+        board.append_quiet_check_moves(&mut checks);
+
+        let mut expected = board
+            .generate_all_moves()
+            .iter()
+            .map(|entry| entry.mv)
+            .filter(|mv| mv.is_quiet() && !mv.is_castling() && !mv.is_promotion())
+            .filter(|&mv| {
+                let mut child = board.clone();
+                child.make_move(mv, &mut NullBoardObserver);
+                child.in_check()
+            })
+            .map(|mv| mv.to_uci(&board))
+            .collect::<Vec<_>>();
+        let mut checks = checks.iter().map(|entry| entry.mv.to_uci(&board)).collect::<Vec<_>>();
+
+        expected.sort();
+        checks.sort();
+        assert_eq!(checks, expected, "{fen}");
+    }
+}
+
+#[test]
+fn see_value_is_the_largest_passing_threshold() {
+    prepare_lut();
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        for entry in board.generate_all_moves().iter().filter(|entry| !entry.mv.is_castling()) {
+            let value = board.see_value(entry.mv);
+            assert!(
+                board.see(entry.mv, value) && !board.see(entry.mv, value + 1),
+                "{fen}: {}",
+                entry.mv.to_uci(&board)
+            );
+        }
+    }
+
+    // The rook wins an undefended pawn
+    let board = Board::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+    let mv = board.generate_all_moves().iter().map(|entry| entry.mv).find(|mv| mv.to_uci(&board) == "e1e5").unwrap();
+    assert_eq!(board.see_value(mv), PieceType::Pawn.value());
+}
+
+#[test]
+fn san_disambiguates_and_marks_checks() {
+    prepare_lut();
+
+    let san = |fen: &str, uci: &str| {
+        let board = Board::from_fen(fen).unwrap();
+        let mv = board.generate_all_moves().iter().map(|entry| entry.mv).find(|mv| mv.to_uci(&board) == uci).unwrap();
+        to_san(&board, mv)
+    };
+
+    let fen = "1k6/8/8/8/7R/2R5/8/K6R w - - 0 1";
+    assert_eq!(san(fen, "h4h3"), "R4h3");
+    assert_eq!(san(fen, "h1h3"), "R1h3");
+    assert_eq!(san(fen, "c3h3"), "Rch3");
+    assert_eq!(san(fen, "c3c4"), "Rcc4");
+    assert_eq!(san(fen, "c3c2"), "Rc2");
+    assert_eq!(san(fen, "h4h8"), "Rh8+");
+    assert_eq!(san("k7/8/1K6/8/8/8/8/7R w - - 0 1", "h1h8"), "Rh8#");
+    assert_eq!(san("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "e8c8"), "O-O-O");
+
+    let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(parse_san(&board, "b8=Q+").map(|mv| to_san(&board, mv)).as_deref(), Some("b8=Q+"));
+    assert_eq!(parse_san(&board, "b8N").map(|mv| to_san(&board, mv)).as_deref(), Some("b8=N"));
+}
+
+#[test]
+fn upcoming_repetition_detects_reversible_cycles() {
+    prepare_lut();
+
+    let mut board = Board::starting_position();
+    let play = |board: &mut Board, san: &str| {
+        let mv = parse_san(board, san).unwrap();
+        board.make_move(mv, &mut NullBoardObserver);
+    };
+
+    for san in ["Nf3", "Nf6", "Ng1"] {
+        play(&mut board, san);
+    }
+
+    // Ng8 repeats the starting position, which counts as a draw only inside the search tree
+    assert!(board.upcoming_repetition(4));
+    assert!(!board.upcoming_repetition(0));
+
+    // Pawn moves are irreversible
+    play(&mut board, "e5");
+    assert!(!board.upcoming_repetition(MAX_PLY));
+}
+
+#[test]
+fn draw_reasons_follow_the_rules() {
+    prepare_lut();
+
+    let reason = |fen: &str| Board::from_fen(fen).unwrap().draw_reason();
+
+    assert_eq!(reason("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), Some(DrawReason::Stalemate));
+    assert_eq!(reason("8/8/4k3/8/8/3NK3/8/8 w - - 0 1"), Some(DrawReason::InsufficientMaterial));
+    assert_eq!(reason("8/8/4k3/1b6/8/3BK3/8/8 w - - 0 1"), Some(DrawReason::InsufficientMaterial));
+    assert_eq!(reason("8/8/4k3/2b5/8/3BK3/8/8 w - - 0 1"), None);
+    assert_eq!(reason("8/8/4k3/8/8/2NNK3/8/8 w - - 0 1"), None);
+    assert_eq!(reason("8/8/4k3/8/8/3RK3/8/8 w - - 100 80"), Some(DrawReason::FiftyMoveRule));
+
+    let mut board = Board::starting_position();
+    for _ in 0..2 {
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+            assert_eq!(board.draw_reason(), None);
+            let mv = parse_san(&board, san).unwrap();
+            board.make_move(mv, &mut NullBoardObserver);
+        }
+    }
+    assert_eq!(board.draw_reason(), Some(DrawReason::ThreefoldRepetition));
+}
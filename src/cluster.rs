@@ -0,0 +1,322 @@
+//! Distributed search over TCP.
+//!
+//! A worker is started with `reckless "worker <address> [token]"` and serves one master at a
+//! time, speaking UCI over the connection. The master is configured with the `ClusterWorkers`
+//! option; on `go` it sends its position to every worker along with an infinite search, stops
+//! them as soon as its own search is done and plays the result of the deepest search.
+//!
+//! A worker only listens on the loopback interface unless it's given a host, and then requires
+//! masters to present the token with `auth <token>` first, set on them with `ClusterToken`. It
+//! only accepts the commands of the cluster protocol, and no options that concern its machine.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, TcpStream},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long to wait for the `bestmove` of the workers after telling them to stop.
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Options that only concern the machine they are set on, such as its resources and files, and
+/// so aren't forwarded to workers. Workers refuse them as well.
+const LOCAL_OPTIONS: [&str; 10] = [
+    "Threads",
+    "Hash",
+    "EvalCache",
+    "ThreadAffinity",
+    "ClusterWorkers",
+    "ClusterToken",
+    "Clear",
+    "OutputFormat",
+    "EvalFile",
+    "SyzygyPath",
+];
+
+/// Serves masters connecting on `address`, one after the other. A port alone listens on the
+/// loopback interface, and any other interface requires a token.
+#[cfg(unix)]
+pub fn serve(args: &str) {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    let (address, token) = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [address] => (*address, None),
+        [address, token] => (*address, Some(token.to_string())),
+        _ => {
+            eprintln!("Usage: worker <address> [token]");
+            return;
+        }
+    };
+
+    let address = if address.contains(':') { address.to_string() } else { format!("127.0.0.1:{address}") };
+
+    let listener = match std::net::TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on '{address}': {e}");
+            return;
+        }
+    };
+
+    if token.is_none() && listener.local_addr().is_ok_and(|local| !local.ip().is_loopback()) {
+        eprintln!("A token is required to listen on '{address}'");
+        return;
+    }
+
+    println!("info string Waiting for masters on {address}");
+
+    for stream in listener.incoming().filter_map(Result::ok) {
+        let peer = stream.peer_addr().map_or_else(|_| "unknown".to_string(), |peer| peer.to_string());
+        eprintln!("Serving master {peer}");
+
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for the two descriptors of the pipe.
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            eprintln!("Failed to create a pipe: {}", std::io::Error::last_os_error());
+            return;
+        }
+
+        let _ = std::io::stdout().flush();
+
+        // The regular UCI loop runs on the connection, with the commands of the master relayed
+        // through a pipe once they have been checked
+        // SAFETY: all descriptors are valid for the duration of the calls, and the write end of
+        // the pipe is owned by the relay from now on.
+        let pipe = unsafe {
+            libc::dup2(fds[0], libc::STDIN_FILENO);
+            libc::dup2(stream.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::close(fds[0]);
+            std::fs::File::from_raw_fd(fds[1])
+        };
+
+        let token = token.clone();
+        std::thread::spawn(move || relay(stream, pipe, token.as_deref()));
+
+        crate::uci::message_loop(Default::default());
+        eprintln!("Master {peer} disconnected");
+    }
+}
+
+#[cfg(not(unix))]
+pub fn serve(_: &str) {
+    eprintln!("Worker mode is only supported on Unix");
+}
+
+/// Passes the commands of a master on to the UCI loop until it disconnects, after it has
+/// authenticated when the worker has a token.
+#[cfg(unix)]
+fn relay(stream: TcpStream, mut pipe: std::fs::File, token: Option<&str>) {
+    let mut lines = BufReader::new(&stream).lines().map_while(Result::ok);
+
+    if let Some(token) = token {
+        let auth = lines.next();
+        if auth.as_deref().and_then(|line| line.trim().strip_prefix("auth ")) != Some(token) {
+            eprintln!("Master failed to authenticate");
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
+        }
+    }
+
+    for line in lines {
+        if !is_cluster_command(&line) {
+            eprintln!("Refused command from master: '{}'", line.trim());
+            continue;
+        }
+        if writeln!(pipe, "{line}").is_err() {
+            break;
+        }
+    }
+
+    // Closing the pipe ends the UCI loop
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Whether a worker accepts a command, which must be one sent by a master.
+fn is_cluster_command(line: &str) -> bool {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+    match tokens.as_slice() {
+        ["position" | "go" | "stop" | "ucinewgame" | "isready" | "quit", ..] => true,
+        ["setoption", "name", name, ..] => !LOCAL_OPTIONS.contains(name),
+        _ => false,
+    }
+}
+
+/// What a worker has reported for the current search.
+#[derive(Clone, Default)]
+pub struct RemoteResult {
+    pub depth: i32,
+    pub nodes: u64,
+    /// The last `info` line of the principal variation.
+    pub info: String,
+    /// The `bestmove` line, once the worker has stopped.
+    pub bestmove: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Cluster {
+    workers: Vec<Worker>,
+    /// The addresses of the workers, to connect again when the token changes.
+    addresses: String,
+    token: Option<String>,
+    position: String,
+    /// The forwarded options, replayed to workers that connect later.
+    options: Vec<String>,
+}
+
+struct Worker {
+    stream: TcpStream,
+    result: Arc<(Mutex<RemoteResult>, Condvar)>,
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Ends the session on the worker, which in turn ends the reader thread
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+impl Cluster {
+    /// Replaces the workers with a comma separated list of new ones, such as
+    /// "10.0.0.2:9000,10.0.0.3:9000", and brings them up to date with the options set so far.
+    pub fn connect(&mut self, addresses: &str) -> std::io::Result<()> {
+        let mut workers = Vec::new();
+        self.workers.clear();
+        self.addresses = addresses.to_string();
+
+        for address in addresses.split(',').map(str::trim).filter(|address| !address.is_empty()) {
+            let context = |e: std::io::Error| std::io::Error::new(e.kind(), format!("{address}: {e}"));
+
+            let stream = TcpStream::connect(address).map_err(context)?;
+            stream.set_nodelay(true).map_err(context)?;
+
+            let result = Arc::new((Mutex::new(RemoteResult::default()), Condvar::new()));
+            let reader = BufReader::new(stream.try_clone().map_err(context)?);
+
+            let shared = result.clone();
+            std::thread::spawn(move || {
+                for line in reader.lines().map_while(Result::ok) {
+                    update(&shared, &line);
+                }
+            });
+
+            if let Some(token) = &self.token {
+                writeln!(&stream, "auth {token}").map_err(context)?;
+            }
+
+            for option in &self.options {
+                writeln!(&stream, "{option}").map_err(context)?;
+            }
+
+            workers.push(Worker { stream, result });
+        }
+
+        self.workers = workers;
+        Ok(())
+    }
+
+    pub fn disconnect(&mut self) {
+        self.workers.clear();
+        self.addresses.clear();
+    }
+
+    /// Sets the token presented to the workers, connecting to them again if there are any.
+    pub fn set_token(&mut self, token: Option<String>) -> std::io::Result<()> {
+        self.token = token;
+
+        if self.addresses.is_empty() { Ok(()) } else { self.connect(&self.addresses.clone()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Remembers the `position` command to forward with the next search.
+    pub fn set_position(&mut self, command: &str) {
+        self.position = command.trim().to_string();
+    }
+
+    /// Forwards a `setoption` command, unless it only concerns the master.
+    pub fn forward_option(&mut self, command: &str) {
+        let tokens = command.split_whitespace().collect::<Vec<_>>();
+        let Some(value) = tokens.iter().position(|&token| token == "value") else {
+            return;
+        };
+
+        let name = tokens[..value].join(" ");
+        if tokens.get(2).is_some_and(|name| LOCAL_OPTIONS.contains(name)) {
+            return;
+        }
+
+        self.options.retain(|option| !option.starts_with(&format!("{name} ")));
+        self.options.push(tokens.join(" "));
+        self.send(&tokens.join(" "));
+    }
+
+    /// Sends a command to every worker.
+    pub fn send(&self, command: &str) {
+        for worker in &self.workers {
+            let _ = writeln!(&worker.stream, "{command}");
+        }
+    }
+
+    /// Starts an infinite search of the current position on every worker.
+    pub fn start(&self, search_moves: &[String]) {
+        let position = if self.position.is_empty() { "position startpos" } else { &self.position };
+
+        let mut go = "go infinite".to_string();
+        if !search_moves.is_empty() {
+            go = format!("{go} searchmoves {}", search_moves.join(" "));
+        }
+
+        for worker in &self.workers {
+            *worker.result.0.lock().unwrap() = RemoteResult::default();
+            let _ = writeln!(&worker.stream, "{position}\n{go}");
+        }
+    }
+
+    /// Stops the workers and collects their results, leaving out those that don't answer in time.
+    /// All workers share one deadline, so that hung workers don't add up.
+    pub fn stop(&self) -> Vec<RemoteResult> {
+        self.send("stop");
+
+        let deadline = Instant::now() + STOP_TIMEOUT;
+
+        self.workers
+            .iter()
+            .filter_map(|worker| {
+                let (lock, cvar) = &*worker.result;
+                let guard = lock.lock().unwrap();
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let (result, _) = cvar.wait_timeout_while(guard, timeout, |result| result.bestmove.is_none()).unwrap();
+                result.bestmove.is_some().then(|| result.clone())
+            })
+            .collect()
+    }
+}
+
+fn update(result: &(Mutex<RemoteResult>, Condvar), line: &str) {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let value = |name: &str| tokens.iter().position(|&token| token == name).and_then(|index| tokens.get(index + 1));
+
+    let (lock, cvar) = result;
+    let mut result = lock.lock().unwrap();
+
+    match tokens.as_slice() {
+        ["bestmove", ..] => {
+            result.bestmove = Some(line.trim().to_string());
+            cvar.notify_all();
+        }
+        ["info", ..] if tokens.contains(&"pv") && value("multipv").is_none_or(|&multipv| multipv == "1") => {
+            result.depth = value("depth").and_then(|depth| depth.parse().ok()).unwrap_or(result.depth);
+            result.nodes = value("nodes").and_then(|nodes| nodes.parse().ok()).unwrap_or(result.nodes);
+            result.info = line.trim().to_string();
+        }
+        _ => (),
+    }
+}
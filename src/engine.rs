@@ -0,0 +1,286 @@
+//! Library interface for embedding the engine in other programs.
+//!
+//! An [`Engine`] owns the search threads and the current position, and reports search
+//! progress through a callback receiving [`SearchInfo`] values instead of printing UCI
+//! lines. A [`SearchHandle`] can be sent to another thread to stop a search in progress.
+//!
+//! ```no_run
+//! use reckless::engine::{Engine, Limits};
+//!
+//! let mut engine = Engine::new();
+//! engine.set_position("startpos", &["e2e4", "e7e5"]).unwrap();
+//! engine.set_option("Hash", "64").unwrap();
+//!
+//! let result = engine.search(Limits::Depth(12), |info| println!("{}", info.to_uci()));
+//! println!("best move: {:?}", result.best_move);
+//! ```
+
+use std::{
+    fmt,
+    sync::{Arc, Once, atomic::Ordering},
+};
+
+pub use crate::{
+    board::DrawReason,
+    thread::{InfoScore, SearchInfo},
+    time::Limits,
+};
+use crate::{
+    board::{Board, NullBoardObserver},
+    numa::NumaConfig,
+    search::Report,
+    thread::{SharedContext, Status, UciWriter},
+    threadpool::ThreadPool,
+    time::TimeManager,
+    transposition::Replacement,
+    types::{Move, Score},
+};
+
+#[derive(Debug)]
+pub enum EngineError {
+    InvalidFen(String),
+    IllegalMove(String),
+    UnknownOption(String),
+    InvalidValue(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFen(fen) => write!(f, "invalid FEN: '{fen}'"),
+            Self::IllegalMove(mv) => write!(f, "illegal move: '{mv}'"),
+            Self::UnknownOption(name) => write!(f, "unknown option: '{name}'"),
+            Self::InvalidValue(value) => write!(f, "invalid value: '{value}'"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Outcome of a finished search.
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// Best move in UCI notation, or `None` if the position has no legal moves.
+    pub best_move: Option<String>,
+    pub ponder: Option<String>,
+    pub score: InfoScore,
+    pub depth: i32,
+    pub nodes: u64,
+}
+
+/// Stops the search of the engine it was created from, usable from any thread.
+#[derive(Clone)]
+pub struct SearchHandle {
+    shared: Arc<SharedContext>,
+}
+
+impl SearchHandle {
+    pub fn stop(&self) {
+        self.shared.status.set(Status::STOPPED);
+    }
+}
+
+pub struct Engine {
+    shared: Arc<SharedContext>,
+    threads: ThreadPool,
+    board: Board,
+    frc: bool,
+    multi_pv: usize,
+    move_overhead: u64,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        static INITIALIZE: Once = Once::new();
+        INITIALIZE.call_once(|| {
+            crate::lookup::initialize();
+            crate::nnue::initialize();
+        });
+
+        let shared = Arc::new(SharedContext::default());
+        let threads = ThreadPool::new(shared.clone());
+
+        Self {
+            shared,
+            threads,
+            board: Board::starting_position(),
+            frc: false,
+            multi_pv: 1,
+            move_overhead: 0,
+        }
+    }
+
+    pub fn handle(&self) -> SearchHandle {
+        SearchHandle { shared: self.shared.clone() }
+    }
+
+    /// Sets up the position from a FEN string, or `startpos`, followed by moves in UCI notation.
+    pub fn set_position(&mut self, fen: &str, moves: &[&str]) -> Result<(), EngineError> {
+        let mut board = match fen {
+            "startpos" => Board::starting_position(),
+            _ => Board::from_fen(fen).map_err(|_| EngineError::InvalidFen(fen.to_string()))?,
+        };
+        board.set_frc(self.frc);
+
+        for &uci_move in moves {
+            let mv = parse_move(&board, uci_move)?;
+            board.make_move(mv, &mut NullBoardObserver);
+        }
+
+        self.board = board;
+        Ok(())
+    }
+
+    /// Sets one of the options also available through the UCI `setoption` command.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        fn parse<T: std::str::FromStr>(value: &str) -> Result<T, EngineError> {
+            value.parse().map_err(|_| EngineError::InvalidValue(value.to_string()))
+        }
+
+        match name {
+            "Hash" => {
+                self.shared.tt.resize(parse::<usize>(value)?.max(1));
+                self.threads.clear_hash();
+            }
+            "Threads" => self.threads.set_count(parse::<usize>(value)?.max(1)),
+            "EvalCache" => {
+                self.shared.eval_cache_size.store(parse::<usize>(value)?.min(1024), Ordering::Relaxed);
+                self.threads.set_count(self.threads.len());
+            }
+            "MultiPV" => self.multi_pv = parse::<usize>(value)?.max(1),
+            "MoveOverhead" => self.move_overhead = parse(value)?,
+            "Contempt" => self.shared.contempt.store(parse::<i32>(value)?.clamp(-100, 100), Ordering::Relaxed),
+            "CurrmoveDelay" => self.shared.currmove_delay.store(parse(value)?, Ordering::Relaxed),
+            "UCI_ShowWDL" => self.shared.show_wdl.store(parse(value)?, Ordering::Relaxed),
+            "TTReplacement" => self.shared.tt.set_replacement(
+                Replacement::from_name(value).ok_or_else(|| EngineError::InvalidValue(value.to_string()))?,
+            ),
+            "TTBucketSize" => match parse(value)? {
+                ways @ 1..=3 => {
+                    self.shared.tt.set_bucket_size(ways);
+                    self.threads.clear_hash();
+                }
+                _ => return Err(EngineError::InvalidValue(value.to_string())),
+            },
+            "ABDADA" => self.shared.abdada.store(parse(value)?, Ordering::Relaxed),
+            "LazyEval" => self.shared.lazy_eval.store(parse(value)?, Ordering::Relaxed),
+            "UCI_Chess960" => {
+                self.frc = parse(value)?;
+                self.board.set_frc(self.frc);
+            }
+            "ThreadAffinity" => {
+                let cfg = match value {
+                    "auto" => NumaConfig::from_system(),
+                    _ => NumaConfig::from_system()
+                        .with_affinity(value)
+                        .ok_or_else(|| EngineError::InvalidValue(value.to_string()))?,
+                };
+                self.shared.numa_context.set_numa_config(cfg);
+                self.threads.set_count(self.threads.len());
+            }
+            "Clear Hash" => self.threads.clear_hash(),
+            _ => return Err(EngineError::UnknownOption(name.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the static exchange evaluation of a move in UCI notation in the current position: the
+    /// material the side to move expects to gain from the sequence of captures it starts.
+    pub fn see(&self, uci_move: &str) -> Result<i32, EngineError> {
+        Ok(self.board.see_value(parse_move(&self.board, uci_move)?))
+    }
+
+    /// Returns why the current position is drawn under the rules of chess, if it is.
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        self.board.draw_reason()
+    }
+
+    /// Forgets everything learned from previous searches, as between games.
+    pub fn new_game(&mut self) {
+        self.threads.clear();
+        self.threads.clear_hash();
+    }
+
+    /// Searches the current position until the limits are reached or the search is stopped
+    /// through a [`SearchHandle`], calling `on_info` with the progress of each iteration.
+    pub fn search<F>(&mut self, limits: Limits, on_info: F) -> SearchResult
+    where
+        F: FnMut(&SearchInfo) + Send + 'static,
+    {
+        let time_manager = TimeManager::new(limits, self.board.fullmove_number(), self.move_overhead);
+
+        let writer = std::mem::replace(&mut self.threads.main_thread().writer, Box::new(CallbackWriter(on_info)));
+        self.threads.execute_searches(time_manager, Report::Full, self.multi_pv, &self.board, &[], &self.shared);
+        self.threads.main_thread().writer = writer;
+
+        let nodes = self.shared.nodes.aggregate();
+
+        if self.threads[0].root_moves.is_empty() {
+            let score = InfoScore::new(if self.board.in_check() { -Score::MATE } else { 0 }, &self.board);
+            return SearchResult { best_move: None, ponder: None, score, depth: 0, nodes };
+        }
+
+        let td = &self.threads[self.threads.best_thread()];
+        let root_move = &td.root_moves[0];
+        let ponder = root_move.pv.line().first().map(|mv| mv.to_uci(&self.board));
+
+        SearchResult {
+            best_move: Some(root_move.mv.to_uci(&self.board)),
+            ponder,
+            score: InfoScore::new(root_move.score, &self.board),
+            depth: td.completed_depth,
+            nodes,
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_move(board: &Board, uci_move: &str) -> Result<Move, EngineError> {
+    board
+        .generate_all_moves()
+        .iter()
+        .map(|entry| entry.mv)
+        .find(|mv| mv.to_uci(board) == uci_move)
+        .ok_or_else(|| EngineError::IllegalMove(uci_move.to_string()))
+}
+
+struct CallbackWriter<F>(F);
+
+impl<F: FnMut(&SearchInfo) + Send> UciWriter for CallbackWriter<F> {
+    fn write_line(&mut self, _: &str) {}
+
+    fn write_info(&mut self, info: &SearchInfo) {
+        (self.0)(info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn search_reports_progress_and_best_move() {
+        let mut engine = Engine::new();
+        engine.set_position("startpos", &["e2e4", "e7e5"]).unwrap();
+        assert!(engine.set_position("startpos", &["e2e5"]).is_err());
+        assert!(engine.set_option("Hash", "abc").is_err());
+
+        let depths = Arc::new(Mutex::new(Vec::new()));
+        let result = engine.search(Limits::Depth(4), {
+            let depths = depths.clone();
+            move |info| depths.lock().unwrap().push(info.depth)
+        });
+
+        assert!(result.best_move.is_some());
+        assert_eq!(result.depth, 4);
+        assert_eq!(depths.lock().unwrap().last(), Some(&4));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{thread::ThreadData, types::Score};
+use crate::{
+    board::Board,
+    thread::ThreadData,
+    types::{Color, PieceType, Score, Square},
+};
 
 pub fn correct_eval(td: &ThreadData, raw_eval: i32, correction_value: i32) -> i32 {
     let mut eval = (raw_eval * (21032 + td.board.material())
@@ -11,3 +15,165 @@ pub fn correct_eval(td: &ThreadData, raw_eval: i32, correction_value: i32) -> i3
 
     eval.clamp(-Score::TB_WIN_IN_MAX + 1, Score::TB_WIN_IN_MAX - 1)
 }
+
+const PIECE_TYPES: [PieceType; 5] =
+    [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen];
+
+/// Material balance from the side to move's point of view, a cheap stand-in for the network in
+/// positions so lopsided that its exact verdict doesn't matter.
+pub fn fast_eval(board: &Board) -> i32 {
+    let stm = board.side_to_move();
+
+    PIECE_TYPES
+        .into_iter()
+        .map(|pt| {
+            let balance =
+                board.colored_pieces(stm, pt).popcount() as i32 - board.colored_pieces(!stm, pt).popcount() as i32;
+            balance * pt.value()
+        })
+        .sum()
+}
+
+/// Handcrafted evaluation from material and piece-square tables, used when no network is available so
+/// that the engine still plays sensibly. Score from the side to move's point of view.
+pub fn handcrafted_eval(board: &Board) -> i32 {
+    const MAX_PHASE: i32 = 24;
+
+    let phase = (board.pieces(PieceType::Knight).popcount()
+        + board.pieces(PieceType::Bishop).popcount()
+        + 2 * board.pieces(PieceType::Rook).popcount()
+        + 4 * board.pieces(PieceType::Queen).popcount()) as i32;
+    let phase = phase.min(MAX_PHASE);
+
+    let mut positional = 0;
+
+    for color in [Color::White, Color::Black] {
+        let sign = if color == board.side_to_move() { 1 } else { -1 };
+        let index = |sq: Square| sq.relative_to(color) as usize ^ 56;
+
+        for (pt, table) in PIECE_TYPES.into_iter().zip(&PSQT) {
+            for sq in board.colored_pieces(color, pt) {
+                positional += sign * table[index(sq)];
+            }
+        }
+
+        let king = index(board.king_square(color));
+        positional += sign * (KING_MIDGAME[king] * phase + KING_ENDGAME[king] * (MAX_PHASE - phase)) / MAX_PHASE;
+    }
+
+    fast_eval(board) + positional
+}
+
+// Piece-square tables of the simplified evaluation function, from White's point of view with the eighth rank first
+
+#[rustfmt::skip]
+const PSQT: [[i32; 64]; 5] = [
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         50,  50,  50,  50,  50,  50,  50,  50,
+         10,  10,  20,  30,  30,  20,  10,  10,
+          5,   5,  10,  25,  25,  10,   5,   5,
+          0,   0,   0,  20,  20,   0,   0,   0,
+          5,  -5, -10,   0,   0, -10,  -5,   5,
+          5,  10,  10, -20, -20,  10,  10,   5,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [
+        -50, -40, -30, -30, -30, -30, -40, -50,
+        -40, -20,   0,   0,   0,   0, -20, -40,
+        -30,   0,  10,  15,  15,  10,   0, -30,
+        -30,   5,  15,  20,  20,  15,   5, -30,
+        -30,   0,  15,  20,  20,  15,   0, -30,
+        -30,   5,  10,  15,  15,  10,   5, -30,
+        -40, -20,   0,   5,   5,   0, -20, -40,
+        -50, -40, -30, -30, -30, -30, -40, -50,
+    ],
+    [
+        -20, -10, -10, -10, -10, -10, -10, -20,
+        -10,   0,   0,   0,   0,   0,   0, -10,
+        -10,   0,   5,  10,  10,   5,   0, -10,
+        -10,   5,   5,  10,  10,   5,   5, -10,
+        -10,   0,  10,  10,  10,  10,   0, -10,
+        -10,  10,  10,  10,  10,  10,  10, -10,
+        -10,   5,   0,   0,   0,   0,   5, -10,
+        -20, -10, -10, -10, -10, -10, -10, -20,
+    ],
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+          5,  10,  10,  10,  10,  10,  10,   5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+         -5,   0,   0,   0,   0,   0,   0,  -5,
+          0,   0,   0,   5,   5,   0,   0,   0,
+    ],
+    [
+        -20, -10, -10,  -5,  -5, -10, -10, -20,
+        -10,   0,   0,   0,   0,   0,   0, -10,
+        -10,   0,   5,   5,   5,   5,   0, -10,
+         -5,   0,   5,   5,   5,   5,   0,  -5,
+          0,   0,   5,   5,   5,   5,   0,  -5,
+        -10,   5,   5,   5,   5,   5,   0, -10,
+        -10,   0,   5,   0,   0,   0,   0, -10,
+        -20, -10, -10,  -5,  -5, -10, -10, -20,
+    ],
+];
+
+#[rustfmt::skip]
+const KING_MIDGAME: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// Per-thread cache of raw network evaluations, so that positions evaluated again without a usable
+/// TT entry skip the forward pass. Each entry packs the upper 48 bits of the key with the evaluation.
+pub struct EvalCache {
+    entries: Box<[u64]>,
+}
+
+impl EvalCache {
+    /// Default size of the cache of each thread, in MB. It's off until it's shown to gain.
+    pub const DEFAULT_SIZE: usize = 0;
+
+    const EVAL_MASK: u64 = 0xFFFF;
+
+    pub fn new(megabytes: usize) -> Self {
+        let len = megabytes * 1024 * 1024 / std::mem::size_of::<u64>();
+        Self { entries: vec![0; len].into_boxed_slice() }
+    }
+
+    pub fn get(&self, hash: u64) -> Option<i32> {
+        let entry = *self.entries.get(self.index(hash))?;
+        (entry != 0 && (entry ^ hash) & !Self::EVAL_MASK == 0).then_some(entry as u16 as i16 as i32)
+    }
+
+    pub fn insert(&mut self, hash: u64, eval: i32) {
+        let index = self.index(hash);
+        if let Some(entry) = self.entries.get_mut(index) {
+            *entry = (hash & !Self::EVAL_MASK) | eval as i16 as u16 as u64;
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (((hash as u128) * (self.entries.len() as u128)) >> 64) as usize
+    }
+}
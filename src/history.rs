@@ -1,6 +1,9 @@
 use std::sync::atomic::{AtomicI16, Ordering};
 
-use crate::types::{Bitboard, Color, Move, Piece, PieceType, Square};
+use crate::{
+    misc::prefetch,
+    types::{Bitboard, Color, Move, Piece, PieceType, Square},
+};
 
 type FromToHistory<T> = [[T; 64]; 64];
 type PieceToHistory<T> = [[T; 64]; 13];
@@ -105,6 +108,38 @@ impl Default for QuietHistory {
     }
 }
 
+/// Quiet history of the first few plies, kept for a single search so that the ordering near the
+/// root settles across iterations without being diluted by the rest of the tree.
+pub struct LowPlyHistory {
+    // [ply][from][to]
+    entries: Box<[FromToHistory<i16>; Self::PLIES as usize]>,
+}
+
+impl LowPlyHistory {
+    const MAX_HISTORY: i32 = 8192;
+
+    pub const PLIES: isize = 4;
+
+    pub fn get(&self, ply: isize, mv: Move) -> i32 {
+        self.entries[ply as usize][mv.from()][mv.to()] as i32
+    }
+
+    pub fn update(&mut self, ply: isize, mv: Move, bonus: i32) {
+        let entry = &mut self.entries[ply as usize][mv.from()][mv.to()];
+        apply_bonus::<{ Self::MAX_HISTORY }>(entry, bonus);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().flatten().flatten().for_each(|entry| *entry = 0);
+    }
+}
+
+impl Default for LowPlyHistory {
+    fn default() -> Self {
+        Self { entries: zeroed_box() }
+    }
+}
+
 pub struct PawnHistory {
     // [pawn_key_bucket][piece][to]
     entries: Box<[PieceToHistory<i16>; Self::SIZE]>,
@@ -132,6 +167,27 @@ impl Default for PawnHistory {
     }
 }
 
+pub struct CounterMoves {
+    // [previous_piece][previous_to]
+    entries: Box<PieceToHistory<Move>>,
+}
+
+impl CounterMoves {
+    pub fn get(&self, piece: Piece, to: Square) -> Move {
+        self.entries[piece][to]
+    }
+
+    pub fn set(&mut self, piece: Piece, to: Square, mv: Move) {
+        self.entries[piece][to] = mv;
+    }
+}
+
+impl Default for CounterMoves {
+    fn default() -> Self {
+        Self { entries: zeroed_box() }
+    }
+}
+
 pub struct NoisyHistory {
     // [piece][to][captured_piece_type][to_threatened]
     entries: Box<PieceToHistory<[[i16; 2]; 7]>>,
@@ -177,6 +233,10 @@ impl CorrectionHistory {
         self.entries[bucket][stm][key as usize & Self::MASK].store(new as i16, Ordering::Relaxed);
     }
 
+    pub fn prefetch(&self, stm: Color, key: u64, bucket: usize) {
+        prefetch(&self.entries[bucket][stm][key as usize & Self::MASK]);
+    }
+
     pub fn clear(&self) {
         for bucket in self.entries.iter() {
             for entries in bucket.iter() {
@@ -12,9 +12,14 @@ mod misc;
 mod movepick;
 mod nnue;
 mod numa;
+mod opponent;
 mod parameters;
+mod rng;
 mod search;
 mod setwise;
+mod skill;
+#[cfg(feature = "stats")]
+mod stats;
 mod stack;
 mod thread;
 mod threadpool;
@@ -24,12 +29,21 @@ mod types;
 
 mod tools;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod cluster;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod engine;
 #[cfg(not(target_arch = "wasm32"))]
 mod uci;
+#[cfg(not(target_arch = "wasm32"))]
+mod xboard;
 
 #[cfg(feature = "syzygy")]
 mod tb;
 
+#[cfg(feature = "tree-trace")]
+mod trace;
+
 #[cfg(feature = "syzygy")]
 #[allow(warnings)]
 mod bindings;
@@ -38,8 +52,20 @@ mod bindings;
 pub mod wasm;
 
 #[cfg(not(target_arch = "wasm32"))]
-pub fn run(buffer: std::collections::VecDeque<String>) {
+pub fn run(mut buffer: std::collections::VecDeque<String>) {
+    // `--seed <n>` comes before the commands, and makes the runs reproducible
+    if buffer.front().is_some_and(|arg| arg == "--seed") {
+        buffer.pop_front();
+        match buffer.pop_front().and_then(|seed| seed.parse().ok()) {
+            Some(seed) => rng::set_seed(seed),
+            None => eprintln!("Usage: --seed <n>"),
+        }
+    }
+
     lookup::initialize();
     nnue::initialize();
+    if !nnue::is_available() {
+        println!("info string No network is embedded, using the handcrafted evaluation until one is loaded");
+    }
     uci::message_loop(buffer);
 }
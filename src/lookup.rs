@@ -6,10 +6,12 @@ static mut CUCKOO: [u64; 0x2000] = [0; 0x2000];
 static mut A: [Square; 0x2000] = [Square::None; 0x2000];
 static mut B: [Square; 0x2000] = [Square::None; 0x2000];
 
+static INITIALIZED: std::sync::Once = std::sync::Once::new();
+
+/// Fills the cuckoo tables. Inserting the same moves twice would never terminate, so
+/// repeated calls are ignored.
 pub fn initialize() {
-    unsafe {
-        init_cuckoo();
-    }
+    INITIALIZED.call_once(|| unsafe { init_cuckoo() });
 }
 
 unsafe fn init_cuckoo() {
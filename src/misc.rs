@@ -145,3 +145,24 @@ pub fn dbg_print() {
         slot.reset();
     }
 }
+
+/// Hints the processor to load the cache line holding `ptr`, so that a later access doesn't stall.
+pub fn prefetch<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast());
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        // Nightly only equivalent:
+        // std::arch::aarch64::_prefetch::<_PREFETCH_READ, _PREFETCH_LOCALITY3>(ptr)
+        std::arch::asm!("prfm pldl1keep, [{ptr}]", ptr = in(reg) ptr, options(nostack, preserves_flags));
+    }
+
+    // No prefetching for other architectures
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = ptr;
+}
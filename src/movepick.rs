@@ -1,5 +1,7 @@
 use crate::{
+    history::LowPlyHistory,
     lookup::king_attacks,
+    parameters::*,
     search::NodeType,
     setwise::{bishop_attacks_setwise, knight_attacks_setwise, pawn_attacks_setwise, rook_attacks_setwise},
     thread::ThreadData,
@@ -9,15 +11,20 @@ use crate::{
 #[derive(Copy, Clone, Eq, PartialEq, PartialOrd)]
 pub enum Stage {
     HashMove,
+    Evasion,
     GenerateNoisy,
     GoodNoisy,
+    CounterMove,
     Quiet,
+    QuietCheck,
     BadNoisy,
 }
 
 pub struct MovePicker {
     list: MoveList,
     tt_move: Move,
+    counter_move: Move,
+    quiet_checks: bool,
     threshold: Option<i32>,
     stage: Stage,
     bad_noisy: ArrayVec<Move, MAX_MOVES>,
@@ -30,6 +37,8 @@ impl MovePicker {
         Self {
             list: MoveList::new(),
             tt_move,
+            counter_move: Move::NULL,
+            quiet_checks: false,
             threshold,
             stage: if tt_move.is_present() { Stage::HashMove } else { Stage::GenerateNoisy },
             bad_noisy: ArrayVec::new(),
@@ -38,6 +47,18 @@ impl MovePicker {
         }
     }
 
+    /// Tries `counter_move`, the quiet reply that last refuted the previous move, before the other quiets.
+    pub const fn with_counter_move(mut self, counter_move: Move) -> Self {
+        self.counter_move = counter_move;
+        self
+    }
+
+    /// Tries the quiet moves giving check after the good noisy moves, when quiets are skipped.
+    pub const fn with_quiet_checks(mut self) -> Self {
+        self.quiet_checks = true;
+        self
+    }
+
     pub const fn stage(&self) -> Stage {
         self.stage
     }
@@ -51,6 +72,26 @@ impl MovePicker {
             }
         }
 
+        if self.stage == Stage::GenerateNoisy && evasion_stage() != 0 && td.board.in_check() && self.threshold.is_none()
+        {
+            self.stage = Stage::Evasion;
+            td.board.append_all_moves(&mut self.list);
+            self.remove_tt();
+            self.score_evasions(td, ply);
+        }
+
+        if self.stage == Stage::Evasion {
+            // Once quiets are skipped, only the remaining captures are tried
+            while !self.list.is_empty() {
+                let entry = self.get_best_entry();
+                if !(skip_quiets && entry.mv.is_quiet()) {
+                    return Some(entry.mv);
+                }
+            }
+
+            return None;
+        }
+
         if self.stage == Stage::GenerateNoisy {
             self.stage = Stage::GoodNoisy;
             td.board.append_noisy_moves(&mut self.list);
@@ -59,10 +100,13 @@ impl MovePicker {
         }
 
         if self.stage == Stage::GoodNoisy {
-            while !self.list.is_empty() {
-                let entry = self.get_best_entry();
+            while let Some(entry) = self.list.pop() {
                 let threshold = self.threshold.unwrap_or_else(|| {
-                    if self.tt_move.is_quiet() && self.noisy_count > 2 { 1 } else { -entry.score / 47 + 116 }
+                    if self.tt_move.is_quiet() && self.noisy_count > 2 {
+                        1
+                    } else {
+                        -entry.score / good_noisy_see_divisor() + good_noisy_see_offset()
+                    }
                 });
                 if !td.board.see(entry.mv, threshold) {
                     self.bad_noisy.push(entry.mv);
@@ -77,16 +121,34 @@ impl MovePicker {
                 return Some(entry.mv);
             }
 
-            if skip_quiets {
-                self.stage = Stage::BadNoisy;
-            } else {
-                self.stage = Stage::Quiet;
-                td.board.append_quiet_moves(&mut self.list);
-                self.remove_tt();
+            self.stage = match skip_quiets {
+                true if self.quiet_checks => Stage::QuietCheck,
+                true => Stage::BadNoisy,
+                false => Stage::CounterMove,
+            };
+
+            if self.stage == Stage::QuietCheck {
+                td.board.append_quiet_check_moves(&mut self.list);
                 self.score_quiet(td, ply);
             }
         }
 
+        if self.stage == Stage::CounterMove {
+            self.stage = Stage::Quiet;
+            td.board.append_quiet_moves(&mut self.list);
+            self.remove_tt();
+
+            let counter_move = match self.counter_move {
+                mv if mv.is_present() => self.list.iter().position(|&e| e.mv == mv).map(|pos| self.list.remove(pos)),
+                _ => None,
+            };
+            self.score_quiet(td, ply);
+
+            if let Some(entry) = counter_move {
+                return Some(entry.mv);
+            }
+        }
+
         if self.stage == Stage::Quiet {
             if !skip_quiets && !self.list.is_empty() {
                 if NODE::ROOT {
@@ -98,7 +160,16 @@ impl MovePicker {
             self.stage = Stage::BadNoisy;
         }
 
+        if self.stage == Stage::QuietCheck {
+            if !self.list.is_empty() {
+                return Some(self.get_best_entry().mv);
+            }
+
+            self.stage = Stage::BadNoisy;
+        }
+
         // Stage::BadNoisy
+        // These were set aside in selection order, so they come out sorted by capture history and MVV
         if self.bad_noisy_idx < self.bad_noisy.len() {
             let mv = self.bad_noisy[self.bad_noisy_idx];
             self.bad_noisy_idx += 1;
@@ -109,16 +180,7 @@ impl MovePicker {
     }
 
     fn get_best_entry(&mut self) -> MoveEntry {
-        let mut best_index = 0;
-        let mut best_score = i32::MIN;
-
-        for (index, entry) in self.list.iter().enumerate() {
-            if entry.score >= best_score {
-                best_index = index;
-                best_score = entry.score;
-            }
-        }
-        self.list.remove(best_index)
+        self.list.remove(self.list.best_index())
     }
 
     fn remove_tt(&mut self) {
@@ -135,11 +197,39 @@ impl MovePicker {
             let captured = td.board.type_on(mv.capture_sq());
             let pt = td.board.type_on(mv.from());
 
-            entry.score = 14232 * captured.value() / 1024
+            entry.score = noisy_capture_value() * captured.value() / 1024
                 + td.noisy_history.get(threats, td.board.moved_piece(mv), mv.to(), captured)
-                + 4558 * (mv.is_promotion() && mv.promo_piece_type() == PieceType::Queen) as i32
+                + noisy_queen_promotion() * (mv.is_promotion() && mv.promo_piece_type() == PieceType::Queen) as i32
                 + (200000 - 20000 * pt as i32) * td.board.in_check() as i32;
         }
+
+        // Noisy moves are taken off the end of the list from now on
+        self.list.sort_by_score();
+    }
+
+    /// Orders the check evasions as winning captures, then quiets by history, then losing captures.
+    fn score_evasions(&mut self, td: &ThreadData, ply: isize) {
+        const CAPTURE_OFFSET: i32 = 1 << 20;
+
+        let threats = td.board.all_threats();
+        let side = td.board.side_to_move();
+
+        for entry in self.list.iter_mut() {
+            let mv = entry.mv;
+
+            entry.score = if mv.is_noisy() {
+                let captured = td.board.type_on(mv.capture_sq());
+                let offset = if td.board.see(mv, 0) { CAPTURE_OFFSET } else { -CAPTURE_OFFSET };
+
+                offset
+                    + noisy_capture_value() * captured.value() / 1024
+                    + td.noisy_history.get(threats, td.board.moved_piece(mv), mv.to(), captured)
+            } else {
+                quiet_history_weight() * td.quiet_history.get(threats, side, mv) / 1024
+                    + conthist1_weight() * td.conthist(ply, 1, mv) / 1024
+                    + conthist2_weight() * td.conthist(ply, 2, mv) / 1024
+            };
+        }
     }
 
     fn score_quiet(&mut self, td: &ThreadData, ply: isize) {
@@ -196,21 +286,30 @@ impl MovePicker {
             Bitboard(0)
         };
 
+        let low_ply = |mv| {
+            if low_ply_history_weight() != 0 && ply < LowPlyHistory::PLIES {
+                low_ply_history_weight() * td.low_ply_history.get(ply, mv) / (1024 * (1 + ply as i32))
+            } else {
+                0
+            }
+        };
+
         for entry in self.list.iter_mut() {
             let mv = entry.mv;
             let pt = td.board.type_on(mv.from());
 
-            entry.score = 1763 * td.quiet_history.get(threats, side, mv) / 1024
-                + 1024 * td.pawn_history.get(pawn_key, td.board.moved_piece(mv), mv.to()) / 1024
-                + 1614 * td.conthist(ply, 1, mv) / 1024
-                + 1066 * td.conthist(ply, 2, mv) / 1024
-                + 1086 * td.conthist(ply, 4, mv) / 1024
-                + 1051 * td.conthist(ply, 6, mv) / 1024
+            entry.score = low_ply(mv)
+                + quiet_history_weight() * td.quiet_history.get(threats, side, mv) / 1024
+                + pawn_history_weight() * td.pawn_history.get(pawn_key, td.board.moved_piece(mv), mv.to()) / 1024
+                + conthist1_weight() * td.conthist(ply, 1, mv) / 1024
+                + conthist2_weight() * td.conthist(ply, 2, mv) / 1024
+                + conthist4_weight() * td.conthist(ply, 4, mv) / 1024
+                + conthist6_weight() * td.conthist(ply, 6, mv) / 1024
                 + escape[pt] * threatened[pt].contains(mv.from()) as i32
-                + 10723 * td.board.checking_squares(pt).contains(mv.to()) as i32
-                - 8875 * threatened[pt].contains(mv.to()) as i32
-                + 3446 * offense[pt].contains(mv.to()) as i32
-                - 4494 * wall_pawns.contains(mv.from()) as i32;
+                + quiet_check_bonus() * td.board.checking_squares(pt).contains(mv.to()) as i32
+                - quiet_threatened_malus() * threatened[pt].contains(mv.to()) as i32
+                + quiet_offense_bonus() * offense[pt].contains(mv.to()) as i32
+                - quiet_wall_pawn_malus() * wall_pawns.contains(mv.from()) as i32;
         }
     }
 }
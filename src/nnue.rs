@@ -1,11 +1,14 @@
 mod accumulator;
+mod embedded;
+mod reference;
 
 pub use accumulator::threats::initialize;
 
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use crate::{
     board::{Board, BoardObserver},
+    evaluation::handcrafted_eval,
     nnue::accumulator::{
         AccumulatorCache, PstAccumulator, ThreatAccumulator,
         threats::{push_threats_on_change, push_threats_on_move, push_threats_on_mutate},
@@ -15,6 +18,9 @@ use crate::{
 };
 
 mod forward {
+    mod batch;
+    pub use batch::*;
+
     #[cfg(any(
         target_feature = "avx2",
         target_feature = "neon",
@@ -82,11 +88,16 @@ mod simd {
 const NETWORK_SCALE: i32 = 380;
 
 const INPUT_BUCKETS: usize = 10;
+const THREAT_INPUTS: usize = 66864;
 const OUTPUT_BUCKETS: usize = 8;
 
-const L1_SIZE: usize = 768;
-const L2_SIZE: usize = 16;
-const L3_SIZE: usize = 32;
+// The hidden layer sizes default to those of the embedded network and can be changed at build time through
+// the `L1_SIZE`, `L2_SIZE` and `L3_SIZE` environment variables, together with an `EVALFILE` to match
+pub const L1_SIZE: usize = parse_size(env!("NNUE_L1_SIZE"));
+const L2_SIZE: usize = parse_size(env!("NNUE_L2_SIZE"));
+const L3_SIZE: usize = parse_size(env!("NNUE_L3_SIZE"));
+
+const _: () = assert!(L1_SIZE.is_multiple_of(64) && L2_SIZE.is_multiple_of(16) && L3_SIZE.is_multiple_of(16));
 
 const FT_QUANT: i32 = 255;
 const L1_QUANT: i32 = 64;
@@ -98,6 +109,13 @@ const FT_SHIFT: i32 = 9;
 
 const DEQUANT_MULTIPLIER: f32 = (1 << FT_SHIFT) as f32 / (FT_QUANT * FT_QUANT * L1_QUANT) as f32;
 
+const fn parse_size(text: &str) -> usize {
+    match usize::from_str_radix(text, 10) {
+        Ok(size) => size,
+        Err(_) => panic!("Invalid network layer size"),
+    }
+}
+
 #[rustfmt::skip]
 const INPUT_BUCKETS_LAYOUT: [u8; 64] = [
     0, 1, 2, 3, 3, 2, 1, 0,
@@ -129,9 +147,23 @@ struct SparseEntry {
     count: usize,
 }
 
+/// Components of a static evaluation, all scores from the side to move's point of view.
+#[derive(Clone)]
+pub struct EvalBreakdown {
+    pub eval: i32,
+    pub bucket: usize,
+    /// Non-zero feature transformer outputs, out of `L1_SIZE`.
+    pub active: usize,
+    /// Non-zero chunks of four outputs fed to the sparse L1 product, out of `L1_SIZE / 4`.
+    pub chunks: usize,
+    /// Evaluation with the threat inputs removed, leaving only the piece-square features.
+    pub without_threats: i32,
+}
+
 #[derive(Clone)]
 pub struct Network {
     parameters: Arc<ParametersHandle>,
+    handcrafted: bool,
     index: usize,
     pst_stack: Box<[PstAccumulator]>,
     threat_stack: Box<[ThreatAccumulator]>,
@@ -158,6 +190,7 @@ impl Network {
 
         Self {
             parameters: parameters.clone(),
+            handcrafted: !is_available(),
             index: 0,
             pst_stack: vec![PstAccumulator::new(&parameters); MAX_PLY].into_boxed_slice(),
             threat_stack: vec![ThreatAccumulator::new(); MAX_PLY].into_boxed_slice(),
@@ -195,6 +228,10 @@ impl Network {
     }
 
     pub fn evaluate(&mut self, board: &Board) -> i32 {
+        if self.handcrafted {
+            return handcrafted_eval(board);
+        }
+
         debug_assert!(self.pst_stack[0].accurate == [true; 2]);
         debug_assert!(self.threat_stack[0].accurate == [true; 2]);
 
@@ -317,6 +354,72 @@ impl Network {
         }
     }
 
+    /// Evaluates the position with the floating point reference implementation of the network.
+    pub fn evaluate_reference(&self, board: &Board) -> f32 {
+        reference::evaluate(board, self.parameters.as_ref())
+    }
+
+    /// Win, draw and loss probabilities in permille for the side to move, predicted by the WDL head
+    /// of the network. Uses the floating point reference implementation, so it is meant for reporting
+    /// rather than for the search itself.
+    pub fn evaluate_wdl(&self, board: &Board) -> Option<(i32, i32, i32)> {
+        let head = EXTERNAL_WDL.read().unwrap().clone()?;
+        let logits = reference::evaluate_wdl(board, self.parameters.as_ref(), &head);
+
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exp = logits.map(|logit| (logit - max).exp());
+        let sum = exp.iter().sum::<f32>();
+
+        let win = (1000.0 * exp[0] / sum).round() as i32;
+        let loss = (1000.0 * exp[2] / sum).round() as i32;
+        Some((win, 1000 - win - loss, loss))
+    }
+
+    /// Evaluates several positions at once, returning the same scores as `evaluate` would.
+    pub fn evaluate_batch(&mut self, boards: &[Board]) -> Vec<i32> {
+        let mut ft_outs = Vec::with_capacity(boards.len());
+        let mut buckets = Vec::with_capacity(boards.len());
+
+        for board in boards {
+            self.full_refresh(board);
+
+            let pst = &self.pst_stack[self.index];
+            let threat = &self.threat_stack[self.index];
+            ft_outs.push(unsafe { forward::activate_ft(pst, threat, board.side_to_move()) });
+            buckets.push(OUTPUT_BUCKETS_LAYOUT[board.occupancies().popcount()]);
+        }
+
+        let outputs = unsafe { forward::evaluate_batch(&ft_outs, &buckets, &self.nnz_table, self.parameters.as_ref()) };
+        outputs.into_iter().map(|output| (output * NETWORK_SCALE as f32) as i32).collect()
+    }
+
+    /// Breaks down the evaluation of the position for debugging the behaviour of the network.
+    pub fn breakdown(&mut self, board: &Board) -> EvalBreakdown {
+        self.full_refresh(board);
+        let eval = self.evaluate(board);
+
+        let bucket = OUTPUT_BUCKETS_LAYOUT[board.occupancies().popcount()];
+        let parameters = self.parameters.as_ref();
+        let pst = &self.pst_stack[self.index];
+
+        unsafe {
+            let ft_out = forward::activate_ft(pst, &self.threat_stack[self.index], board.side_to_move());
+            let active = ft_out.iter().filter(|&&value| value != 0).count();
+            let (_, nnz_count) = forward::find_nnz(&ft_out, &self.nnz_table);
+
+            // Zeroed threat accumulators leave only the piece-square features
+            let ft_out = forward::activate_ft(pst, &ThreatAccumulator::new(), board.side_to_move());
+            let (nnz_indexes, count) = forward::find_nnz(&ft_out, &self.nnz_table);
+
+            let l1_out = forward::propagate_l1(&ft_out, &nnz_indexes[..count], bucket, parameters);
+            let l2_out = forward::propagate_l2(&l1_out, bucket, parameters);
+            let l3_out = forward::propagate_l3(&l2_out, bucket, parameters);
+            let without_threats = (l3_out * NETWORK_SCALE as f32) as i32;
+
+            EvalBreakdown { eval, bucket, active, chunks: nnz_count, without_threats }
+        }
+    }
+
     pub fn piece_contribution(&mut self, board: &Board, sq: Square) -> Option<i32> {
         let piece = board.piece_on(sq);
 
@@ -355,7 +458,7 @@ impl BoardObserver for Network {
 
 #[repr(C)]
 pub struct Parameters {
-    ft_threat_weights: Aligned<[[i8; L1_SIZE]; 66864]>,
+    ft_threat_weights: Aligned<[[i8; L1_SIZE]; THREAT_INPUTS]>,
     ft_piece_weights: Aligned<[[i16; L1_SIZE]; INPUT_BUCKETS * 768]>,
     ft_biases: Aligned<[i16; L1_SIZE]>,
     l1_weights: Aligned<[[i8; L2_SIZE * L1_SIZE]; OUTPUT_BUCKETS]>,
@@ -366,10 +469,402 @@ pub struct Parameters {
     l3_biases: Aligned<[f32; OUTPUT_BUCKETS]>,
 }
 
+/// Optional output head predicting win, draw and loss logits from the last hidden layer, stored
+/// right after [`Parameters`] in network files that were trained with it.
+#[repr(C)]
+pub struct WdlHead {
+    weights: [[[f32; L3_SIZE]; 3]; OUTPUT_BUCKETS],
+    biases: [[f32; 3]; OUTPUT_BUCKETS],
+}
+
+/// Network loaded at runtime through the `EvalFile` option, replacing the embedded one.
+static EXTERNAL: RwLock<Option<Arc<Parameters>>> = RwLock::new(None);
+
+/// WDL head of the network loaded through `EvalFile`, if it has one.
+static EXTERNAL_WDL: RwLock<Option<Arc<WdlHead>>> = RwLock::new(None);
+
+/// Order of the L1 weights of each output bucket in a network file.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum WeightLayout {
+    /// The layout consumed by `propagate_l1`: for every group of 4 inputs, the 4 weights of each output
+    /// in turn, as written by `export_net`.
+    Interleaved,
+    /// All the weights of the first output, then all those of the second and so on, as most trainers
+    /// export them.
+    RowMajor,
+}
+
+impl WeightLayout {
+    pub const ALL: [Self; 2] = [Self::Interleaved, Self::RowMajor];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Interleaved => "native",
+            Self::RowMajor => "rowmajor",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.name() == name)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadNetworkError {
+    /// The network file could not be read.
+    Io(std::io::Error),
+    /// The network file doesn't match the architecture of this build.
+    ArchitectureMismatch { expected: usize, found: usize },
+    /// The network file contains floating point parameters that are infinite or NaN.
+    Corrupted,
+    /// The header of the network file is of a format version this build can't read.
+    UnsupportedVersion(u32),
+    /// The header of the network file describes another architecture than the one of this build.
+    DimensionsMismatch([u32; 6]),
+    /// The contents of the network file don't match the checksum in its header.
+    ChecksumMismatch { expected: u64, found: u64 },
+}
+
+impl std::fmt::Display for LoadNetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::ArchitectureMismatch { expected, found } => {
+                write!(
+                    f,
+                    "expected {expected} bytes for a {INPUT_BUCKETS}x{L1_SIZE} (+{THREAT_INPUTS} threat inputs) \
+                     -> {L2_SIZE} -> {L3_SIZE} -> 1 x{OUTPUT_BUCKETS} network, optionally followed by a {} byte \
+                     WDL head, found {found} bytes",
+                    std::mem::size_of::<WdlHead>()
+                )?;
+
+                match matching_l1_size(*found) {
+                    Some(size) => write!(f, ", which matches an L1 of {size} that needs a build with L1_SIZE={size}"),
+                    None => Ok(()),
+                }
+            }
+            Self::Corrupted => write!(f, "the network contains non-finite parameters"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "the network file has format version {version}, expected {}", NetworkHeader::VERSION)
+            }
+            Self::DimensionsMismatch([input_buckets, l1, l2, l3, output_buckets, threat_inputs]) => write!(
+                f,
+                "the header describes a {input_buckets}x{l1} (+{threat_inputs} threat inputs) -> {l2} -> {l3} \
+                 -> 1 x{output_buckets} network, expected a {INPUT_BUCKETS}x{L1_SIZE} (+{THREAT_INPUTS} threat \
+                 inputs) -> {L2_SIZE} -> {L3_SIZE} -> 1 x{OUTPUT_BUCKETS} network"
+            ),
+            Self::ChecksumMismatch { expected, found } => {
+                write!(f, "the header has checksum {expected:016x}, but the contents have {found:016x}")
+            }
+        }
+    }
+}
+
+/// Returns the L1 size for which a network of `found` bytes would match this build, with or without a
+/// WDL head, provided the other layers are the same.
+fn matching_l1_size(found: usize) -> Option<usize> {
+    // Every L1 neuron adds its threat, piece square and L1 weights along with its bias
+    let per_neuron = THREAT_INPUTS + INPUT_BUCKETS * 768 * 2 + 2 + OUTPUT_BUCKETS * L2_SIZE;
+    let fixed = std::mem::size_of::<Parameters>() - per_neuron * L1_SIZE;
+
+    [found, found.wrapping_sub(std::mem::size_of::<WdlHead>())]
+        .into_iter()
+        .filter(|&size| size > fixed && (size - fixed).is_multiple_of(per_neuron))
+        .map(|size| (size - fixed) / per_neuron)
+        .find(|&size| size != L1_SIZE && size.is_multiple_of(64))
+}
+
+/// Whether a network is available, either embedded or loaded through `EvalFile`. Without one the
+/// handcrafted evaluation is used instead.
+pub fn is_available() -> bool {
+    Parameters::external().is_some() || Parameters::embedded().is_some()
+}
+
+/// Header that network files may start with, so that they can be checked before being used.
+/// It's written by `export_net`, while trainers usually write raw dumps without one.
+struct NetworkHeader {
+    version: u32,
+    /// Input buckets, L1, L2 and L3 sizes, output buckets and threat inputs.
+    dimensions: [u32; 6],
+    /// Checksum of everything that follows the header.
+    checksum: u64,
+}
+
+impl NetworkHeader {
+    const MAGIC: [u8; 4] = *b"RKNN";
+    const VERSION: u32 = 1;
+    const SIZE: usize = 40;
+
+    fn new(checksum: u64) -> Self {
+        let dimensions = [INPUT_BUCKETS, L1_SIZE, L2_SIZE, L3_SIZE, OUTPUT_BUCKETS, THREAT_INPUTS];
+        Self {
+            version: Self::VERSION,
+            dimensions: dimensions.map(|size| size as u32),
+            checksum,
+        }
+    }
+
+    fn encode(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&Self::MAGIC);
+        bytes[4..8].copy_from_slice(&self.version.to_le_bytes());
+        for (i, size) in self.dimensions.iter().enumerate() {
+            bytes[8 + 4 * i..12 + 4 * i].copy_from_slice(&size.to_le_bytes());
+        }
+        bytes[32..].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Returns `None` if the bytes don't start with the magic, i.e. the file is a raw dump.
+    fn decode(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        if bytes[..4] != Self::MAGIC {
+            return None;
+        }
+
+        let word = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        Some(Self {
+            version: word(4),
+            dimensions: std::array::from_fn(|i| word(8 + 4 * i)),
+            checksum: u64::from_le_bytes(bytes[32..].try_into().unwrap()),
+        })
+    }
+
+    fn validate(&self) -> Result<(), LoadNetworkError> {
+        if self.version != Self::VERSION {
+            return Err(LoadNetworkError::UnsupportedVersion(self.version));
+        }
+        if self.dimensions != Self::new(0).dimensions {
+            return Err(LoadNetworkError::DimensionsMismatch(self.dimensions));
+        }
+        Ok(())
+    }
+}
+
+/// Loads a network from disk, making it the source for all subsequently created network instances.
+///
+/// The file must be a raw dump of [`Parameters`], i.e. the same format as the embedded network,
+/// optionally followed by a raw dump of a [`WdlHead`]. It may start with a [`NetworkHeader`], in
+/// which case its version, dimensions and checksum are checked and the L1 weights are in the
+/// native layout. Otherwise L1 weights stored in another `layout` are permuted once here.
+pub fn load_network(path: &str, layout: WeightLayout) -> Result<(), LoadNetworkError> {
+    use std::io::{Read, Seek};
+
+    let mut file = std::fs::File::open(path).map_err(LoadNetworkError::Io)?;
+    let mut found = file.metadata().map_err(LoadNetworkError::Io)?.len() as usize;
+    let expected = std::mem::size_of::<Parameters>();
+
+    let mut bytes = [0; NetworkHeader::SIZE];
+    let header = match found >= NetworkHeader::SIZE {
+        true => {
+            file.read_exact(&mut bytes).map_err(LoadNetworkError::Io)?;
+            NetworkHeader::decode(&bytes)
+        }
+        false => None,
+    };
+
+    match &header {
+        Some(header) => {
+            header.validate()?;
+            found -= NetworkHeader::SIZE;
+        }
+        None => {
+            file.rewind().map_err(LoadNetworkError::Io)?;
+        }
+    }
+
+    if found != expected && found != expected + std::mem::size_of::<WdlHead>() {
+        return Err(LoadNetworkError::ArchitectureMismatch { expected, found });
+    }
+
+    let mut parameters = read_raw::<Parameters>(&mut file).map_err(LoadNetworkError::Io)?;
+    let wdl_head = match found > expected {
+        true => Some(read_raw::<WdlHead>(&mut file).map_err(LoadNetworkError::Io)?),
+        false => None,
+    };
+
+    if let Some(header) = &header {
+        // `Parameters` is a multiple of 64 bytes long, so hashing the parts equals hashing the file.
+        // SAFETY: both were read with `read_raw`.
+        let mut found = update_checksum(CHECKSUM_OFFSET, unsafe { raw_bytes(&*parameters) });
+        if let Some(head) = &wdl_head {
+            found = update_checksum(found, unsafe { raw_bytes(&**head) });
+        }
+
+        if found != header.checksum {
+            return Err(LoadNetworkError::ChecksumMismatch { expected: header.checksum, found });
+        }
+    }
+
+    if !parameters.is_finite() || wdl_head.as_ref().is_some_and(|head| !head.is_finite()) {
+        return Err(LoadNetworkError::Corrupted);
+    }
+
+    if header.is_none() && layout == WeightLayout::RowMajor {
+        parameters.interleave_l1_weights();
+    }
+
+    *EXTERNAL.write().unwrap() = Some(Arc::from(parameters));
+    *EXTERNAL_WDL.write().unwrap() = wdl_head.map(Arc::from);
+    Ok(())
+}
+
+/// Reads a structure of plain integers and floats from the file.
+fn read_raw<T>(file: &mut std::fs::File) -> std::io::Result<Box<T>> {
+    use std::io::Read;
+
+    let mut boxed = Box::<T>::new_uninit();
+
+    // SAFETY: `T` consists solely of plain integers and floats, so any byte pattern is valid.
+    unsafe {
+        let bytes = std::slice::from_raw_parts_mut(boxed.as_mut_ptr().cast::<u8>(), std::mem::size_of::<T>());
+        file.read_exact(bytes)?;
+        Ok(boxed.assume_init())
+    }
+}
+
+/// Writes the network in use, either the embedded one or the one loaded through `EvalFile`, to disk,
+/// preceded by a [`NetworkHeader`].
+pub fn export_network(path: &str) -> std::io::Result<()> {
+    let bytes = network_bytes()?;
+    let header = NetworkHeader::new(update_checksum(CHECKSUM_OFFSET, &bytes));
+
+    let mut contents = header.encode().to_vec();
+    contents.extend_from_slice(&bytes);
+    std::fs::write(path, contents)
+}
+
+/// The network in use without a header, with its L1 weights in the interleaved layout.
+fn network_bytes() -> std::io::Result<Vec<u8>> {
+    let external = Parameters::external();
+
+    let bytes = match &external {
+        // SAFETY: the parameters were read from a file.
+        Some(parameters) => unsafe { raw_bytes(&**parameters) },
+        None => Parameters::embedded_bytes().ok_or_else(|| std::io::Error::other("there is no network to export"))?,
+    };
+
+    let mut contents = bytes.to_vec();
+
+    if let Some(head) = EXTERNAL_WDL.read().unwrap().as_deref() {
+        // SAFETY: the head was read from a file.
+        contents.extend_from_slice(unsafe { raw_bytes(head) });
+    }
+
+    Ok(contents)
+}
+
+/// The bytes of a structure read from a network file.
+///
+/// # Safety
+///
+/// Every byte of `value`, padding included, must be initialized, as it is when the structure was read
+/// with [`read_raw`].
+unsafe fn raw_bytes<T>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts((value as *const T).cast::<u8>(), std::mem::size_of::<T>()) }
+}
+
+/// Prints the checksum of the network in use, as it is stored in the header of an exported file.
+/// The embedded network is also compared against the checksum computed by the build script, so
+/// that a corrupted binary can be told apart from a bad network. Hashing hundreds of megabytes is
+/// slow, so this is only done when asked for.
+pub fn print_checksum() {
+    let bytes = match network_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to compute the network checksum: {e}");
+            return;
+        }
+    };
+
+    let found = update_checksum(CHECKSUM_OFFSET, &bytes);
+    println!("info string Network checksum {found:016x}");
+
+    if Parameters::external().is_none() {
+        let expected = u64::from_str_radix(env!("MODEL_CHECKSUM"), 16).unwrap_or_default();
+        if found != expected {
+            println!("info string Embedded network checksum mismatch: expected {expected:016x}, found {found:016x}");
+        }
+    }
+}
+
+const CHECKSUM_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// 64-bit FNV-1a over little-endian words, matching the checksum computed by the build script.
+/// Updating with consecutive slices whose lengths are multiples of 8 equals hashing them at once.
+fn update_checksum(mut hash: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let mut words = bytes.chunks_exact(8);
+
+    for word in &mut words {
+        hash = (hash ^ u64::from_le_bytes(word.try_into().unwrap())).wrapping_mul(PRIME);
+    }
+
+    for &byte in words.remainder() {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+    }
+
+    hash
+}
+
+impl WdlHead {
+    fn is_finite(&self) -> bool {
+        self.weights.iter().flatten().flatten().chain(self.biases.iter().flatten()).all(|v| v.is_finite())
+    }
+}
+
 impl Parameters {
-    fn embedded() -> &'static Self {
-        static EMBEDDED: Parameters = unsafe { std::mem::transmute(*include_bytes!(env!("MODEL"))) };
-        &EMBEDDED
+    fn embedded() -> Option<&'static Self> {
+        // SAFETY: the bytes are aligned for and have the exact size of `Parameters`, which consists solely
+        // of plain integers and floats.
+        Self::embedded_bytes().map(|bytes| unsafe { &*bytes.as_ptr().cast::<Self>() })
+    }
+
+    fn embedded_bytes() -> Option<&'static [u8]> {
+        static EMBEDDED: std::sync::OnceLock<Option<&[u8]>> = std::sync::OnceLock::new();
+        *EMBEDDED.get_or_init(embedded::load)
+    }
+
+    /// All-zero parameters standing in for a missing network, so that the accumulators keep working
+    /// while the handcrafted evaluation is used.
+    fn zeroed() -> &'static Self {
+        static ZEROED: std::sync::OnceLock<&Parameters> = std::sync::OnceLock::new();
+
+        // SAFETY: `Parameters` consists solely of plain integers and floats, for which zero is valid.
+        ZEROED.get_or_init(|| Box::leak(unsafe { Box::<Self>::new_zeroed().assume_init() }))
+    }
+
+    fn is_finite(&self) -> bool {
+        let l1_biases = self.l1_biases.iter().flatten();
+        let l2_weights = self.l2_weights.iter().flatten().flatten();
+        let l2_biases = self.l2_biases.iter().flatten();
+        let l3_weights = self.l3_weights.iter().flatten();
+
+        l1_biases
+            .chain(l2_weights)
+            .chain(l2_biases)
+            .chain(l3_weights)
+            .chain(self.l3_biases.iter())
+            .all(|v| v.is_finite())
+    }
+
+    /// Converts the L1 weights from [`WeightLayout::RowMajor`] to [`WeightLayout::Interleaved`].
+    fn interleave_l1_weights(&mut self) {
+        const CHUNKS: usize = 4;
+
+        for weights in self.l1_weights.iter_mut() {
+            let row_major = weights.to_vec();
+
+            for (output, row) in row_major.chunks_exact(L1_SIZE).enumerate() {
+                for (input, &weight) in row.iter().enumerate() {
+                    weights[(input / CHUNKS) * L2_SIZE * CHUNKS + output * CHUNKS + input % CHUNKS] = weight;
+                }
+            }
+        }
+    }
+
+    fn external() -> Option<Arc<Self>> {
+        EXTERNAL.read().unwrap().clone()
     }
 
     fn allocate_owned() -> Arc<Self> {
@@ -377,8 +872,11 @@ impl Parameters {
         let ptr = boxed.as_mut_ptr();
         std::mem::forget(boxed);
 
+        let external = Self::external();
+        let source = external.as_deref().or(Self::embedded()).unwrap_or(Self::zeroed());
+
         unsafe {
-            std::ptr::copy_nonoverlapping(Self::embedded() as *const Self, ptr, 1);
+            std::ptr::copy_nonoverlapping(source as *const Self, ptr, 1);
             Arc::from(Box::from_raw(ptr))
         }
     }
@@ -396,8 +894,13 @@ enum ParametersStorage {
 }
 
 impl ParametersHandle {
-    fn embedded() -> Self {
-        Self { inner: ParametersStorage::Embedded(Parameters::embedded()) }
+    fn shared() -> Self {
+        match Parameters::external() {
+            Some(parameters) => Self::owned(parameters),
+            None => Self {
+                inner: ParametersStorage::Embedded(Parameters::embedded().unwrap_or(Parameters::zeroed())),
+            },
+        }
     }
 
     const fn owned(parameters: Arc<Parameters>) -> Self {
@@ -422,7 +925,7 @@ impl NumaReplicable for ParametersHandle {
     }
 
     fn allocate_shared() -> Option<Arc<Self>> {
-        Arc::new(Self::embedded()).into()
+        Arc::new(Self::shared()).into()
     }
 }
 
@@ -451,3 +954,182 @@ impl<T> std::ops::DerefMut for Aligned<T> {
         &mut self.data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_f32(state: &mut u64) -> f32 {
+        (random(state) % 2001) as f32 / 1000.0 - 1.0
+    }
+
+    fn random_ft_out(state: &mut u64, density: u64) -> Aligned<[u8; L1_SIZE]> {
+        let mut ft_out = Aligned::new([0u8; L1_SIZE]);
+        for value in ft_out.iter_mut() {
+            *value = if random(state).is_multiple_of(density) { (random(state) % 128) as u8 } else { 0 };
+        }
+        ft_out
+    }
+
+    fn nnz_table() -> Box<[SparseEntry]> {
+        Network::new(Arc::new(ParametersHandle::shared())).nnz_table
+    }
+
+    #[test]
+    fn dense_l1_matches_sparse() {
+        let mut parameters = Parameters::allocate_owned();
+        let parameters = Arc::get_mut(&mut parameters).unwrap();
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+
+        for weight in parameters.l1_weights[0].iter_mut() {
+            *weight = (random(&mut state) % 128) as i8 - 64;
+        }
+
+        let nnz_table = nnz_table();
+
+        for density in [4, 2, 1] {
+            let ft_out = random_ft_out(&mut state, density);
+
+            unsafe {
+                let (nnz, count) = forward::find_nnz(&ft_out, &nnz_table);
+                let sparse = forward::propagate_l1(&ft_out, &nnz[..count], 0, parameters);
+                let dense = forward::propagate_l1_dense(&ft_out, 0, parameters);
+
+                assert_eq!(*sparse, *dense);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_threats_match_refresh() {
+        crate::lookup::initialize();
+
+        let mut parameters = Parameters::allocate_owned();
+        let mut state = 0x3C6E_F372_FE94_F82B;
+
+        for weight in Arc::get_mut(&mut parameters).unwrap().ft_threat_weights.iter_mut().flatten() {
+            *weight = (random(&mut state) % 16) as i8 - 8;
+        }
+
+        let mut network = Network::new(Arc::new(ParametersHandle::owned(parameters)));
+        network.handcrafted = false;
+
+        const FENS: [&str; 3] = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+
+        for fen in FENS.iter().flat_map(|fen| [fen; 20]) {
+            let mut board = Board::from_fen(fen).unwrap();
+            network.index = 0;
+            network.full_refresh(&board);
+
+            for _ in 0..60 {
+                let moves = board.generate_all_moves();
+                if moves.is_empty() {
+                    break;
+                }
+
+                let mv = moves[random(&mut state) as usize % moves.len()].mv;
+                network.push(mv, &board);
+                board.make_move(mv, &mut network);
+
+                // Skip some evaluations so that updates also span several plies
+                if random(&mut state).is_multiple_of(3) {
+                    continue;
+                }
+
+                network.evaluate(&board);
+
+                for pov in [Color::White, Color::Black] {
+                    let mut expected = network.threat_stack[network.index].clone();
+                    expected.refresh(&board, pov, network.parameters.as_ref());
+
+                    assert!(
+                        expected.values[pov] == network.threat_stack[network.index].values[pov],
+                        "incremental threat update diverged in position '{}'",
+                        board.to_fen()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn network_header_round_trip() {
+        let header = NetworkHeader::decode(&NetworkHeader::new(0x0123_4567_89AB_CDEF).encode()).unwrap();
+
+        assert_eq!(header.checksum, 0x0123_4567_89AB_CDEF);
+        assert!(header.validate().is_ok());
+        assert!(NetworkHeader::decode(&[0; NetworkHeader::SIZE]).is_none());
+    }
+
+    #[test]
+    fn row_major_l1_weights_are_interleaved() {
+        let mut parameters = Parameters::allocate_owned();
+        let parameters = Arc::get_mut(&mut parameters).unwrap();
+        let mut state = 0x6A09_E667_F3BC_C908;
+
+        for weight in parameters.l1_weights.iter_mut().flatten() {
+            *weight = random(&mut state) as i8;
+        }
+
+        let interleaved = parameters.l1_weights.to_vec();
+
+        for (bucket, weights) in parameters.l1_weights.iter_mut().enumerate() {
+            for output in 0..L2_SIZE {
+                for input in 0..L1_SIZE {
+                    weights[output * L1_SIZE + input] =
+                        interleaved[bucket][(input / 4) * L2_SIZE * 4 + output * 4 + input % 4];
+                }
+            }
+        }
+
+        parameters.interleave_l1_weights();
+        assert!(parameters.l1_weights.iter().eq(interleaved.iter()));
+    }
+
+    #[test]
+    fn batch_matches_single_position() {
+        const BUCKETS: usize = 3;
+
+        let mut parameters = Parameters::allocate_owned();
+        let parameters = Arc::get_mut(&mut parameters).unwrap();
+        let mut state = 0x2545_F491_4F6C_DD1D;
+
+        for bucket in 0..BUCKETS {
+            parameters.l1_weights[bucket].iter_mut().for_each(|w| *w = (random(&mut state) % 16) as i8 - 8);
+            parameters.l1_biases[bucket].iter_mut().for_each(|b| *b = random_f32(&mut state));
+            parameters.l2_biases[bucket].iter_mut().for_each(|b| *b = random_f32(&mut state));
+            parameters.l2_weights[bucket].iter_mut().flatten().for_each(|w| *w = random_f32(&mut state));
+            parameters.l3_weights[bucket].iter_mut().for_each(|w| *w = random_f32(&mut state));
+            parameters.l3_biases[bucket] = random_f32(&mut state);
+        }
+
+        let nnz_table = nnz_table();
+
+        let ft_outs = (0..20).map(|_| random_ft_out(&mut state, 2)).collect::<Vec<_>>();
+        let buckets = (0..20).map(|index| index % BUCKETS).collect::<Vec<_>>();
+
+        unsafe {
+            let batch = forward::evaluate_batch(&ft_outs, &buckets, &nnz_table, parameters);
+
+            for (index, ft_out) in ft_outs.iter().enumerate() {
+                let bucket = buckets[index];
+                let (nnz, count) = forward::find_nnz(ft_out, &nnz_table);
+                let l1_out = forward::propagate_l1(ft_out, &nnz[..count], bucket, parameters);
+                let l2_out = forward::propagate_l2(&l1_out, bucket, parameters);
+
+                assert_eq!(batch[index], forward::propagate_l3(&l2_out, bucket, parameters));
+            }
+        }
+    }
+}
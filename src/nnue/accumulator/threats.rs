@@ -9,7 +9,7 @@ use crate::{
 mod threat_index;
 pub use threat_index::*;
 
-#[cfg(not(target_feature = "avx2"))]
+#[cfg(any(test, not(target_feature = "avx2")))]
 mod scalar;
 #[cfg(not(target_feature = "avx2"))]
 pub use scalar::*;
@@ -219,3 +219,85 @@ impl ThreatAccumulator {
         self.accurate[pov] = true;
     }
 }
+
+#[cfg(all(test, target_feature = "avx2"))]
+mod tests {
+    use super::*;
+    use crate::{board::BoardObserver, lookup};
+
+    /// Records the deltas of the vectorized and the scalar detectors for every change to the board.
+    struct Differential {
+        vectorized: ThreatAccumulator,
+        scalar: ThreatAccumulator,
+    }
+
+    impl Differential {
+        fn assert_same_deltas(&mut self) {
+            let mut vectorized = self.vectorized.delta.iter().map(|delta| delta.0).collect::<Vec<_>>();
+            let mut scalar = self.scalar.delta.iter().map(|delta| delta.0).collect::<Vec<_>>();
+            vectorized.sort_unstable();
+            scalar.sort_unstable();
+
+            assert_eq!(vectorized, scalar);
+            self.vectorized.delta.clear();
+            self.scalar.delta.clear();
+        }
+    }
+
+    impl BoardObserver for Differential {
+        fn on_piece_change(&mut self, board: &Board, piece: Piece, sq: Square, add: bool) {
+            push_threats_on_change(&mut self.vectorized, board, piece, sq, add);
+            scalar::push_threats_on_change(&mut self.scalar, board, piece, sq, add);
+            self.assert_same_deltas();
+        }
+
+        fn on_piece_move(&mut self, board: &Board, piece: Piece, from: Square, to: Square) {
+            push_threats_on_move(&mut self.vectorized, board, piece, from, to);
+            scalar::push_threats_on_move(&mut self.scalar, board, piece, from, to);
+            self.assert_same_deltas();
+        }
+
+        fn on_piece_mutate(&mut self, board: &Board, old_piece: Piece, new_piece: Piece, sq: Square) {
+            push_threats_on_mutate(&mut self.vectorized, board, old_piece, new_piece, sq);
+            scalar::push_threats_on_mutate(&mut self.scalar, board, old_piece, new_piece, sq);
+            self.assert_same_deltas();
+        }
+    }
+
+    const FENS: [&str; 4] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ];
+
+    #[test]
+    fn vectorized_threats_match_scalar() {
+        lookup::initialize();
+
+        let mut observer = Differential {
+            vectorized: ThreatAccumulator::new(),
+            scalar: ThreatAccumulator::new(),
+        };
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+
+        // A few random games from each position, covering captures, castling, en passant and promotions
+        for fen in FENS.iter().flat_map(|fen| [fen; 50]) {
+            let mut board = Board::from_fen(fen).unwrap();
+
+            for _ in 0..60 {
+                let moves = board.generate_all_moves();
+                if moves.is_empty() {
+                    break;
+                }
+
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                let mv = moves[state as usize % moves.len()].mv;
+                board.make_move(mv, &mut observer);
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Storage of the embedded network.
+//!
+//! By default the network is embedded compressed in the [LZ4 block format][lz4] by the build
+//! script and decompressed once into an aligned buffer on first use. With the `mmap-net`
+//! feature nothing is embedded; the uncompressed network the binary was built against is
+//! instead mapped from disk, which keeps startup instant for development builds. Only the `no-net`
+//! feature builds without a network, and a network that can't be loaded otherwise is a panic.
+//!
+//! [lz4]: https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md
+
+use super::Parameters;
+
+#[cfg(not(feature = "mmap-net"))]
+pub fn load() -> Option<&'static [u8]> {
+    static COMPRESSED: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/network.lz4"));
+
+    if cfg!(feature = "no-net") {
+        return None;
+    }
+
+    let size = std::mem::size_of::<Parameters>();
+    let mut boxed = Box::<Parameters>::new_uninit();
+
+    // SAFETY: the buffer is exactly `size` bytes long and is fully written by `decompress`.
+    unsafe {
+        let bytes = std::slice::from_raw_parts_mut(boxed.as_mut_ptr().cast::<u8>(), size);
+        bytes.fill(0);
+
+        let written = decompress(COMPRESSED, bytes);
+        assert_eq!(written, size, "The embedded network is corrupted");
+
+        Some(std::slice::from_raw_parts(Box::leak(boxed).as_ptr().cast::<u8>(), size))
+    }
+}
+
+#[cfg(all(feature = "mmap-net", unix))]
+pub fn load() -> Option<&'static [u8]> {
+    use std::os::fd::AsRawFd;
+
+    use libc::{MAP_FAILED, MAP_PRIVATE, PROT_READ, mmap};
+
+    if cfg!(feature = "no-net") {
+        return None;
+    }
+
+    let path = env!("MODEL");
+    let size = std::mem::size_of::<Parameters>();
+
+    let file = std::fs::File::open(path).unwrap_or_else(|e| panic!("Failed to open network '{path}': {e}"));
+    let found = file.metadata().map_or(0, |metadata| metadata.len() as usize);
+    assert_eq!(found, size, "The network '{path}' doesn't match the architecture of this build");
+
+    // SAFETY: the mapping covers the whole file, is page aligned and stays valid after the file is closed.
+    unsafe {
+        let ptr = mmap(std::ptr::null_mut(), size, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0);
+        assert_ne!(ptr, MAP_FAILED, "Failed to map network '{path}'");
+
+        Some(std::slice::from_raw_parts(ptr.cast::<u8>(), size))
+    }
+}
+
+#[cfg(all(feature = "mmap-net", not(unix)))]
+compile_error!("The `mmap-net` feature is only supported on Unix targets");
+
+/// Decompresses an LZ4 block into `output`, returning the number of bytes written.
+#[cfg(not(feature = "mmap-net"))]
+fn decompress(input: &[u8], output: &mut [u8]) -> usize {
+    let mut i = 0;
+    let mut o = 0;
+
+    loop {
+        let token = input[i];
+        i += 1;
+
+        let literals = read_length(input, &mut i, (token >> 4) as usize);
+        output[o..o + literals].copy_from_slice(&input[i..i + literals]);
+        i += literals;
+        o += literals;
+
+        // The last sequence consists of literals only
+        if i == input.len() {
+            return o;
+        }
+
+        let offset = u16::from_le_bytes([input[i], input[i + 1]]) as usize;
+        i += 2;
+
+        let length = read_length(input, &mut i, (token & 15) as usize) + 4;
+
+        if offset >= length {
+            output.copy_within(o - offset..o - offset + length, o);
+        } else {
+            // Overlapping matches repeat the last `offset` bytes
+            for k in o..o + length {
+                output[k] = output[k - offset];
+            }
+        }
+        o += length;
+    }
+}
+
+#[cfg(not(feature = "mmap-net"))]
+fn read_length(input: &[u8], i: &mut usize, mut length: usize) -> usize {
+    if length == 15 {
+        loop {
+            let byte = input[*i];
+            *i += 1;
+            length += byte as usize;
+
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    length
+}
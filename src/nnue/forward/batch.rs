@@ -0,0 +1,106 @@
+//! Forward pass over several positions at once.
+//!
+//! L1 is propagated one position at a time since its sparse inputs differ between positions,
+//! but L2 and L3 run for all positions sharing an output bucket together. Activations are stored
+//! feature-major with the positions in the innermost loop, which lets the compiler vectorize
+//! across positions. The floating point operations of each position happen in the same order
+//! as in the single position pass, so the results are identical wherever `mul_add_f32` is fused.
+
+use super::propagate_l1;
+use crate::nnue::{Aligned, L1_SIZE, L2_SIZE, L3_SIZE, OUTPUT_BUCKETS, Parameters, SparseEntry};
+
+pub unsafe fn evaluate_batch(
+    ft_outs: &[Aligned<[u8; L1_SIZE]>], buckets: &[usize], nnz_table: &[SparseEntry], parameters: &Parameters,
+) -> Vec<f32> {
+    debug_assert_eq!(ft_outs.len(), buckets.len());
+
+    let mut outputs = vec![0.0; ft_outs.len()];
+
+    for bucket in 0..OUTPUT_BUCKETS {
+        let members = (0..ft_outs.len()).filter(|&index| buckets[index] == bucket).collect::<Vec<_>>();
+        let count = members.len();
+
+        if count == 0 {
+            continue;
+        }
+
+        let mut l1_out = vec![0.0; L2_SIZE * count];
+
+        for (position, &index) in members.iter().enumerate() {
+            let (nnz_indexes, nnz_count) = super::find_nnz(&ft_outs[index], nnz_table);
+            let output = propagate_l1(&ft_outs[index], &nnz_indexes[..nnz_count], bucket, parameters);
+
+            for i in 0..L2_SIZE {
+                l1_out[i * count + position] = output[i];
+            }
+        }
+
+        let l2_out = propagate_l2(&l1_out, count, bucket, parameters);
+        let l3_out = propagate_l3(&l2_out, count, bucket, parameters);
+
+        for (position, &index) in members.iter().enumerate() {
+            outputs[index] = l3_out[position];
+        }
+    }
+
+    outputs
+}
+
+fn propagate_l2(l1_out: &[f32], count: usize, bucket: usize, parameters: &Parameters) -> Vec<f32> {
+    let mut output = vec![0.0; L3_SIZE * count];
+
+    for j in 0..L3_SIZE {
+        output[j * count..(j + 1) * count].fill(parameters.l2_biases[bucket][j]);
+    }
+
+    for i in 0..L2_SIZE {
+        let inputs = &l1_out[i * count..(i + 1) * count];
+
+        for j in 0..L3_SIZE {
+            let weight = parameters.l2_weights[bucket][i][j];
+
+            for (output, &input) in output[j * count..(j + 1) * count].iter_mut().zip(inputs) {
+                *output = weight.mul_add(input, *output);
+            }
+        }
+    }
+
+    for value in &mut output {
+        *value = value.clamp(0.0, 1.0);
+    }
+
+    output
+}
+
+fn propagate_l3(l2_out: &[f32], count: usize, bucket: usize, parameters: &Parameters) -> Vec<f32> {
+    // Same 16 partial sums and recursive halving as the single position pass
+    const LANES: usize = 16;
+
+    let mut sums = vec![0.0; LANES * count];
+
+    for i in (0..L3_SIZE).step_by(LANES) {
+        for k in 0..LANES {
+            let weight = parameters.l3_weights[bucket][i + k];
+            let inputs = &l2_out[(i + k) * count..(i + k + 1) * count];
+
+            for (sum, &input) in sums[k * count..(k + 1) * count].iter_mut().zip(inputs) {
+                *sum = weight.mul_add(input, *sum);
+            }
+        }
+    }
+
+    let mut stride = LANES / 2;
+    while stride > 0 {
+        for k in 0..stride {
+            let (lower, upper) = sums.split_at_mut((k + stride) * count);
+            for (sum, &other) in lower[k * count..(k + 1) * count].iter_mut().zip(&upper[..count]) {
+                *sum += other;
+            }
+        }
+        stride /= 2;
+    }
+
+    sums.truncate(count);
+    sums.iter_mut().for_each(|sum| *sum += parameters.l3_biases[bucket]);
+    sums
+}
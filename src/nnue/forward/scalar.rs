@@ -6,7 +6,7 @@ use crate::{
     types::Color,
 };
 
-pub fn activate_ft(pst: &PstAccumulator, threat: &ThreatAccumulator, stm: Color) -> Aligned<[u8; L1_SIZE]> {
+pub unsafe fn activate_ft(pst: &PstAccumulator, threat: &ThreatAccumulator, stm: Color) -> Aligned<[u8; L1_SIZE]> {
     let mut output = Aligned::new([0; L1_SIZE]);
 
     for flip in [0, 1] {
@@ -64,6 +64,18 @@ pub unsafe fn propagate_l1(
     output
 }
 
+/// Dense counterpart of `propagate_l1` that walks every input chunk in order.
+///
+/// Nothing dispatches to it, as it lost to the sparse product at every density measured, but it's
+/// kept along with its test for networks where that may change.
+#[allow(dead_code)]
+pub unsafe fn propagate_l1_dense(
+    ft_out: &Aligned<[u8; L1_SIZE]>, bucket: usize, parameters: &Parameters,
+) -> Aligned<[f32; L2_SIZE]> {
+    let all = std::array::from_fn::<u16, { L1_SIZE / 4 }, _>(|index| index as u16);
+    propagate_l1(ft_out, &all, bucket, parameters)
+}
+
 pub fn propagate_l2(
     l1_out: &Aligned<[f32; L2_SIZE]>, bucket: usize, parameters: &Parameters,
 ) -> Aligned<[f32; L3_SIZE]> {
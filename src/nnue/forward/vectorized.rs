@@ -52,6 +52,7 @@ pub unsafe fn activate_ft(pst: &PstAccumulator, threat: &ThreatAccumulator, stm:
     output
 }
 
+#[cfg(not(target_feature = "avx512vnni"))]
 pub unsafe fn propagate_l1(
     ft_out: &Aligned<[u8; L1_SIZE]>, nnz: &[u16], bucket: usize, parameters: &Parameters,
 ) -> Aligned<[f32; L2_SIZE]> {
@@ -109,6 +110,107 @@ pub unsafe fn propagate_l1(
     output
 }
 
+#[cfg(target_feature = "avx512vnni")]
+pub unsafe fn propagate_l1(
+    ft_out: &Aligned<[u8; L1_SIZE]>, nnz: &[u16], bucket: usize, parameters: &Parameters,
+) -> Aligned<[f32; L2_SIZE]> {
+    const CHUNKS: usize = 4;
+    const ACCUMULATORS: usize = 4;
+
+    // With VNNI a single `vpdpbusd` covers one non-zero chunk for all L2 outputs, so the loop is bound by
+    // the latency of the accumulator dependency chain. Spreading the chunks over independent accumulators
+    // lets consecutive `vpdpbusd` instructions overlap; integer addition keeps the result bit-exact.
+    let mut pre_activations = [[simd::zeroed(); L2_SIZE / simd::F32_LANES]; ACCUMULATORS];
+
+    let packed = std::slice::from_raw_parts(ft_out.as_ptr().cast::<i32>(), L1_SIZE / CHUNKS);
+
+    let mut chunks = nnz.chunks_exact(ACCUMULATORS);
+
+    for chunk in &mut chunks {
+        for (k, accumulator) in pre_activations.iter_mut().enumerate() {
+            let index = *chunk.get_unchecked(k) as usize;
+            let input = simd::splat_i32(*packed.get_unchecked(index));
+            let weights = parameters.l1_weights[bucket].as_ptr().add(index * L2_SIZE * CHUNKS);
+
+            for j in (0..L2_SIZE).step_by(simd::F32_LANES) {
+                let vector = &mut accumulator[j / simd::F32_LANES];
+                *vector = simd::dpbusd(*vector, input, *weights.add(j * CHUNKS).cast());
+            }
+        }
+    }
+
+    for (k, &index) in chunks.remainder().iter().enumerate() {
+        let index = index as usize;
+        let input = simd::splat_i32(*packed.get_unchecked(index));
+        let weights = parameters.l1_weights[bucket].as_ptr().add(index * L2_SIZE * CHUNKS);
+
+        for j in (0..L2_SIZE).step_by(simd::F32_LANES) {
+            let vector = &mut pre_activations[k][j / simd::F32_LANES];
+            *vector = simd::dpbusd(*vector, input, *weights.add(j * CHUNKS).cast());
+        }
+    }
+
+    let mut output = Aligned::new([0.0; L2_SIZE]);
+
+    let zero = simd::zero_f32();
+    let one = simd::splat_f32(1.0);
+    let dequant = simd::splat_f32(DEQUANT_MULTIPLIER);
+
+    for i in (0..L2_SIZE).step_by(simd::F32_LANES) {
+        let j = i / simd::F32_LANES;
+        let sum = simd::add_i32(
+            simd::add_i32(pre_activations[0][j], pre_activations[1][j]),
+            simd::add_i32(pre_activations[2][j], pre_activations[3][j]),
+        );
+
+        let biases = *parameters.l1_biases[bucket].as_ptr().add(i).cast();
+        let vector = simd::mul_add_f32(simd::convert_to_f32(sum), dequant, biases);
+        *output.as_mut_ptr().add(i).cast() = simd::clamp_f32(vector, zero, one);
+    }
+
+    output
+}
+
+/// Dense counterpart of `propagate_l1` that walks every input chunk in order, which avoids the
+/// indirect weight loads once most of the feature transformer output is non-zero.
+///
+/// Nothing dispatches to it, as it lost to the sparse product at every density measured, but it's
+/// kept along with its test for networks where that may change.
+#[allow(dead_code)]
+pub unsafe fn propagate_l1_dense(
+    ft_out: &Aligned<[u8; L1_SIZE]>, bucket: usize, parameters: &Parameters,
+) -> Aligned<[f32; L2_SIZE]> {
+    const CHUNKS: usize = 4;
+
+    let mut pre_activations = Aligned::new([simd::zeroed(); L2_SIZE / simd::F32_LANES]);
+
+    let packed = std::slice::from_raw_parts(ft_out.as_ptr().cast::<i32>(), L1_SIZE / CHUNKS);
+
+    for (index, &input) in packed.iter().enumerate() {
+        let input = simd::splat_i32(input);
+        let weights = parameters.l1_weights[bucket].as_ptr().add(index * L2_SIZE * CHUNKS);
+
+        for j in (0..L2_SIZE).step_by(simd::F32_LANES) {
+            let vector = &mut pre_activations[j / simd::F32_LANES];
+            *vector = simd::dpbusd(*vector, input, *weights.add(j * CHUNKS).cast());
+        }
+    }
+
+    let mut output = Aligned::new([0.0; L2_SIZE]);
+
+    let zero = simd::zero_f32();
+    let one = simd::splat_f32(1.0);
+    let dequant = simd::splat_f32(DEQUANT_MULTIPLIER);
+
+    for i in (0..L2_SIZE).step_by(simd::F32_LANES) {
+        let biases = *parameters.l1_biases[bucket].as_ptr().add(i).cast();
+        let vector = simd::mul_add_f32(simd::convert_to_f32(pre_activations[i / simd::F32_LANES]), dequant, biases);
+        *output.as_mut_ptr().add(i).cast() = simd::clamp_f32(vector, zero, one);
+    }
+
+    output
+}
+
 pub unsafe fn propagate_l2(
     l1_out: &Aligned<[f32; L2_SIZE]>, bucket: usize, parameters: &Parameters,
 ) -> Aligned<[f32; L3_SIZE]> {
@@ -156,6 +258,8 @@ pub unsafe fn propagate_l3(l2_out: &Aligned<[f32; L3_SIZE]>, bucket: usize, para
     simd::horizontal_sum(output) + parameters.l3_biases[bucket]
 }
 
+// The bytes of the nonzero mask are expanded through a lookup table rather than with BMI2's `pext`,
+// which is microcoded and very slow on AMD before Zen 3, so this is fast on every x86-64 target.
 #[cfg(all(not(target_arch = "wasm32"), not(target_feature = "neon"), not(target_feature = "avx512vbmi2")))]
 pub unsafe fn find_nnz(
     ft_out: &Aligned<[u8; L1_SIZE]>, nnz_table: &[SparseEntry],
@@ -0,0 +1,97 @@
+//! Floating point reference implementation of the network.
+//!
+//! The features are collected straight from the board and every layer is computed in `f32`
+//! without the integer rounding of the feature transformer activation. Comparing it with the
+//! quantized inference measures the quantization error, and a sudden jump in that error
+//! points at a bug in the accumulators or the SIMD kernels.
+
+use super::{
+    DEQUANT_MULTIPLIER, FT_QUANT, FT_SHIFT, L1_SIZE, L2_SIZE, L3_SIZE, NETWORK_SCALE, OUTPUT_BUCKETS_LAYOUT,
+    Parameters, WdlHead,
+    accumulator::{psq::pst_index, threats::threat_index},
+};
+use crate::{board::Board, lookup::attacks, types::Color};
+
+/// Evaluates the position from the side to move's point of view, in the same units as `Network::evaluate`.
+pub fn evaluate(board: &Board, parameters: &Parameters) -> f32 {
+    let (l2_out, bucket) = hidden(board, parameters);
+
+    let l3_out =
+        (0..L3_SIZE).map(|i| parameters.l3_weights[bucket][i] * l2_out[i]).sum::<f32>() + parameters.l3_biases[bucket];
+
+    l3_out * NETWORK_SCALE as f32
+}
+
+/// Win, draw and loss logits of the WDL head for the side to move.
+pub fn evaluate_wdl(board: &Board, parameters: &Parameters, head: &WdlHead) -> [f32; 3] {
+    let (l2_out, bucket) = hidden(board, parameters);
+
+    std::array::from_fn(|k| {
+        (0..L3_SIZE).map(|i| head.weights[bucket][k][i] * l2_out[i]).sum::<f32>() + head.biases[bucket][k]
+    })
+}
+
+/// Computes the activated output of the last hidden layer along with the output bucket.
+fn hidden(board: &Board, parameters: &Parameters) -> ([f32; L3_SIZE], usize) {
+    let stm = board.side_to_move();
+    let accumulators = [transform(board, stm, parameters), transform(board, !stm, parameters)];
+
+    let mut ft_out = [0.0; L1_SIZE];
+    for (flip, accumulator) in accumulators.iter().enumerate() {
+        for i in 0..L1_SIZE / 2 {
+            let left = accumulator[i].clamp(0.0, FT_QUANT as f32);
+            let right = accumulator[i + L1_SIZE / 2].clamp(0.0, FT_QUANT as f32);
+
+            ft_out[i + flip * L1_SIZE / 2] = left * right / (1 << FT_SHIFT) as f32;
+        }
+    }
+
+    let bucket = OUTPUT_BUCKETS_LAYOUT[board.occupancies().popcount()];
+
+    let mut l1_out = [0.0; L2_SIZE];
+    for (j, output) in l1_out.iter_mut().enumerate() {
+        // L1 weights are interleaved in chunks of four consecutive inputs
+        let sum = (0..L1_SIZE)
+            .map(|i| ft_out[i] * parameters.l1_weights[bucket][(i / 4) * L2_SIZE * 4 + j * 4 + i % 4] as f32)
+            .sum::<f32>();
+
+        *output = (sum * DEQUANT_MULTIPLIER + parameters.l1_biases[bucket][j]).clamp(0.0, 1.0);
+    }
+
+    let mut l2_out = parameters.l2_biases[bucket];
+    for (i, &input) in l1_out.iter().enumerate() {
+        for (j, output) in l2_out.iter_mut().enumerate() {
+            *output += parameters.l2_weights[bucket][i][j] * input;
+        }
+    }
+
+    (l2_out.map(|value| value.clamp(0.0, 1.0)), bucket)
+}
+
+/// Sums the feature transformer weights of every active piece and threat feature.
+fn transform(board: &Board, pov: Color, parameters: &Parameters) -> Vec<f32> {
+    let king = board.king_square(pov);
+    let mut accumulator = parameters.ft_biases.iter().map(|&bias| bias as f32).collect::<Vec<_>>();
+
+    for square in board.occupancies() {
+        let piece = board.piece_on(square);
+
+        let index = pst_index(piece.color(), piece.piece_type(), square, king, pov) as usize;
+        for (value, &weight) in accumulator.iter_mut().zip(parameters.ft_piece_weights[index].iter()) {
+            *value += weight as f32;
+        }
+
+        for target in attacks(piece, square, board.occupancies()) & board.occupancies() {
+            let index = threat_index(piece, square, board.piece_on(target), target, king.is_kingside(), pov);
+
+            if index >= 0 {
+                for (value, &weight) in accumulator.iter_mut().zip(parameters.ft_threat_weights[index as usize].iter())
+                {
+                    *value += weight as f32;
+                }
+            }
+        }
+    }
+
+    accumulator
+}
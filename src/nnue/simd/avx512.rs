@@ -74,6 +74,11 @@ pub unsafe fn clamp_f32(x: __m512, min: __m512, max: __m512) -> __m512 {
     _mm512_max_ps(_mm512_min_ps(x, max), min)
 }
 
+#[cfg(target_feature = "avx512vnni")]
+pub unsafe fn add_i32(a: __m512i, b: __m512i) -> __m512i {
+    _mm512_add_epi32(a, b)
+}
+
 #[cfg(target_feature = "avx512vnni")]
 pub unsafe fn dpbusd(i32s: __m512i, u8s: __m512i, i8s: __m512i) -> __m512i {
     _mm512_dpbusd_epi32(i32s, u8s, i8s)
@@ -86,11 +91,6 @@ pub unsafe fn dpbusd(i32s: __m512i, u8s: __m512i, i8s: __m512i) -> __m512i {
     _mm512_add_epi32(i32s, widened)
 }
 
-#[cfg(target_feature = "avx512vnni")]
-pub unsafe fn double_dpbusd(i32s: __m512i, u8s1: __m512i, i8s1: __m512i, u8s2: __m512i, i8s2: __m512i) -> __m512i {
-    _mm512_dpbusd_epi32(_mm512_dpbusd_epi32(i32s, u8s1, i8s1), u8s2, i8s2)
-}
-
 #[cfg(not(target_feature = "avx512vnni"))]
 pub unsafe fn double_dpbusd(i32s: __m512i, u8s1: __m512i, i8s1: __m512i, u8s2: __m512i, i8s2: __m512i) -> __m512i {
     let pairwise1 = _mm512_maddubs_epi16(u8s1, i8s1);
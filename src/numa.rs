@@ -61,6 +61,8 @@ pub struct NumaConfig {
     nodes: Vec<BTreeSet<CpuIndex>>,
     node_by_cpu: BTreeMap<CpuIndex, NumaIndex>,
     highest_cpu_index: CpuIndex,
+    /// Set when the CPUs were chosen by the user, in which case threads are always bound to them.
+    pinned: bool,
 }
 
 impl Default for NumaConfig {
@@ -79,6 +81,7 @@ impl NumaConfig {
             nodes: Vec::new(),
             node_by_cpu: BTreeMap::new(),
             highest_cpu_index: 0,
+            pinned: false,
         }
     }
 
@@ -95,6 +98,21 @@ impl NumaConfig {
         }
     }
 
+    /// Restricts the configuration to a list of CPU ranges such as "0-15,32-47", returning
+    /// `None` if none of them is available to the process.
+    pub fn with_affinity(&self, cpus: &str) -> Option<Self> {
+        let allowed = parse_cpu_indices(&remove_whitespace(cpus.to_string())).into_iter().collect::<BTreeSet<_>>();
+
+        let mut cfg = self.clone();
+        for node in &mut cfg.nodes {
+            node.retain(|cpu| allowed.contains(cpu));
+        }
+        cfg.remove_empty_numa_nodes();
+        cfg.pinned = true;
+
+        (!cfg.nodes.is_empty()).then_some(cfg)
+    }
+
     pub const fn num_numa_nodes(&self) -> NumaIndex {
         self.nodes.len()
     }
@@ -104,6 +122,10 @@ impl NumaConfig {
     }
 
     pub fn suggests_binding_threads(&self, threads: CpuIndex) -> bool {
+        if self.pinned {
+            return true;
+        }
+
         if !self.requires_memory_replication() || threads <= 1 {
             return false;
         }
@@ -270,6 +292,24 @@ fn parse_cpu_indices(cpu_ids: &str) -> Vec<usize> {
         .collect()
 }
 
+/// Formats CPU indices as a list of ranges, the inverse of `parse_cpu_indices`.
+fn format_cpu_indices(cpus: &BTreeSet<CpuIndex>) -> String {
+    let mut ranges = Vec::<(CpuIndex, CpuIndex)>::new();
+
+    for &cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{start}-{end}") })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn remove_whitespace(s: String) -> String {
     s.chars().filter(|c| !c.is_whitespace()).collect()
 }
@@ -302,6 +342,17 @@ impl NumaReplicationContext {
         self.config.read().unwrap().clone()
     }
 
+    /// Replaces the configuration, e.g. with a user defined affinity, and moves the replicated
+    /// objects to the new nodes.
+    pub fn set_numa_config(&self, cfg: NumaConfig) {
+        *self.config.write().unwrap() = cfg;
+
+        let tracked = self.tracked.lock().unwrap().clone();
+        for obj in tracked {
+            obj.on_numa_config_changed();
+        }
+    }
+
     pub fn set_thread_count(&self, threads: usize) {
         let previous = self.thread_count.swap(threads, Ordering::Release);
         if previous == threads {
@@ -317,6 +368,24 @@ impl NumaReplicationContext {
     pub fn get_thread_count(&self) -> usize {
         self.thread_count.load(Ordering::Acquire)
     }
+
+    /// Describes the detected topology and how the given number of threads are placed on it.
+    pub fn describe(&self, threads: usize) -> String {
+        let cfg = self.get_numa_config();
+        let nodes = cfg.nodes.iter().map(format_cpu_indices).collect::<Vec<_>>().join(":");
+
+        if cfg.suggests_binding_threads(threads) {
+            let mut occupation = vec![0; cfg.num_numa_nodes()];
+            for node in cfg.distribute_threads_among_numa_nodes(threads) {
+                occupation[node] += 1;
+            }
+
+            let occupation = occupation.iter().map(usize::to_string).collect::<Vec<_>>().join(":");
+            format!("NUMA nodes: {}, CPUs: {nodes}, threads per node: {occupation}", cfg.num_numa_nodes())
+        } else {
+            format!("NUMA nodes: {}, CPUs: {nodes}, threads not bound", cfg.num_numa_nodes())
+        }
+    }
 }
 
 pub struct NumaReplicated<T: NumaReplicable> {
@@ -340,6 +409,11 @@ impl<T: NumaReplicable> NumaReplicated<T> {
         self.instances.read().unwrap().clone()
     }
 
+    /// Discards the current instances and allocates them again from their source.
+    pub fn refresh(&self) {
+        self.replicate_instances();
+    }
+
     fn replicate_instances(&self) {
         let cfg = self.ctx.get_numa_config();
         let mut instances = Vec::<Arc<T>>::new();
@@ -375,3 +449,27 @@ impl<T: NumaReplicable> NumaReplicatedBase for NumaReplicated<T> {
         self.ctx.get_numa_config()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affinity_restricts_nodes() {
+        let mut cfg = NumaConfig::empty();
+        (0..8).for_each(|cpu| cfg.add_cpu_to_node(0, cpu));
+        (8..16).for_each(|cpu| cfg.add_cpu_to_node(1, cpu));
+        assert!(!cfg.suggests_binding_threads(2));
+
+        let pinned = cfg.with_affinity("2-3, 12").unwrap();
+        assert_eq!(pinned.nodes, [BTreeSet::from([2, 3]), BTreeSet::from([12])]);
+        assert!(pinned.suggests_binding_threads(1));
+
+        let pinned = cfg.with_affinity("9-10").unwrap();
+        assert_eq!(pinned.num_numa_nodes(), 1);
+        assert_eq!(pinned.node_by_cpu[&9], 0);
+
+        assert!(cfg.with_affinity("16-31").is_none());
+        assert!(cfg.with_affinity("junk").is_none());
+    }
+}
@@ -0,0 +1,91 @@
+//! The opponent of the current game, as announced by the GUI with `UCI_Opponent`:
+//!
+//! ```text
+//! setoption name UCI_Opponent value <title> <rating> <computer|human> <name>
+//! ```
+//!
+//! The title and the rating are `none` when they're unknown, e.g. `none none computer Shredder`.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opponent {
+    /// The title, such as `GM` or `IM`.
+    pub title: Option<String>,
+    pub rating: Option<i32>,
+    pub computer: bool,
+    pub name: String,
+}
+
+impl Opponent {
+    /// Rating difference for each point of contempt added by `AdaptiveContempt`.
+    const RATING_PER_CONTEMPT: i32 = 10;
+    const MAX_ADAPTIVE_CONTEMPT: i32 = 50;
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut tokens = value.split_whitespace();
+
+        let title = match tokens.next()? {
+            "none" => None,
+            title => Some(title.to_string()),
+        };
+        let rating = match tokens.next()? {
+            "none" => None,
+            rating => Some(rating.parse().ok()?),
+        };
+        let computer = match tokens.next()? {
+            "computer" => true,
+            "human" => false,
+            _ => return None,
+        };
+        let name = tokens.collect::<Vec<_>>().join(" ");
+
+        Some(Self { title, rating, computer, name })
+    }
+
+    /// Adjusts the contempt by the rating difference: draws are avoided against weaker
+    /// opponents and welcomed against stronger ones. Unrated opponents leave it as it is.
+    pub fn contempt(&self, contempt: i32, own_rating: i32) -> i32 {
+        let Some(rating) = self.rating else {
+            return contempt;
+        };
+
+        let adjustment = ((own_rating - rating) / Self::RATING_PER_CONTEMPT)
+            .clamp(-Self::MAX_ADAPTIVE_CONTEMPT, Self::MAX_ADAPTIVE_CONTEMPT);
+        (contempt + adjustment).clamp(-100, 100)
+    }
+}
+
+impl fmt::Display for Opponent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(title) = &self.title {
+            write!(f, "{title} ")?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(rating) = self.rating {
+            write!(f, " ({rating})")?;
+        }
+        write!(f, ", {}", if self.computer { "computer" } else { "human" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_uci_opponent() {
+        let opponent = Opponent::parse("GM 2800 human Gary Kasparov").unwrap();
+        assert_eq!(opponent.title.as_deref(), Some("GM"));
+        assert_eq!(opponent.rating, Some(2800));
+        assert!(!opponent.computer);
+        assert_eq!(opponent.name, "Gary Kasparov");
+        assert_eq!(opponent.contempt(0, 3100), 30);
+
+        let opponent = Opponent::parse("none none computer Shredder").unwrap();
+        assert_eq!(opponent.contempt(20, 3000), 20);
+        assert_eq!((opponent.title, opponent.rating, opponent.computer), (None, None, true));
+
+        assert_eq!(Opponent::parse("GM 2800 robot Deep Blue"), None);
+    }
+}
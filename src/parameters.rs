@@ -1,4 +1,3 @@
-#[allow(unused_macros)]
 #[cfg(not(feature = "spsa"))]
 macro_rules! define {
     {$($type:ident $name:ident: $value:expr; )*} => {
@@ -19,7 +18,23 @@ macro_rules! define {
         }
 
         pub fn print_options() {
-            $(println!("option name {} type string", stringify!($name));)*
+            $(println!("option name {} type string default {}", stringify!($name), $value);)*
+        }
+
+        /// Prints the parameters in the input format of OpenBench SPSA tunes, searching
+        /// within half the default value on either side.
+        pub fn print_spsa_inputs() {
+            $({
+                let value = $value as f64;
+                let (min, max) = (value - value.abs() / 2.0, value + value.abs() / 2.0);
+                let (kind, digits) = if stringify!($type) == "f32" { ("float", 4) } else { ("int", 1) };
+                println!(
+                    "{}, {kind}, {value:.digits$}, {min:.digits$}, {max:.digits$}, {:.step$}, 0.002",
+                    stringify!($name),
+                    (max - min) / 20.0,
+                    step = digits + 1
+                );
+            })*
         }
 
         $(pub fn $name() -> $type {
@@ -32,3 +47,69 @@ macro_rules! define {
         }
     };
 }
+
+define! {
+    // Time management, see `TimeManager::soft_bound_scale`
+    f32 tm_nodes_base: 3.1838;
+    f32 tm_nodes_scale: 2.6554;
+    f32 tm_nodes_min: 0.5460;
+    f32 tm_score_trend_base: 0.7426;
+    f32 tm_score_trend_scale: 0.0480;
+    f32 tm_score_trend_min: 0.7214;
+    f32 tm_score_trend_max: 1.4031;
+    f32 tm_pv_stability_base: 1.2881;
+    f32 tm_pv_stability_scale: 0.0440;
+    f32 tm_pv_stability_min: 0.7160;
+    f32 tm_eval_stability_base: 1.2664;
+    f32 tm_eval_stability_scale: 0.0416;
+    f32 tm_eval_stability_min: 0.8642;
+    f32 tm_best_move_changes_base: 1.1500;
+    f32 tm_best_move_changes_scale: 0.2526;
+
+    // Move ordering
+    i32 good_noisy_see_divisor: 47;
+    i32 good_noisy_see_offset: 116;
+    i32 noisy_capture_value: 14232;
+    i32 noisy_queen_promotion: 4558;
+    i32 quiet_history_weight: 1763;
+    i32 pawn_history_weight: 1024;
+    i32 conthist1_weight: 1614;
+    i32 conthist2_weight: 1066;
+    i32 conthist4_weight: 1086;
+    i32 conthist6_weight: 1051;
+    i32 quiet_check_bonus: 10723;
+    i32 quiet_threatened_malus: 8875;
+    i32 quiet_offense_bonus: 3446;
+    i32 quiet_wall_pawn_malus: 4494;
+
+    // Move ordering heuristics that are off until they pass an SPRT, and on at any other value
+    i32 counter_move_stage: 0;
+    i32 evasion_stage: 0;
+    i32 low_ply_history_weight: 0;
+    i32 qsearch_quiet_check_stage: 0;
+
+    // Razoring
+    i32 razoring_base: 237;
+    i32 razoring_depth_scale: 254;
+
+    // Reverse futility pruning
+    i32 rfp_depth_squared: 1140;
+    i32 rfp_improvement: 120;
+    i32 rfp_depth: 22;
+    i32 rfp_correction: 669;
+
+    // Null move pruning
+    i32 nmp_base: 337;
+    i32 nmp_depth: 9;
+    i32 nmp_reduction_base: 4407;
+    i32 nmp_reduction_depth: 265;
+
+    // Late move reductions
+    i32 lmr_depth_log: 269;
+    i32 lmr_quiet_base: 2171;
+    i32 lmr_quiet_history: 179;
+    i32 lmr_noisy_base: 1426;
+    i32 lmr_noisy_history: 130;
+    i32 lmr_tt_pv: 333;
+    i32 lmr_cut_node: 1852;
+}
@@ -0,0 +1,66 @@
+//! Seedable pseudorandom numbers for everything stochastic in the engine, such as the played
+//! move under `UCI_LimitStrength` and the openings of `datagen`. Runs are reproducible once
+//! a seed is given with `--seed <n>`, and differ from run to run otherwise.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static SEED: AtomicU64 = AtomicU64::new(0);
+static SEEDED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_seed(seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+    SEEDED.store(true, Ordering::Relaxed);
+}
+
+/// The seed given with `--seed`, or one taken from the clock otherwise.
+pub fn seed() -> u64 {
+    if SEEDED.load(Ordering::Relaxed) {
+        return SEED.load(Ordering::Relaxed);
+    }
+    clock_seed()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn clock_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// There's no clock without JavaScript bindings.
+#[cfg(target_arch = "wasm32")]
+const fn clock_seed() -> u64 {
+    0
+}
+
+/// SplitMix64 generator.
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator for one of several streams from the same seed, such as one per thread.
+    pub const fn for_stream(seed: u64, stream: usize) -> Self {
+        let mut rng = Self::new(seed ^ (stream as u64).wrapping_mul(0xD1B5_4A32_D192_ED03));
+        rng.state = rng.next();
+        rng
+    }
+
+    pub const fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::atomic::Ordering;
 
 use crate::{
-    evaluation::correct_eval,
+    evaluation::{correct_eval, fast_eval},
+    history::LowPlyHistory,
     movepick::{MovePicker, Stage},
+    parameters::*,
     stack::Stack,
-    thread::{PlyArray, RootMove, Status, ThreadData},
-    time::Limits,
+    thread::{CURRLINE_INTERVAL, PlyArray, RootMove, Status, ThreadData},
+    time::{Limits, TimeManager},
     transposition::{Bound, TtDepth},
     types::{
         ArrayVec, Color, MAX_PLY, Move, Piece, PieceType, Score, Square, draw, is_decisive, is_loss, is_valid, is_win,
@@ -19,9 +21,40 @@ use crate::{
     types::{tb_loss_in, tb_win_in},
 };
 
+#[cfg(feature = "tree-trace")]
+use crate::trace::{Outcome, Tracer};
+
 #[allow(unused_imports)]
 use crate::misc::{dbg_hit, dbg_stats};
 
+/// Adds to one of the heuristic counters of the thread. Compiles to nothing without the
+/// `stats` feature.
+macro_rules! stat {
+    ($td:expr, $field:ident += $value:expr) => {
+        #[cfg(feature = "stats")]
+        {
+            $td.stats.$field += $value as u64;
+        }
+    };
+}
+
+/// Records an event into the search tree trace of the thread, if it has one. Compiles to
+/// nothing without the `tree-trace` feature.
+macro_rules! trace {
+    ($td:expr, $event:ident($($arg:expr),* $(,)?)) => {
+        #[cfg(feature = "tree-trace")]
+        if let Some(tracer) = &mut $td.tracer {
+            tracer.$event($($arg),*);
+        }
+    };
+}
+
+/// Shallower nodes are searched too quickly for the threads to get in each other's way.
+const ABDADA_MIN_DEPTH: i32 = 6;
+
+/// How far the material balance must exceed beta for quiescence search to stand pat without the network.
+const LAZY_EVAL_MARGIN: i32 = 900;
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum Report {
     None,
@@ -55,10 +88,25 @@ impl NodeType for NonPV {
 pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
     td.completed_depth = 0;
 
+    #[cfg(feature = "stats")]
+    {
+        td.stats = crate::stats::SearchStats::default();
+    }
+
     td.pv_table.clear(0);
+    td.low_ply_history.clear();
     td.nnue.full_refresh(&td.board);
 
     td.multi_pv = td.multi_pv.min(td.root_moves.len());
+    td.report_currmove = report == Report::Full && td.id == 0 && !td.shared.xboard.load(Ordering::Relaxed);
+    td.report_currline = td.report_currmove && td.shared.show_currline.load(Ordering::Relaxed);
+    td.next_currline = CURRLINE_INTERVAL;
+    td.currline_root = td.report_currline.then(|| td.board.clone());
+
+    // Positive contempt makes draws unattractive for the side to move at the root
+    let contempt = td.shared.contempt.load(Ordering::Relaxed);
+    td.contempt[td.board.side_to_move()] = -contempt;
+    td.contempt[!td.board.side_to_move()] = contempt;
 
     let mut average = vec![td.previous_best_score; td.multi_pv];
     let mut last_best_rootmove = RootMove::default();
@@ -74,13 +122,22 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
         return;
     }
 
+    #[cfg(feature = "tree-trace")]
+    if td.id == 0
+        && let Some(path) = td.shared.tree_trace.lock().unwrap().as_deref()
+    {
+        td.tracer = Tracer::create(path).map_err(|e| eprintln!("Failed to create '{path}': {e}")).ok();
+    }
+
     // Iterative Deepening
     for depth in 1..MAX_PLY as i32 {
         if td.id == 0
             && let Limits::Depth(maximum) = td.time_manager.limits()
             && depth > maximum
         {
-            td.shared.status.set(Status::STOPPED);
+            if !td.shared.pondering.load(Ordering::Relaxed) {
+                td.shared.status.set(Status::STOPPED);
+            }
             break;
         }
 
@@ -95,9 +152,6 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
             rm.previous_score = rm.score;
         }
 
-        let mut delta = 23 - eval_stability.min(pv_stability).min(7);
-        let mut reduction = 0;
-
         for index in 0..td.multi_pv {
             td.pv_index = index;
 
@@ -112,6 +166,12 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
             }
 
             // Aspiration Windows
+            //
+            // Each line gets its own window, so that a wide re-search of one line
+            // doesn't inflate the windows of the lines that follow it.
+            let mut delta = 23 - eval_stability.min(pv_stability).min(7);
+            let mut reduction = 0;
+
             delta += average[td.pv_index] * average[td.pv_index] / 26394;
 
             let mut alpha = (average[td.pv_index] - delta).max(-Score::INFINITE);
@@ -132,7 +192,8 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
                 // Root Search
                 let score = search::<Root>(td, alpha, beta, (depth - reduction).max(1), false, 0);
 
-                td.root_moves[td.pv_index..td.pv_end].sort_by_key(|rm| std::cmp::Reverse(rm.score));
+                td.root_moves[td.pv_index..td.pv_end]
+                    .sort_by_key(|rm| std::cmp::Reverse((rm.score, rm.previous_score)));
 
                 if td.shared.status.get() == Status::STOPPED {
                     break;
@@ -170,7 +231,8 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
                     }
                 }
 
-                td.root_moves[td.pv_start..=td.pv_index].sort_by_key(|rm| std::cmp::Reverse(rm.score));
+                td.root_moves[td.pv_start..=td.pv_index]
+                    .sort_by_key(|rm| std::cmp::Reverse((rm.score, rm.previous_score)));
 
                 if report == Report::Full && td.shared.nodes.aggregate() > 10_000_000 {
                     td.print_uci_info(depth);
@@ -238,29 +300,13 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
             && let Limits::Mate(moves) = td.time_manager.limits()
             && Score::MATE - td.root_moves[0].score.abs() <= moves as i32 * 2
         {
-            td.shared.status.set(Status::STOPPED);
+            if !td.shared.pondering.load(Ordering::Relaxed) {
+                td.shared.status.set(Status::STOPPED);
+            }
             break;
         }
 
-        let multiplier = || {
-            let nodes = {
-                let fraction = td.root_moves[0].nodes as f32 / td.nodes() as f32;
-                (3.1838 - 2.6554 * fraction).max(0.5460)
-            };
-
-            let score_trend = {
-                let difference = (td.previous_best_score - td.root_moves[0].score) as f32;
-                (0.7426 + 0.0480 * difference).clamp(0.7214, 1.4031)
-            };
-
-            let pv_stability = (1.2881 - 0.0440 * pv_stability as f32).max(0.7160);
-
-            let eval_stability = (1.2664 - 0.0416 * eval_stability as f32).max(0.8642);
-
-            let best_move_stability = 1.1500 + (0.2526 * td.best_move_changes as f32).ln_1p();
-
-            nodes * pv_stability * eval_stability * score_trend * best_move_stability
-        };
+        let multiplier = || TimeManager::soft_bound_scale(td, pv_stability, eval_stability);
 
         if td.time_manager.use_time_management() {
             if td.time_manager.soft_limit(td, multiplier) {
@@ -284,10 +330,10 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
         }
     }
 
-    if matches!(td.time_manager.limits(), Limits::Infinite) {
-        while td.shared.status.get() != Status::STOPPED {
-            std::hint::spin_loop();
-        }
+    // The bestmove may only be sent once the GUI either stops the search or confirms the ponder move
+    let infinite = matches!(td.time_manager.limits(), Limits::Infinite);
+    while td.shared.status.get() != Status::STOPPED && (infinite || td.shared.pondering.load(Ordering::Relaxed)) {
+        std::hint::spin_loop();
     }
 
     if report == Report::Minimal {
@@ -295,6 +341,11 @@ pub fn start(td: &mut ThreadData, report: Report, thread_count: usize) {
     }
 
     td.previous_best_score = td.root_moves[0].score;
+
+    #[cfg(feature = "tree-trace")]
+    {
+        td.tracer = None;
+    }
 }
 
 fn search<NODE: NodeType>(
@@ -307,6 +358,7 @@ fn search<NODE: NodeType>(
     let stm = td.board.side_to_move();
     let in_check = td.board.in_check();
     let excluded = td.excluded[ply].is_present();
+    let mate_search = td.time_manager.is_mate_search();
 
     if !NODE::ROOT && NODE::PV {
         td.pv_table.clear(ply as usize);
@@ -318,9 +370,12 @@ fn search<NODE: NodeType>(
 
     // Qsearch Dive
     if depth <= 0 {
-        return qsearch::<NODE>(td, alpha, beta, ply);
+        return qsearch::<NODE>(td, alpha, beta, ply, true);
     }
 
+    trace!(td, node(ply, depth, td.stack[ply - 1].mv, alpha, beta));
+    stat!(td, nodes += 1);
+
     let draw_score = draw(td);
     if !NODE::ROOT && alpha < draw_score && td.board.upcoming_repetition(ply as usize) {
         alpha = draw_score;
@@ -329,15 +384,21 @@ fn search<NODE: NodeType>(
         }
     }
 
-    if NODE::PV {
-        td.sel_depth = td.sel_depth.max(ply as i32);
-    }
+    update_sel_depth(td, ply);
 
     if td.id == 0 && td.time_manager.check_time(td) {
         td.shared.status.set(Status::STOPPED);
         return Score::ZERO;
     }
 
+    if td.report_currline && TimeManager::is_clock_check(td) {
+        let elapsed = td.time_manager.elapsed(td).as_millis();
+        if elapsed >= td.next_currline {
+            td.next_currline = elapsed + CURRLINE_INTERVAL;
+            td.print_currline(ply);
+        }
+    }
+
     if !NODE::ROOT {
         if td.board.is_draw(ply) {
             return draw(td);
@@ -366,6 +427,9 @@ fn search<NODE: NodeType>(
     let hash = td.board.hash();
     let entry = td.shared.tt.read(hash, td.board.fiftymove_clock(), ply);
 
+    stat!(td, tt_probes += 1);
+    stat!(td, tt_hits += entry.is_some());
+
     let mut tt_depth = 0;
     let mut tt_move = Move::NULL;
     let mut tt_score = Score::NONE;
@@ -401,6 +465,7 @@ fn search<NODE: NodeType>(
             }
 
             if td.board.fiftymove_clock() < 90 {
+                trace!(td, prune(ply, depth, Outcome::TtCutoff, Move::NULL, tt_score));
                 return tt_score;
             }
         }
@@ -421,7 +486,7 @@ fn search<NODE: NodeType>(
         let (score, bound) = match outcome {
             tb::GameOutcome::Win => (tb_win_in(ply), Bound::Lower),
             tb::GameOutcome::Loss => (tb_loss_in(ply), Bound::Upper),
-            tb::GameOutcome::Draw => (Score::ZERO, Bound::Exact),
+            tb::GameOutcome::Draw => (td.contempt[stm], Bound::Exact),
         };
 
         if bound == Bound::Exact
@@ -430,6 +495,7 @@ fn search<NODE: NodeType>(
         {
             let depth = (depth + 6).min(MAX_PLY as i32 - 1);
             td.shared.tt.write(hash, depth, Score::NONE, score, bound, Move::NULL, ply, tt_pv, false);
+            trace!(td, prune(ply, depth, Outcome::Tablebase, Move::NULL, score));
             return score;
         }
 
@@ -456,10 +522,10 @@ fn search<NODE: NodeType>(
         raw_eval = Score::NONE;
         eval = td.stack[ply].eval;
     } else if let Some(entry) = &entry {
-        raw_eval = if is_valid(entry.raw_eval) { entry.raw_eval } else { td.nnue.evaluate(&td.board) };
+        raw_eval = if is_valid(entry.raw_eval) { entry.raw_eval } else { evaluate(td) };
         eval = correct_eval(td, raw_eval, correction_value);
     } else {
-        raw_eval = td.nnue.evaluate(&td.board);
+        raw_eval = evaluate(td);
         eval = correct_eval(td, raw_eval, correction_value);
 
         td.shared.tt.write(hash, TtDepth::SOME, raw_eval, Score::NONE, Bound::None, Move::NULL, ply, tt_pv, false);
@@ -533,47 +599,58 @@ fn search<NODE: NodeType>(
 
     let improving = improvement > 0;
 
+    if !in_check {
+        trace!(td, eval(ply, depth, eval, estimated_score));
+    }
+
     // Razoring
     if !NODE::PV
         && !in_check
-        && estimated_score < alpha - 237 - 254 * depth * depth
+        && !mate_search
+        && estimated_score < alpha - razoring_base() - razoring_depth_scale() * depth * depth
         && alpha < 2048
         && !tt_move.is_quiet()
         && tt_bound != Bound::Lower
     {
-        return qsearch::<NonPV>(td, alpha, beta, ply);
+        let score = qsearch::<NonPV>(td, alpha, beta, ply, true);
+        trace!(td, prune(ply, depth, Outcome::Razoring, Move::NULL, score));
+        return score;
     }
 
     // Reverse Futility Pruning (RFP)
     if !tt_pv
         && !in_check
+        && !mate_search
         && !excluded
         && estimated_score
             >= beta
-                + (1140 * depth * depth / 128 - 120 * improvement / 1024
-                    + 22 * depth
-                    + 669 * correction_value.abs() / 1024
+                + (rfp_depth_squared() * depth * depth / 128 - rfp_improvement() * improvement / 1024
+                    + rfp_depth() * depth
+                    + rfp_correction() * correction_value.abs() / 1024
                     - 54 * (td.board.all_threats() & td.board.colors(stm)).is_empty() as i32
                     - 19)
                     .max(2)
         && !is_loss(beta)
         && !is_win(estimated_score)
     {
-        return lerp(estimated_score, beta, 0.6945);
+        let score = lerp(estimated_score, beta, 0.6945);
+        trace!(td, prune(ply, depth, Outcome::ReverseFutility, Move::NULL, score));
+        return score;
     }
 
     // Null Move Pruning (NMP)
     if cut_node
         && !in_check
+        && !mate_search
         && !excluded
         && !potential_singularity
         && estimated_score
             >= beta
-                + (-9 * depth + 110 * tt_pv as i32
+                + (-nmp_depth() * depth + 110 * tt_pv as i32
                     - 94 * improvement / 1024
                     - 21 * (td.cutoff_count[ply + 1] < 2) as i32
-                    + 337)
-                    .max(2)
+                    + nmp_base())
+                .max(2)
         && ply as i32 >= td.nmp_min_ply
         && td.board.material() > 491
         && !is_loss(beta)
@@ -584,8 +661,11 @@ fn search<NODE: NodeType>(
     {
         debug_assert_ne!(td.stack[ply - 1].mv, Move::NULL);
 
-        let r =
-            (4407 + 917 * improving as i32 + 265 * depth + 477 * (estimated_score - beta).clamp(0, 1187) / 128) / 1024;
+        let r = (nmp_reduction_base()
+            + 917 * improving as i32
+            + nmp_reduction_depth() * depth
+            + 477 * (estimated_score - beta).clamp(0, 1187) / 128)
+            / 1024;
 
         td.stack[ply].conthist = td.stack.sentinel().conthist;
         td.stack[ply].contcorrhist = td.stack.sentinel().contcorrhist;
@@ -594,6 +674,7 @@ fn search<NODE: NodeType>(
 
         td.board.make_null_move();
         td.shared.tt.prefetch(td.board.hash());
+        prefetch_correction_histories(td);
 
         let bound = if is_valid(tt_score) && beta > tt_score && tt_bound == Bound::Lower && depth - 2 <= tt_depth {
             tt_score
@@ -609,8 +690,12 @@ fn search<NODE: NodeType>(
             return Score::ZERO;
         }
 
+        stat!(td, null_moves += 1);
+        stat!(td, null_cutoffs += score >= bound && !is_win(score));
+
         if score >= bound && !is_win(score) {
             if (td.nmp_min_ply > 0 || depth < 16) && score >= beta {
+                trace!(td, prune(ply, depth, Outcome::NullMove, Move::NULL, score));
                 return score;
             }
 
@@ -624,7 +709,11 @@ fn search<NODE: NodeType>(
                 return Score::ZERO;
             }
 
+            stat!(td, verifications += 1);
+            stat!(td, verified += verified_score >= beta);
+
             if verified_score >= beta {
+                trace!(td, prune(ply, depth, Outcome::NullMove, Move::NULL, score));
                 return score;
             }
         }
@@ -634,6 +723,7 @@ fn search<NODE: NodeType>(
     let mut probcut_beta = beta + 254 - 85 * improving as i32;
 
     if cut_node
+        && !mate_search
         && !is_win(beta)
         && if is_valid(tt_score) { tt_score >= probcut_beta && !is_decisive(tt_score) } else { eval >= beta }
         && !tt_move.is_quiet()
@@ -651,7 +741,7 @@ fn search<NODE: NodeType>(
 
             make_move(td, ply, mv);
 
-            let mut score = -qsearch::<NonPV>(td, -probcut_beta, -probcut_beta + 1, ply + 1);
+            let mut score = -qsearch::<NonPV>(td, -probcut_beta, -probcut_beta + 1, ply + 1, false);
 
             let base_depth = (depth - 4 - improving as i32).max(0);
             let mut probcut_depth = (base_depth - (score - probcut_beta) / 319).clamp(0, base_depth);
@@ -678,10 +768,9 @@ fn search<NODE: NodeType>(
             if score >= probcut_beta {
                 td.shared.tt.write(hash, probcut_depth + 1, raw_eval, score, Bound::Lower, mv, ply, tt_pv, false);
 
-                if is_decisive(score) {
-                    return score;
-                }
-                return lerp(score, beta, 0.2695);
+                let score = if is_decisive(score) { score } else { lerp(score, beta, 0.2695) };
+                trace!(td, prune(ply, depth, Outcome::ProbCut, mv, score));
+                return score;
             }
         }
     }
@@ -723,7 +812,9 @@ fn search<NODE: NodeType>(
         }
         // Multi-Cut
         else if singular_score >= beta && !is_decisive(singular_score) {
-            return lerp(singular_score, beta, 0.4027);
+            let score = lerp(singular_score, beta, 0.4027);
+            trace!(td, prune(ply, depth, Outcome::MultiCut, Move::NULL, score));
+            return score;
         } else if singular_score > tt_score && td.stack[ply].mv != Move::NULL {
             tt_move = Move::NULL;
         }
@@ -744,16 +835,56 @@ fn search<NODE: NodeType>(
     let mut noisy_moves = ArrayVec::<Move, 32>::new();
 
     let mut move_count = 0;
-    let mut move_picker = MovePicker::new(tt_move, None);
+    let counter_move = match td.stack[ply - 1].mv {
+        mv if counter_move_stage() != 0 && mv.is_present() => td.counter_moves.get(td.stack[ply - 1].piece, mv.to()),
+        _ => Move::NULL,
+    };
+
+    let mut move_picker = MovePicker::new(tt_move, None).with_counter_move(counter_move);
     let mut skip_quiets = false;
     let mut current_search_count = 0;
     let mut tt_move_score = Score::NONE;
 
-    while let Some(mv) = move_picker.next::<NODE>(td, skip_quiets, ply) {
+    // ABDADA: moves leading to positions other threads are busy with are searched last
+    let abdada = !NODE::ROOT && depth >= ABDADA_MIN_DEPTH && td.shared.abdada.load(Ordering::Relaxed);
+    let mut deferred = ArrayVec::<Move, 32>::new();
+    let mut deferred_index = 0;
+    let mut picking = true;
+
+    loop {
+        let next = if picking { move_picker.next::<NODE>(td, skip_quiets, ply) } else { None };
+        let is_deferred = next.is_none();
+
+        let mv = match next {
+            Some(mv) => mv,
+            None if deferred_index < deferred.len() => {
+                picking = false;
+                deferred_index += 1;
+                *deferred.get(deferred_index - 1)
+            }
+            None => break,
+        };
+
+        if is_deferred && skip_quiets && mv.is_quiet() {
+            continue;
+        }
+
         if mv == td.excluded[ply] {
             continue;
         }
 
+        let child_key = if abdada { td.board.key_after(mv) } else { 0 };
+
+        if abdada
+            && !is_deferred
+            && move_count > 0
+            && deferred.len() < 32
+            && td.shared.searching.is_busy(child_key, td.id)
+        {
+            deferred.push(mv);
+            continue;
+        }
+
         if NODE::ROOT && !td.root_moves[td.pv_index..td.pv_end].iter().any(|rm| rm.mv == mv) {
             continue;
         }
@@ -763,6 +894,13 @@ fn search<NODE: NodeType>(
         move_count += 1;
         td.stack[ply].move_count = move_count;
 
+        if NODE::ROOT
+            && td.report_currmove
+            && td.time_manager.elapsed(td).as_millis() >= td.shared.currmove_delay.load(Ordering::Relaxed) as u128
+        {
+            td.print_currmove(mv, move_count as usize + td.pv_index);
+        }
+
         let is_quiet = mv.is_quiet();
         let is_direct_check = td.board.is_direct_check(mv);
 
@@ -782,6 +920,7 @@ fn search<NODE: NodeType>(
                 && move_count as i32
                     >= (2818 + 78 * improvement / 16 + 1351 * depth * depth + 74 * history / 1024) / 1024
             {
+                trace!(td, prune(ply, depth, Outcome::LateMove, mv, history));
                 skip_quiets = true;
                 continue;
             }
@@ -794,10 +933,11 @@ fn search<NODE: NodeType>(
                 + 555 * correction_value.abs() / 1024
                 - 127;
 
-            if !in_check && !is_direct_check && is_quiet && depth < 14 && futility_value <= alpha {
+            if !in_check && !mate_search && !is_direct_check && is_quiet && depth < 14 && futility_value <= alpha {
                 if !is_decisive(best_score) && best_score < futility_value {
                     best_score = futility_value;
                 }
+                trace!(td, prune(ply, depth, Outcome::Futility, mv, futility_value));
                 skip_quiets = true;
                 continue;
             }
@@ -806,19 +946,24 @@ fn search<NODE: NodeType>(
             let noisy_futility_value = eval + 84 * depth + 82 * history / 1024 + 24;
 
             if !in_check
+                && !mate_search
                 && !is_direct_check
                 && depth < 11
+                && !is_deferred
                 && move_picker.stage() == Stage::BadNoisy
                 && noisy_futility_value <= alpha
             {
                 if !is_decisive(best_score) && best_score < noisy_futility_value {
                     best_score = noisy_futility_value;
                 }
-                break;
+                trace!(td, prune(ply, depth, Outcome::BadNoisyFutility, mv, noisy_futility_value));
+                picking = false;
+                continue;
             }
 
             // History Pruning (HP)
             if !in_check && is_quiet && depth < 5 && history < -948 * depth {
+                trace!(td, prune(ply, depth, Outcome::History, mv, history));
                 continue;
             }
 
@@ -830,11 +975,13 @@ fn search<NODE: NodeType>(
             };
 
             if !in_check && !td.board.see(mv, threshold) {
+                trace!(td, prune(ply, depth, Outcome::See, mv, threshold));
                 continue;
             }
         }
 
         let initial_nodes = td.nodes();
+        let entered = abdada && td.shared.searching.enter(child_key, td.id);
 
         make_move(td, ply, mv);
 
@@ -843,7 +990,7 @@ fn search<NODE: NodeType>(
 
         // Late Move Reductions (LMR)
         if depth >= 2 && move_count >= 2 {
-            let mut reduction = 269 * depth.ilog2() as i32;
+            let mut reduction = lmr_depth_log() * depth.ilog2() as i32;
 
             reduction -= (425 * improvement / 128).clamp(-241, 1155);
             reduction -= 3417 * correction_value.abs() / 1024;
@@ -855,12 +1002,12 @@ fn search<NODE: NodeType>(
             reduction += 1024 * is_win(beta) as i32;
 
             if is_quiet {
-                reduction += 2171;
-                reduction -= 179 * history / 1024;
+                reduction += lmr_quiet_base();
+                reduction -= lmr_quiet_history() * history / 1024;
                 reduction += 418 * ((alpha - estimated_score).clamp(-65, 91)) / 128;
             } else {
-                reduction += 1426;
-                reduction -= 130 * history / 1024;
+                reduction += lmr_noisy_base();
+                reduction -= lmr_noisy_history() * history / 1024;
             }
 
             if NODE::PV {
@@ -868,11 +1015,11 @@ fn search<NODE: NodeType>(
             }
 
             if tt_pv {
-                reduction -= 333;
+                reduction -= lmr_tt_pv();
                 reduction -= 611 * (is_valid(tt_score) && tt_score > alpha) as i32;
                 reduction -= 685 * (is_valid(tt_score) && tt_depth >= depth) as i32;
             } else if cut_node {
-                reduction += 1852;
+                reduction += lmr_cut_node();
                 reduction += 2204 * tt_move.is_null() as i32;
             }
 
@@ -903,6 +1050,8 @@ fn search<NODE: NodeType>(
             td.stack[ply].reduction = 0;
             current_search_count += 1;
 
+            stat!(td, reductions += 1);
+
             if score > alpha {
                 if !NODE::ROOT {
                     new_depth += (score > best_score + 57) as i32;
@@ -910,6 +1059,7 @@ fn search<NODE: NodeType>(
                 }
 
                 if new_depth > reduced_depth {
+                    stat!(td, researches += 1);
                     score = -search::<NonPV>(td, -alpha - 1, -alpha, new_depth, !cut_node, ply + 1);
                     current_search_count += 1;
                 }
@@ -976,10 +1126,16 @@ fn search<NODE: NodeType>(
 
         undo_move(td, mv);
 
+        if entered {
+            td.shared.searching.leave(child_key, td.id);
+        }
+
         if td.shared.status.get() == Status::STOPPED {
             return Score::ZERO;
         }
 
+        trace!(td, searched(ply, depth, mv, score));
+
         if NODE::ROOT {
             let current_nodes = td.nodes();
             let root_move = td.root_moves.iter_mut().find(|v| v.mv == mv).unwrap();
@@ -1033,6 +1189,8 @@ fn search<NODE: NodeType>(
                 if score >= beta {
                     bound = Bound::Lower;
                     td.cutoff_count[ply] += 1;
+                    stat!(td, cutoffs += 1);
+                    stat!(td, first_move_cutoffs += move_count == 1);
                     break;
                 }
 
@@ -1084,6 +1242,18 @@ fn search<NODE: NodeType>(
             td.pawn_history.update(td.board.pawn_key(), td.board.moved_piece(best_move), best_move.to(), quiet_bonus);
             update_continuation_histories(td, ply, td.board.moved_piece(best_move), best_move.to(), cont_bonus);
 
+            if low_ply_history_weight() != 0 && ply < LowPlyHistory::PLIES {
+                td.low_ply_history.update(ply, best_move, quiet_bonus);
+
+                for &mv in quiet_moves.iter() {
+                    td.low_ply_history.update(ply, mv, -quiet_malus);
+                }
+            }
+
+            if counter_move_stage() != 0 && best_score >= beta && td.stack[ply - 1].mv.is_present() {
+                td.counter_moves.set(td.stack[ply - 1].piece, td.stack[ply - 1].mv.to(), best_move);
+            }
+
             for (i, &mv) in quiet_moves.iter().enumerate() {
                 let denom = 1024 + 45 * i as i32;
                 let scale = 1024_i32 * 1024 / (denom * denom / 1024);
@@ -1174,15 +1344,28 @@ fn search<NODE: NodeType>(
     debug_assert!(alpha < beta);
     debug_assert!(-Score::INFINITE < best_score && best_score < Score::INFINITE);
 
+    trace!(td, exit(ply, depth, bound, best_move, best_score));
+
     best_score
 }
 
-fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply: isize) -> i32 {
+/// Raises the deepest ply reached by the thread in this iteration, which is shared with the other
+/// threads for the report of the stopped search.
+fn update_sel_depth(td: &mut ThreadData, ply: isize) {
+    if ply as i32 > td.sel_depth {
+        td.sel_depth = ply as i32;
+        td.shared.sel_depth.fetch_max(ply as i32, Ordering::Relaxed);
+    }
+}
+
+fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply: isize, quiet_checks: bool) -> i32 {
     debug_assert!(!NODE::ROOT);
     debug_assert!(ply as usize <= MAX_PLY);
     debug_assert!(-Score::INFINITE <= alpha && alpha < beta && beta <= Score::INFINITE);
     debug_assert!(NODE::PV || alpha == beta - 1);
 
+    stat!(td, qnodes += 1);
+
     let draw_score = draw(td);
     if alpha < draw_score && td.board.upcoming_repetition(ply as usize) {
         alpha = draw_score;
@@ -1195,9 +1378,10 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
 
     if NODE::PV {
         td.pv_table.clear(ply as usize);
-        td.sel_depth = td.sel_depth.max(ply as i32);
     }
 
+    update_sel_depth(td, ply);
+
     if td.id == 0 && td.time_manager.check_time(td) {
         td.shared.status.set(Status::STOPPED);
         return Score::ZERO;
@@ -1236,6 +1420,14 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
         }
     }
 
+    // Lazy evaluation: a material lead this far above beta stands pat without running the network
+    if !NODE::PV && !in_check && entry.is_none() && !is_decisive(beta) && td.shared.lazy_eval.load(Ordering::Relaxed) {
+        let lower_bound = fast_eval(&td.board) - LAZY_EVAL_MARGIN;
+        if lower_bound >= beta {
+            return lower_bound.min(Score::TB_WIN_IN_MAX - 1);
+        }
+    }
+
     let correction_value = eval_correction(td, ply);
 
     let raw_eval;
@@ -1250,7 +1442,7 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
     } else {
         raw_eval = match &entry {
             Some(entry) if is_valid(entry.raw_eval) => entry.raw_eval,
-            _ => td.nnue.evaluate(&td.board),
+            _ => evaluate(td),
         };
         eval = correct_eval(td, raw_eval, correction_value);
         best_score = eval;
@@ -1287,7 +1479,11 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
     let mut best_move = Move::NULL;
 
     let mut move_count = 0;
+    // Only the first ply of the quiescence search looks at quiet checks, which keeps it from running away
     let mut move_picker = MovePicker::new(Move::NULL, None);
+    if quiet_checks && qsearch_quiet_check_stage() != 0 {
+        move_picker = move_picker.with_quiet_checks();
+    }
 
     let skip_quiets = |best_score| !in_check || !is_loss(best_score);
 
@@ -1295,8 +1491,8 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
         move_count += 1;
 
         if !is_loss(best_score) {
-            // Late Move Pruning (LMP)
-            if move_count >= 3 && !td.board.is_direct_check(mv) {
+            // Late Move Pruning (LMP), where the quiet checks count as checks even when discovered
+            if move_count >= 3 && !td.board.is_direct_check(mv) && move_picker.stage() != Stage::QuietCheck {
                 break;
             }
 
@@ -1307,7 +1503,7 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
         }
 
         make_move(td, ply, mv);
-        let score = -qsearch::<NODE>(td, -beta, -alpha, ply + 1);
+        let score = -qsearch::<NODE>(td, -beta, -alpha, ply + 1, false);
         undo_move(td, mv);
 
         if td.shared.status.get() == Status::STOPPED {
@@ -1363,6 +1559,18 @@ fn qsearch<NODE: NodeType>(td: &mut ThreadData, mut alpha: i32, beta: i32, ply:
     best_score
 }
 
+/// Raw network evaluation of the current position, served from the eval cache when possible.
+fn evaluate(td: &mut ThreadData) -> i32 {
+    let hash = td.board.hash();
+    if let Some(eval) = td.eval_cache.get(hash) {
+        return eval;
+    }
+
+    let eval = td.nnue.evaluate(&td.board);
+    td.eval_cache.insert(hash, eval);
+    eval
+}
+
 fn eval_correction(td: &ThreadData, ply: isize) -> i32 {
     let stm = td.board.side_to_move();
     let bucket = td.board.fiftymove_clock_bucket();
@@ -1438,6 +1646,18 @@ fn make_move(td: &mut ThreadData, ply: isize, mv: Move) {
     td.board.make_move(mv, &mut td.nnue);
 
     td.shared.tt.prefetch(td.board.hash());
+    prefetch_correction_histories(td);
+}
+
+/// Warms up the correction history entries that the evaluation of the new position reads.
+fn prefetch_correction_histories(td: &ThreadData) {
+    let stm = td.board.side_to_move();
+    let bucket = td.board.fiftymove_clock_bucket();
+    let corrhist = td.corrhist();
+
+    corrhist.pawn.prefetch(stm, td.board.pawn_key(), bucket);
+    corrhist.non_pawn[Color::White].prefetch(stm, td.board.non_pawn_key(Color::White), bucket);
+    corrhist.non_pawn[Color::Black].prefetch(stm, td.board.non_pawn_key(Color::Black), bucket);
 }
 
 fn undo_move(td: &mut ThreadData, mv: Move) {
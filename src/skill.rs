@@ -0,0 +1,97 @@
+use crate::{
+    rng::Rng,
+    thread::RootMove,
+    time::Limits,
+    types::{Move, Score},
+};
+
+/// Strength limiting for `UCI_LimitStrength`.
+///
+/// The engine plays weaker by searching a smaller tree and by sampling the played
+/// move from the best root moves, with more randomness at lower Elo settings.
+pub struct Skill {
+    elo: i32,
+}
+
+impl Skill {
+    pub const MIN_ELO: i32 = 1320;
+    pub const MAX_ELO: i32 = 3190;
+
+    /// Number of root moves searched so that there's something to choose from.
+    pub const MULTI_PV: usize = 4;
+
+    pub fn new(elo: i32) -> Self {
+        Self { elo: elo.clamp(Self::MIN_ELO, Self::MAX_ELO) }
+    }
+
+    /// The node budget of a move at this strength.
+    pub fn max_nodes(&self) -> u64 {
+        1000 * 2f64.powf((self.elo - Self::MIN_ELO) as f64 / 120.0) as u64
+    }
+
+    /// Restricts the node budget of node limited searches. Timed, depth and mate searches are
+    /// capped by the time manager instead, which keeps their other limits and only starts
+    /// counting on a `ponderhit`, while `go infinite` stays unbounded until the GUI stops it.
+    pub fn limits(&self, limits: Limits) -> Limits {
+        match limits {
+            Limits::Nodes(nodes) => Limits::Nodes(nodes.min(self.max_nodes())),
+            _ => limits,
+        }
+    }
+
+    /// Samples a move from the searched root moves using a softmax over their scores.
+    pub fn pick_move(&self, root_moves: &[RootMove], rng: &mut Rng) -> Move {
+        let temperature = 20.0 + (Self::MAX_ELO - self.elo) as f64 / 5.0;
+        sample_root_move(&root_moves[..root_moves.len().min(Self::MULTI_PV)], temperature, rng)
+    }
+}
+
+/// Opening variety without a book, for `datagen` and casual play.
+///
+/// For the first plies of a game the played move is sampled from a softmax over the root
+/// scores, with the temperature in internal score units.
+#[derive(Copy, Clone, Default)]
+pub struct Temperature {
+    pub temperature: i32,
+    pub plies: usize,
+}
+
+impl Temperature {
+    pub const MAX_TEMPERATURE: i32 = 1000;
+    pub const MAX_PLIES: usize = 1000;
+
+    /// Number of root moves searched so that there's something to choose from.
+    pub const MULTI_PV: usize = 8;
+
+    /// Whether the move at `ply` of the game is sampled.
+    pub const fn is_active(&self, ply: usize) -> bool {
+        self.temperature > 0 && ply < self.plies
+    }
+
+    pub fn pick_move(&self, root_moves: &[RootMove], rng: &mut Rng) -> Move {
+        sample_root_move(&root_moves[..root_moves.len().min(Self::MULTI_PV)], self.temperature as f64, rng)
+    }
+}
+
+fn sample_root_move(root_moves: &[RootMove], temperature: f64, rng: &mut Rng) -> Move {
+    // Moves not reached by the last, unfinished iteration keep their score from the one before
+    let score = |rm: &RootMove| if rm.score != -Score::INFINITE { rm.score } else { rm.previous_score };
+
+    let candidates = root_moves.iter().filter(|rm| score(rm) != -Score::INFINITE).collect::<Vec<_>>();
+    let best = candidates.iter().map(|rm| score(rm)).max().unwrap_or_default() as f64;
+
+    let weights =
+        candidates.iter().map(|rm| (rm.mv, ((score(rm) as f64 - best) / temperature).exp())).collect::<Vec<_>>();
+    let total = weights.iter().map(|(_, weight)| weight).sum::<f64>();
+
+    let mut target = total * rng.next_f64();
+
+    for &(mv, weight) in &weights {
+        if target < weight {
+            return mv;
+        }
+        target -= weight;
+    }
+
+    root_moves[0].mv
+}
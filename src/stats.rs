@@ -0,0 +1,96 @@
+//! Counters of how often the search heuristics apply, built with the `stats` feature. Every
+//! thread keeps its own so that the search doesn't contend on them, and the UCI loop prints
+//! their sum after each search, for example:
+//!
+//! ```text
+//! info string stats nodes 1204566 qnodes 71.3% tthits 48.2% firstcut 88.4% nmpcut 63.0% nmpverified 91.7% lmrresearch 7.9%
+//! ```
+
+use std::{fmt, iter::Sum, ops::AddAssign};
+
+#[derive(Copy, Clone, Default)]
+pub struct SearchStats {
+    /// Nodes of the main search, without the ones of qsearch.
+    pub nodes: u64,
+    pub qnodes: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub cutoffs: u64,
+    /// Beta cutoffs produced by the first move searched.
+    pub first_move_cutoffs: u64,
+    pub null_moves: u64,
+    pub null_cutoffs: u64,
+    /// Verification searches of null move cutoffs at high depths, and how many of them held.
+    pub verifications: u64,
+    pub verified: u64,
+    pub reductions: u64,
+    /// Reduced searches that beat alpha and were searched again at full depth.
+    pub researches: u64,
+}
+
+impl AddAssign<&Self> for SearchStats {
+    fn add_assign(&mut self, other: &Self) {
+        self.nodes += other.nodes;
+        self.qnodes += other.qnodes;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        self.cutoffs += other.cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.null_moves += other.null_moves;
+        self.null_cutoffs += other.null_cutoffs;
+        self.verifications += other.verifications;
+        self.verified += other.verified;
+        self.reductions += other.reductions;
+        self.researches += other.researches;
+    }
+}
+
+impl<'a> Sum<&'a SearchStats> for SearchStats {
+    fn sum<I: Iterator<Item = &'a SearchStats>>(iter: I) -> Self {
+        iter.fold(Self::default(), |mut total, stats| {
+            total += stats;
+            total
+        })
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |part: u64, total: u64| 100.0 * part as f64 / total.max(1) as f64;
+
+        write!(
+            f,
+            "stats nodes {} qnodes {:.1}% tthits {:.1}% firstcut {:.1}% nmpcut {:.1}% nmpverified {:.1}% lmrresearch {:.1}%",
+            self.nodes + self.qnodes,
+            percent(self.qnodes, self.nodes + self.qnodes),
+            percent(self.tt_hits, self.tt_probes),
+            percent(self.first_move_cutoffs, self.cutoffs),
+            percent(self.null_cutoffs, self.null_moves),
+            percent(self.verified, self.verifications),
+            percent(self.researches, self.reductions),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sum_of_threads() {
+        let thread = SearchStats {
+            nodes: 30,
+            qnodes: 70,
+            cutoffs: 4,
+            first_move_cutoffs: 3,
+            ..Default::default()
+        };
+        let total = [thread, thread].iter().sum::<SearchStats>();
+
+        assert_eq!(total.nodes, 60);
+        assert_eq!(
+            total.to_string(),
+            "stats nodes 200 qnodes 70.0% tthits 0.0% firstcut 75.0% nmpcut 0.0% nmpverified 0.0% lmrresearch 0.0%"
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::{
     ops::{Index, IndexMut},
     sync::{
         Arc,
-        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering},
     },
 };
 
@@ -18,21 +18,39 @@ thread_local! {
 pub static WORKERS_REMAINING: AtomicUsize = AtomicUsize::new(0);
 
 use crate::{
-    board::Board,
+    board::{Board, NullBoardObserver},
+    evaluation::EvalCache,
     history::{
-        ContinuationCorrectionHistory, ContinuationHistory, CorrectionHistory, NoisyHistory, PawnHistory, QuietHistory,
+        ContinuationCorrectionHistory, ContinuationHistory, CorrectionHistory, CounterMoves, LowPlyHistory,
+        NoisyHistory, PawnHistory, QuietHistory,
     },
     nnue::{Network, ParametersHandle},
     numa::{NumaConfig, NumaReplicable, NumaReplicated, NumaReplicatedAccessToken, NumaReplicationContext},
+    rng::{self, Rng},
     stack::Stack,
     threadpool::ThreadPool,
-    time::{Limits, TimeManager},
+    time::{Limits, PonderHit, TimeManager},
     transposition::TranspositionTable,
-    types::{MAX_MOVES, MAX_PLY, Move, Score, normalize_to_cp},
+    types::{MAX_MOVES, MAX_PLY, Move, Score, is_decisive, normalize_to_cp, win_draw_loss},
 };
 
+/// Milliseconds into the search after which the root move being searched is reported.
+pub const DEFAULT_CURRMOVE_DELAY: u64 = 3000;
+
+/// Milliseconds between the reports of the line being searched with `UCI_ShowCurrLine`.
+pub const CURRLINE_INTERVAL: u128 = 1000;
+
+/// Moves of a refutation line, the refuted root move included.
+const REFUTATION_LENGTH: usize = 4;
+
 pub trait UciWriter: Send {
     fn write_line(&mut self, line: &str);
+
+    /// Reports search progress, formatted as a UCI `info` line by default.
+    fn write_info(&mut self, info: &SearchInfo) {
+        self.write_line(&info.to_uci());
+    }
+
     #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
     fn take(&mut self) -> String {
         String::new()
@@ -70,6 +88,98 @@ impl UciWriter for BufferWriter {
     }
 }
 
+/// Score of a principal variation as reported to the user.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InfoScore {
+    /// Normalized centipawns, where tablebase wins are reported as scores close to 20000.
+    Cp(i32),
+    /// Moves to mate, negative when the side to move is getting mated.
+    Mate(i32),
+}
+
+impl InfoScore {
+    pub fn new(score: i32, board: &Board) -> Self {
+        match score.abs() {
+            s if s < Score::TB_WIN_IN_MAX => Self::Cp(normalize_to_cp(score, board)),
+            s if s <= Score::TB_WIN => Self::Cp(score.signum() * (20_000 - Score::TB_WIN + score.abs())),
+            _ => {
+                let mate = (Score::MATE - score.abs() + score.is_positive() as i32) / 2;
+                Self::Mate(if score.is_positive() { mate } else { -mate })
+            }
+        }
+    }
+}
+
+/// Progress of a principal variation after a search iteration.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: i32,
+    pub seldepth: i32,
+    pub multipv: usize,
+    pub score: InfoScore,
+    pub wdl: Option<(i32, i32, i32)>,
+    pub upperbound: bool,
+    pub lowerbound: bool,
+    pub nodes: u64,
+    pub time: u128,
+    pub nps: u64,
+    pub hashfull: usize,
+    pub tbhits: u64,
+    pub pv: Vec<String>,
+}
+
+impl SearchInfo {
+    pub fn to_uci(&self) -> String {
+        let mut score = match self.score {
+            InfoScore::Cp(cp) => format!("cp {cp}"),
+            InfoScore::Mate(mate) => format!("mate {mate}"),
+        };
+
+        if let Some((win, draw, loss)) = self.wdl {
+            score.push_str(&format!(" wdl {win} {draw} {loss}"));
+        }
+
+        if self.upperbound {
+            score.push_str(" upperbound");
+        } else if self.lowerbound {
+            score.push_str(" lowerbound");
+        }
+
+        format!(
+            "info depth {} seldepth {} multipv {} score {score} nodes {} time {} nps {} hashfull {} tbhits {} pv {}",
+            self.depth,
+            self.seldepth,
+            self.multipv,
+            self.nodes,
+            self.time,
+            self.nps,
+            self.hashfull,
+            self.tbhits,
+            self.pv.join(" ")
+        )
+    }
+
+    /// Formats the line as a JSON object for `OutputFormat=json`.
+    pub fn to_json(&self) -> String {
+        let score = match self.score {
+            InfoScore::Cp(cp) => format!(r#""cp":{cp}"#),
+            InfoScore::Mate(mate) => format!(r#""mate":{mate}"#),
+        };
+        let wdl = self.wdl.map_or(String::new(), |(win, draw, loss)| format!(r#","wdl":[{win},{draw},{loss}]"#));
+        let bound = match (self.upperbound, self.lowerbound) {
+            (true, _) => r#","bound":"upper""#,
+            (false, true) => r#","bound":"lower""#,
+            (false, false) => "",
+        };
+        let pv = self.pv.iter().map(|mv| format!(r#""{mv}""#)).collect::<Vec<_>>().join(",");
+
+        format!(
+            r#"{{"type":"info","depth":{},"seldepth":{},"multipv":{},"score":{{{score}}}{wdl}{bound},"nodes":{},"time":{},"nps":{},"hashfull":{},"tbhits":{},"pv":[{pv}]}}"#,
+            self.depth, self.seldepth, self.multipv, self.nodes, self.time, self.nps, self.hashfull, self.tbhits,
+        )
+    }
+}
+
 #[repr(align(64))]
 struct AlignedAtomicU64 {
     inner: AtomicU64,
@@ -77,13 +187,19 @@ struct AlignedAtomicU64 {
 
 pub struct Counter {
     shards: Box<[AlignedAtomicU64]>,
+    active: AtomicUsize,
 }
 
 unsafe impl Sync for Counter {}
 
 impl Counter {
     pub fn aggregate(&self) -> u64 {
-        self.shards.iter().map(|shard| shard.inner.load(Ordering::Relaxed)).sum()
+        self.shards[..self.active()].iter().map(|shard| shard.inner.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Number of shards written by the search in progress.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
     }
 
     pub fn get(&self, id: usize) -> u64 {
@@ -94,21 +210,64 @@ impl Counter {
         self.shards[id].inner.store(self.shards[id].inner.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
     }
 
-    pub fn reset(&self) {
+    /// Zeroes the counters and limits aggregation to the shards of the first `threads` threads.
+    pub fn reset(&self, threads: usize) {
         for shard in &self.shards {
             shard.inner.store(0, Ordering::Relaxed);
         }
+        self.active.store(threads.min(self.shards.len()), Ordering::Relaxed);
     }
 }
 
 impl Default for Counter {
     fn default() -> Self {
-        Self {
-            shards: std::iter::from_fn(|| Some(AlignedAtomicU64 { inner: AtomicU64::new(0) }))
-                .take(ThreadPool::available_threads())
-                .collect::<Vec<_>>()
-                .into_boxed_slice(),
-        }
+        let shards = std::iter::from_fn(|| Some(AlignedAtomicU64 { inner: AtomicU64::new(0) }))
+            .take(ThreadPool::available_threads())
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        Self { active: AtomicUsize::new(shards.len()), shards }
+    }
+}
+
+/// Positions being searched right now, shared between the threads for ABDADA: a thread
+/// postpones a move if another thread is already searching the position it leads to.
+/// Each slot holds the upper bits of the key along with the id of the searching thread.
+pub struct SearchingTable {
+    slots: Box<[AtomicU64]>,
+}
+
+impl SearchingTable {
+    const SIZE: usize = 4096;
+    const ID_MASK: u64 = 0xFFFF;
+
+    fn slot(&self, key: u64) -> &AtomicU64 {
+        &self.slots[key as usize & (Self::SIZE - 1)]
+    }
+
+    const fn entry(key: u64, id: usize) -> u64 {
+        (key & !Self::ID_MASK) | (id as u64 + 1)
+    }
+
+    /// Whether a thread other than `id` is searching the position.
+    pub fn is_busy(&self, key: u64, id: usize) -> bool {
+        let entry = self.slot(key).load(Ordering::Relaxed);
+        entry != 0 && (entry ^ key) & !Self::ID_MASK == 0 && entry & Self::ID_MASK != id as u64 + 1
+    }
+
+    /// Marks the position as being searched by `id`, unless its slot is already taken.
+    pub fn enter(&self, key: u64, id: usize) -> bool {
+        self.slot(key).compare_exchange(0, Self::entry(key, id), Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    }
+
+    pub fn leave(&self, key: u64, id: usize) {
+        let _ = self.slot(key).compare_exchange(Self::entry(key, id), 0, Ordering::Relaxed, Ordering::Relaxed);
+    }
+}
+
+impl Default for SearchingTable {
+    fn default() -> Self {
+        Self { slots: (0..Self::SIZE).map(|_| AtomicU64::new(0)).collect() }
     }
 }
 
@@ -160,7 +319,26 @@ pub struct SharedContext {
     pub tb_hits: Counter,
     pub stop_probing_tb: AtomicBool,
     pub root_in_tb: AtomicBool,
+    pub show_wdl: AtomicBool,
+    /// Whether the line searched by the main thread is reported, with `debug on` or `UCI_ShowCurrLine`.
+    pub show_currline: AtomicBool,
+    pub xboard: AtomicBool,
+    /// Whether the output is printed as JSON objects, one per line, instead of UCI lines.
+    pub json: AtomicBool,
+    pub pondering: AtomicBool,
+    pub ponderhit: PonderHit,
+    pub abdada: AtomicBool,
+    pub lazy_eval: AtomicBool,
+    pub searching: SearchingTable,
+    pub contempt: AtomicI32,
+    pub currmove_delay: AtomicU64,
+    pub eval_cache_size: AtomicUsize,
     pub soft_stop_votes: AtomicUsize,
+    /// The deepest ply reached by any of the threads in the current search, qsearch included.
+    pub sel_depth: AtomicI32,
+    /// The file that the main thread traces the search tree into, see `debug tree`.
+    #[cfg(feature = "tree-trace")]
+    pub tree_trace: std::sync::Mutex<Option<String>>,
     pub best_stats: [AtomicU32; MAX_MOVES],
     pub history: Arc<NumaReplicated<SharedCorrectionHistory>>,
     pub parameters: Arc<NumaReplicated<ParametersHandle>>,
@@ -178,7 +356,22 @@ impl Default for SharedContext {
             tb_hits: Counter::default(),
             stop_probing_tb: AtomicBool::new(false),
             root_in_tb: AtomicBool::new(false),
+            show_wdl: AtomicBool::new(false),
+            show_currline: AtomicBool::new(false),
+            xboard: AtomicBool::new(false),
+            json: AtomicBool::new(false),
+            pondering: AtomicBool::new(false),
+            ponderhit: PonderHit::default(),
+            abdada: AtomicBool::new(false),
+            lazy_eval: AtomicBool::new(false),
+            searching: SearchingTable::default(),
+            contempt: AtomicI32::new(0),
+            currmove_delay: AtomicU64::new(DEFAULT_CURRMOVE_DELAY),
+            eval_cache_size: AtomicUsize::new(EvalCache::DEFAULT_SIZE),
             soft_stop_votes: AtomicUsize::new(0),
+            sel_depth: AtomicI32::new(0),
+            #[cfg(feature = "tree-trace")]
+            tree_trace: std::sync::Mutex::new(None),
             best_stats: [const { AtomicU32::new(0) }; MAX_MOVES],
             history: NumaReplicated::new(numa_context.clone()),
             parameters: NumaReplicated::new(numa_context.clone()),
@@ -218,15 +411,19 @@ pub struct ThreadData {
     pub time_manager: TimeManager,
     pub stack: Box<Stack>,
     pub nnue: Network,
+    pub eval_cache: EvalCache,
     pub root_moves: Vec<RootMove>,
     pub pv_table: PrincipalVariationTable,
     pub noisy_history: NoisyHistory,
     pub quiet_history: QuietHistory,
     pub pawn_history: PawnHistory,
     pub continuation_history: ContinuationHistory,
+    pub counter_moves: CounterMoves,
+    pub low_ply_history: LowPlyHistory,
     pub continuation_corrhist: ContinuationCorrectionHistory,
     pub best_move_changes: usize,
     pub optimism: [i32; 2],
+    pub contempt: [i32; 2],
     pub root_depth: i32,
     pub root_delta: i32,
     pub sel_depth: i32,
@@ -239,13 +436,26 @@ pub struct ThreadData {
     pub pv_end: usize,
     pub cutoff_count: PlyArray<i32, { MAX_PLY + 16 }>,
     pub excluded: PlyArray<Move, { MAX_PLY + 16 }>,
+    pub report_currmove: bool,
+    pub report_currline: bool,
+    /// Milliseconds into the search at which the line being searched is reported next.
+    pub next_currline: u128,
+    /// The root position, in which the moves of the reported line are written.
+    pub currline_root: Option<Board>,
     pub writer: Box<dyn UciWriter>,
+    /// Seeded per thread by the thread pool, so that seeded runs are reproducible.
+    pub rng: Rng,
+    #[cfg(feature = "tree-trace")]
+    pub tracer: Option<crate::trace::Tracer>,
+    #[cfg(feature = "stats")]
+    pub stats: crate::stats::SearchStats,
 }
 
 impl ThreadData {
     pub fn new(shared: Arc<SharedContext>, numa_token: NumaReplicatedAccessToken) -> Self {
         let corrhist = shared.history.get(numa_token);
         let parameters = shared.parameters.get(numa_token);
+        let eval_cache = EvalCache::new(shared.eval_cache_size.load(Ordering::Relaxed));
 
         Self {
             id: 0,
@@ -255,15 +465,19 @@ impl ThreadData {
             time_manager: TimeManager::new(Limits::Infinite, 0, 0),
             stack: Stack::new(),
             nnue: Network::new(parameters),
+            eval_cache,
             root_moves: Vec::new(),
             pv_table: PrincipalVariationTable::default(),
             noisy_history: NoisyHistory::default(),
             quiet_history: QuietHistory::default(),
             pawn_history: PawnHistory::default(),
             continuation_history: ContinuationHistory::default(),
+            counter_moves: CounterMoves::default(),
+            low_ply_history: LowPlyHistory::default(),
             continuation_corrhist: ContinuationCorrectionHistory::default(),
             best_move_changes: 0,
             optimism: [0; 2],
+            contempt: [0; 2],
             root_depth: 0,
             root_delta: 0,
             sel_depth: 0,
@@ -276,13 +490,35 @@ impl ThreadData {
             pv_end: 0,
             cutoff_count: PlyArray::default(),
             excluded: PlyArray::default(),
+            report_currmove: false,
+            report_currline: false,
+            next_currline: 0,
+            currline_root: None,
             #[cfg(not(target_arch = "wasm32"))]
             writer: Box::new(StdoutWriter),
             #[cfg(target_arch = "wasm32")]
             writer: Box::new(BufferWriter::default()),
+            rng: Rng::new(rng::seed()),
+            #[cfg(feature = "tree-trace")]
+            tracer: None,
+            #[cfg(feature = "stats")]
+            stats: crate::stats::SearchStats::default(),
         }
     }
 
+    /// Takes over the histories of `previous`, the data of this thread before the pool was resized.
+    /// Everything tied to the NUMA node, like the network and the correction history, stays fresh.
+    pub fn inherit(&mut self, previous: ThreadData) {
+        self.noisy_history = previous.noisy_history;
+        self.quiet_history = previous.quiet_history;
+        self.pawn_history = previous.pawn_history;
+        self.continuation_history = previous.continuation_history;
+        self.counter_moves = previous.counter_moves;
+        self.continuation_corrhist = previous.continuation_corrhist;
+        self.previous_best_score = previous.previous_best_score;
+        self.writer = previous.writer;
+    }
+
     pub fn nodes(&self) -> u64 {
         self.shared.nodes.get(self.id)
     }
@@ -296,14 +532,26 @@ impl ThreadData {
     }
 
     pub fn print_uci_info(&mut self, depth: i32) {
+        if self.shared.xboard.load(Ordering::Relaxed) {
+            self.print_xboard_info(depth);
+            return;
+        }
+
         if self.root_moves.is_empty() {
             self.print_uci_no_move();
             return;
         }
 
-        let elapsed = self.time_manager.elapsed();
-        let nps = self.shared.nodes.aggregate() as f64 / elapsed.as_secs_f64();
-        let ms = elapsed.as_millis();
+        let elapsed = self.time_manager.elapsed(self);
+        let seconds = elapsed.as_secs_f64();
+        let nps = if seconds > 0.0 { self.shared.nodes.aggregate() as f64 / seconds } else { 0.0 };
+
+        // Sampled once, so that all lines of a MultiPV report agree on the occupancy
+        let hashfull = self.shared.tt.hashfull();
+
+        // The report of a stopped search covers all threads, including the helpers that got deeper
+        let stopped = self.shared.status.get() == Status::STOPPED;
+        let shared_sel_depth = if stopped { self.shared.sel_depth.load(Ordering::Relaxed) } else { 0 };
 
         for pv_index in 0..self.multi_pv {
             let root_move = &self.root_moves[pv_index];
@@ -336,51 +584,184 @@ impl ThreadData {
                 }
             }
 
-            let mut formatted_score = match score.abs() {
-                s if s < Score::TB_WIN_IN_MAX => {
-                    format!("cp {}", normalize_to_cp(score, &self.board))
-                }
-                s if s <= Score::TB_WIN => {
-                    let cp = 20_000 - Score::TB_WIN + score.abs();
-                    format!("cp {}", if score.is_positive() { cp } else { -cp })
-                }
-                _ => {
-                    let mate = (Score::MATE - score.abs() + score.is_positive() as i32) / 2;
-                    format!("mate {}", if score.is_positive() { mate } else { -mate })
-                }
+            let info = SearchInfo {
+                depth,
+                seldepth: root_move.sel_depth.max(shared_sel_depth),
+                multipv: pv_index + 1,
+                score: InfoScore::new(score, &self.board),
+                wdl: self.shared.show_wdl.load(Ordering::Relaxed).then(|| self.report_wdl(root_move, score)),
+                upperbound,
+                lowerbound,
+                nodes: self.shared.nodes.aggregate(),
+                time: elapsed.as_millis(),
+                nps: nps as u64,
+                hashfull,
+                tbhits: self.shared.tb_hits.aggregate(),
+                pv: self.principal_variation(root_move, depth).iter().map(|mv| mv.to_uci(&self.board)).collect(),
             };
 
-            if upperbound {
-                formatted_score.push_str(" upperbound");
-            } else if lowerbound {
-                formatted_score.push_str(" lowerbound");
+            if self.shared.json.load(Ordering::Relaxed) {
+                self.writer.write_line(&info.to_json());
+            } else {
+                self.writer.write_info(&info);
             }
+        }
+    }
+
+    /// The principal variation of `root_move`, extended from the transposition table when TT cutoffs
+    /// left it shorter than `depth`.
+    fn principal_variation(&self, root_move: &RootMove, depth: i32) -> Vec<Move> {
+        let mut line = std::iter::once(root_move.mv).chain(root_move.pv.line().iter().copied()).collect::<Vec<_>>();
+        self.extend_from_tt(&mut line, (depth.max(0) as usize).min(MAX_PLY));
+        line
+    }
+
+    /// Extends a line of moves from the root to `length` moves by walking the transposition table
+    /// from its tail. The walk only follows legal moves and ends at a draw, so that it can't run
+    /// in circles.
+    fn extend_from_tt(&self, line: &mut Vec<Move>, length: usize) {
+        if line.len() >= length {
+            return;
+        }
 
-            let mut line = format!(
-                "info depth {depth} seldepth {} multipv {} score {formatted_score} nodes {} time {ms} nps {nps:.0} hashfull {} tbhits {} pv",
-                root_move.sel_depth,
-                pv_index + 1,
-                self.shared.nodes.aggregate(),
-                self.shared.tt.hashfull(),
-                self.shared.tb_hits.aggregate(),
-            );
-
-            line.push_str(&format!(" {}", root_move.mv.to_uci(&self.board)));
-            for mv in root_move.pv.line() {
-                line.push_str(&format!(" {}", mv.to_uci(&self.board)));
+        let mut board = self.board.clone();
+        for &mv in line.iter() {
+            board.make_move(mv, &mut NullBoardObserver);
+        }
+
+        while line.len() < length && !board.is_draw(line.len() as isize) {
+            let Some(entry) = self.shared.tt.read(board.hash(), board.fiftymove_clock(), line.len() as isize) else {
+                break;
+            };
+
+            if !entry.mv.is_present() || !board.generate_all_moves().iter().any(|e| e.mv == entry.mv) {
+                break;
             }
 
-            self.writer.write_line(&line);
+            board.make_move(entry.mv, &mut NullBoardObserver);
+            line.push(entry.mv);
         }
     }
 
-    fn print_uci_no_move(&mut self) {
-        if self.board.in_check() {
-            self.writer.write_line("info depth 0 score mate 0");
-        } else {
-            self.writer.write_line("info depth 0 score cp 0");
+    /// Reports how the root moves below the principal variations are refuted, for
+    /// `UCI_ShowRefutations`. The reply is the cutoff move stored in the transposition table for the
+    /// position after the refuted move, or the line the root move last had when the entry is gone.
+    pub fn print_refutations(&mut self) {
+        let json = self.shared.json.load(Ordering::Relaxed);
+
+        for root_move in self.root_moves.iter().skip(self.multi_pv) {
+            let mut line = vec![root_move.mv];
+            self.extend_from_tt(&mut line, REFUTATION_LENGTH);
+            if line.len() == 1 {
+                line.extend(root_move.pv.line().iter().take(REFUTATION_LENGTH - 1));
+            }
+
+            // A move without a known reply refutes nothing
+            if line.len() == 1 {
+                continue;
+            }
+
+            let moves = line.iter().map(|mv| mv.to_uci(&self.board)).collect::<Vec<_>>();
+            let output = if json {
+                let moves = moves.iter().map(|mv| format!(r#""{mv}""#)).collect::<Vec<_>>().join(",");
+                format!(r#"{{"type":"refutation","line":[{moves}]}}"#)
+            } else {
+                format!("info refutation {}", moves.join(" "))
+            };
+            self.writer.write_line(&output);
         }
     }
+
+    /// Expected win, draw and loss probabilities of the side to move, predicted by the WDL head of the
+    /// network at the end of the principal variation when the network has one and the score isn't decisive.
+    fn report_wdl(&self, root_move: &RootMove, score: i32) -> (i32, i32, i32) {
+        if is_decisive(score) {
+            return win_draw_loss(score, &self.board);
+        }
+
+        let mut board = self.board.clone();
+        let line = std::iter::once(root_move.mv).chain(root_move.pv.line().iter().copied()).collect::<Vec<_>>();
+
+        for &mv in &line {
+            board.make_move(mv, &mut NullBoardObserver);
+        }
+
+        match self.nnue.evaluate_wdl(&board) {
+            Some((win, draw, loss)) if line.len() % 2 == 1 => (loss, draw, win),
+            Some(wdl) => wdl,
+            None => win_draw_loss(score, &self.board),
+        }
+    }
+
+    /// Prints the thinking output of the CECP protocol: `ply score time nodes pv`, with the time
+    /// in centiseconds and mate scores reported as `100000 + moves`.
+    fn print_xboard_info(&mut self, depth: i32) {
+        let Some(root_move) = self.root_moves.first() else {
+            return;
+        };
+
+        let updated = root_move.score != -Score::INFINITE;
+        let depth = if updated { depth } else { (depth - 1).max(1) };
+        let score = if updated { root_move.display_score } else { root_move.previous_score };
+
+        let score = match score.abs() {
+            s if s < Score::TB_WIN_IN_MAX => normalize_to_cp(score, &self.board),
+            s if s <= Score::TB_WIN => score.signum() * (20_000 - Score::TB_WIN + score.abs()),
+            _ => score.signum() * (100_000 + (Score::MATE - score.abs() + 1) / 2),
+        };
+
+        let mut line = format!(
+            "{depth} {score} {} {}",
+            self.time_manager.elapsed(self).as_millis() / 10,
+            self.shared.nodes.aggregate()
+        );
+
+        for mv in self.principal_variation(root_move, depth) {
+            line.push_str(&format!(" {}", mv.to_uci(&self.board)));
+        }
+
+        self.writer.write_line(&line);
+    }
+
+    /// Reports the root move being searched, numbered from one across all principal variations.
+    pub fn print_currmove(&mut self, mv: Move, number: usize) {
+        let mv = mv.to_uci(&self.board);
+        let line = if self.shared.json.load(Ordering::Relaxed) {
+            format!(r#"{{"type":"currmove","depth":{},"currmove":"{mv}","currmovenumber":{number}}}"#, self.root_depth)
+        } else {
+            format!("info depth {} currmove {mv} currmovenumber {number}", self.root_depth)
+        };
+        self.writer.write_line(&line);
+    }
+
+    /// Reports the moves from the root to the node at `ply`, as the line of the first CPU.
+    pub fn print_currline(&mut self, ply: isize) {
+        let root = self.currline_root.as_ref().unwrap_or(&self.board);
+        let line = (0..ply)
+            .map(|ply| match self.stack[ply].mv {
+                mv if mv.is_null() => "0000".to_string(),
+                mv => mv.to_uci(root),
+            })
+            .collect::<Vec<_>>();
+
+        let line = if self.shared.json.load(Ordering::Relaxed) {
+            let moves = line.iter().map(|mv| format!(r#""{mv}""#)).collect::<Vec<_>>().join(",");
+            format!(r#"{{"type":"currline","cpunr":1,"line":[{moves}]}}"#)
+        } else {
+            format!("info currline 1 {}", line.join(" "))
+        };
+        self.writer.write_line(&line);
+    }
+
+    fn print_uci_no_move(&mut self) {
+        let line = match (self.shared.json.load(Ordering::Relaxed), self.board.in_check()) {
+            (true, true) => r#"{"type":"info","depth":0,"score":{"mate":0}}"#,
+            (true, false) => r#"{"type":"info","depth":0,"score":{"cp":0}}"#,
+            (false, true) => "info depth 0 score mate 0",
+            (false, false) => "info depth 0 score cp 0",
+        };
+        self.writer.write_line(line);
+    }
 }
 
 #[derive(Clone)]
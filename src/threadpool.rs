@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     ops::{Index, IndexMut},
     sync::{
         Arc, Condvar, Mutex,
@@ -16,9 +17,11 @@ use crate::tb;
 use crate::{
     board::Board,
     numa::NumaReplicatedAccessToken,
+    rng::{self, Rng},
     search::{self, Report},
     thread::{RootMove, SharedContext, Status, ThreadData},
-    time::TimeManager,
+    time::{Limits, TimeManager},
+    types::{Move, Score, is_decisive, is_loss, is_win},
 };
 
 pub struct ThreadPool {
@@ -40,7 +43,8 @@ impl ThreadPool {
         shared.numa_context.set_thread_count(1);
 
         let workers = make_worker_threads(1);
-        let data = make_thread_data(shared, &workers);
+        let mut data = make_thread_data(shared, &workers);
+        seed_rngs(&mut data);
 
         Self { workers, vector: data }
     }
@@ -54,8 +58,15 @@ impl ThreadPool {
         self.workers.drain(..).for_each(WorkerThread::join);
         self.workers = make_worker_threads(threads);
 
-        std::mem::drop(self.vector.drain(..));
+        // The thread data is rebuilt on the new workers for NUMA locality, but the threads
+        // that remain keep what they have learned so far in the game
+        let previous = std::mem::take(&mut self.vector);
         self.vector = make_thread_data(shared, &self.workers);
+
+        for (td, previous) in self.vector.iter_mut().zip(previous) {
+            td.inherit(previous);
+        }
+        seed_rngs(&mut self.vector);
     }
 
     pub fn main_thread(&mut self) -> &mut ThreadData {
@@ -77,16 +88,112 @@ impl ThreadPool {
 
         std::mem::drop(self.vector.drain(..));
         self.vector = make_thread_data(shared, &self.workers);
+        seed_rngs(&mut self.vector);
+    }
+
+    /// Clears the transposition table by having each worker thread zero its own slice, so that
+    /// the memory is touched by the threads (and NUMA nodes) that will access it during search.
+    pub fn clear_hash(&self) {
+        let shared = &self.vector[0].shared;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::scope(|scope| {
+            let count = self.workers.len();
+            let handlers = self
+                .workers
+                .iter()
+                .enumerate()
+                .map(|(index, worker)| scope.spawn_into(move || shared.tt.clear_slice(index, count), worker))
+                .collect::<Vec<_>>();
+
+            join_all(handlers, || ());
+        });
+
+        #[cfg(target_arch = "wasm32")]
+        shared.tt.clear_slice(0, 1);
+
+        shared.tt.reset_age();
+    }
+
+    /// Runs `f` on every worker thread with the index of the worker, returning once all calls have.
+    pub fn broadcast(&self, f: impl Fn(usize) + Sync) {
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::scope(|scope| {
+            let f = &f;
+            let handlers = self
+                .workers
+                .iter()
+                .enumerate()
+                .map(|(index, worker)| scope.spawn_into(move || f(index), worker))
+                .collect::<Vec<_>>();
+
+            join_all(handlers, || ());
+        });
+
+        #[cfg(target_arch = "wasm32")]
+        f(0);
+    }
+
+    /// Selects the thread whose best move is played, weighting each thread's move by its
+    /// score and completed depth.
+    pub fn best_thread(&self) -> usize {
+        let min_score = self.iter().map(|v| v.root_moves[0].score).min().unwrap();
+        let vote_value = |td: &ThreadData| (td.root_moves[0].score - min_score + 10) * td.completed_depth;
+
+        let mut votes: HashMap<&Move, i32> = HashMap::new();
+        for result in self.iter() {
+            *votes.entry(&result.root_moves[0].mv).or_default() += vote_value(result);
+        }
+
+        let mut best = 0;
+
+        if !matches!(self[best].time_manager.limits(), Limits::Depth(_)) && self[0].multi_pv == 1 {
+            for current in 1..self.len() {
+                let is_better_candidate = || -> bool {
+                    let best = &self[best];
+                    let current = &self[current];
+
+                    if is_win(best.root_moves[0].score) {
+                        return current.root_moves[0].score > best.root_moves[0].score;
+                    }
+
+                    if current.root_moves[0].score != -Score::INFINITE
+                        && best.root_moves[0].score != -Score::INFINITE
+                        && is_loss(best.root_moves[0].score)
+                    {
+                        return current.root_moves[0].score < best.root_moves[0].score;
+                    }
+
+                    if current.root_moves[0].score != -Score::INFINITE && is_decisive(current.root_moves[0].score) {
+                        return true;
+                    }
+
+                    let best_vote = votes[&best.root_moves[0].mv];
+                    let current_vote = votes[&current.root_moves[0].mv];
+
+                    !is_loss(current.root_moves[0].score)
+                        && (current_vote > best_vote
+                            || (current_vote == best_vote && vote_value(current) > vote_value(best)))
+                };
+
+                if is_better_candidate() {
+                    best = current;
+                }
+            }
+        }
+
+        best
     }
 
     pub fn execute_searches(
-        &mut self, time_manager: TimeManager, report: Report, multi_pv: usize, board: &Board,
+        &mut self, time_manager: TimeManager, report: Report, multi_pv: usize, board: &Board, search_moves: &[Move],
         shared: &Arc<SharedContext>,
     ) {
         shared.tt.increment_age();
 
-        shared.nodes.reset();
-        shared.tb_hits.reset();
+        shared.nodes.reset(self.vector.len());
+        shared.tb_hits.reset(self.vector.len());
+        shared.sel_depth.store(0, Ordering::Relaxed);
         shared.soft_stop_votes.store(0, Ordering::Release);
         shared.status.set(Status::RUNNING);
         shared.best_stats.iter().for_each(|x| {
@@ -103,8 +210,7 @@ impl ThreadPool {
             {
                 let t1 = &mut self.vector[0];
                 t1.board = (*board).clone();
-                t1.root_moves =
-                    t1.board.generate_all_moves().iter().map(|v| RootMove { mv: v.mv, ..Default::default() }).collect();
+                t1.root_moves = make_root_moves(&t1.board, search_moves);
                 t1.multi_pv = multi_pv;
                 t1.time_manager = time_manager.clone();
             }
@@ -156,8 +262,7 @@ impl ThreadPool {
             t1.shared.stop_probing_tb.store(false, Ordering::Relaxed);
 
             t1.board = (*board).clone();
-            t1.root_moves =
-                t1.board.generate_all_moves().iter().map(|v| RootMove { mv: v.mv, ..Default::default() }).collect();
+            t1.root_moves = make_root_moves(&t1.board, search_moves);
 
             #[cfg(feature = "syzygy")]
             if t1.board.castling().raw() == 0 && t1.board.occupancies().popcount() <= tb::size() && !t1.board.is_draw(0)
@@ -196,13 +301,22 @@ impl ThreadPool {
                 ));
             }
 
-            for handler in handlers {
-                handler.join();
-            }
+            // A panicking thread can't report back, so the others are stopped before it's propagated
+            join_all(handlers, || shared.status.set(Status::STOPPED));
         });
     }
 }
 
+/// Generates the root moves for a search, restricted to `search_moves` unless it's empty.
+fn make_root_moves(board: &Board, search_moves: &[Move]) -> Vec<RootMove> {
+    board
+        .generate_all_moves()
+        .iter()
+        .filter(|entry| search_moves.is_empty() || search_moves.contains(&entry.mv))
+        .map(|entry| RootMove { mv: entry.mv, ..Default::default() })
+        .collect()
+}
+
 impl Index<usize> for ThreadPool {
     type Output = ThreadData;
 
@@ -217,6 +331,16 @@ impl IndexMut<usize> for ThreadPool {
     }
 }
 
+/// Joins the worker threads, so that the pool can be dropped at any point without leaving
+/// threads behind. Searches run to completion inside `execute_searches`, so the workers are
+/// always idle by then.
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.workers.drain(..).for_each(WorkerThread::join);
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub struct WorkerThread {
     handle: std::thread::JoinHandle<()>,
@@ -231,24 +355,29 @@ impl WorkerThread {
     }
 }
 
+/// Outcome of the last task sent to a worker thread, `None` while it's still running.
+/// A panic is caught by the worker and its payload stored here instead of killing the thread,
+/// which would otherwise leave the pool waiting forever for the completion signal.
+type CompletionSignal = Arc<(Mutex<Option<std::thread::Result<()>>>, Condvar)>;
+
 // Handle for communicating with a worker thread.
 // Contains a sender for sending messages to the worker thread,
 // and a receiver for receiving messages from the worker thread.
 struct WorkSender {
     // INVARIANT: Each send must be matched by a receive.
     sender: SyncSender<Box<dyn FnOnce() + Send>>,
-    completion_signal: Arc<(Mutex<bool>, Condvar)>,
+    completion_signal: CompletionSignal,
 }
 
 /// Handle for the receiver side of a worker thread.
 struct WorkReceiver {
     receiver: Receiver<Box<dyn FnOnce() + Send>>,
-    completion_signal: Arc<(Mutex<bool>, Condvar)>,
+    completion_signal: CompletionSignal,
 }
 
 fn make_work_channel() -> (WorkSender, WorkReceiver) {
     let (sender, receiver) = std::sync::mpsc::sync_channel(0);
-    let completion_signal = Arc::new((Mutex::new(false), Condvar::new()));
+    let completion_signal = Arc::new((Mutex::new(None), Condvar::new()));
 
     (
         WorkSender { sender, completion_signal: Arc::clone(&completion_signal) },
@@ -257,19 +386,43 @@ fn make_work_channel() -> (WorkSender, WorkReceiver) {
 }
 
 pub struct ReceiverHandle<'scope> {
-    completion_signal: &'scope Arc<(Mutex<bool>, Condvar)>,
+    completion_signal: &'scope CompletionSignal,
     received: bool,
 }
 
 impl ReceiverHandle<'_> {
-    pub fn join(mut self) {
+    /// Waits for the task to finish, returning the panic payload if it panicked.
+    pub fn join(mut self) -> std::thread::Result<()> {
         let (lock, cvar) = &**self.completion_signal;
         let mut completed = lock.lock().unwrap();
-        while !*completed {
-            completed = cvar.wait(completed).unwrap();
-        }
+        let result = loop {
+            match completed.take() {
+                Some(result) => break result,
+                None => completed = cvar.wait(completed).unwrap(),
+            }
+        };
         drop(completed);
         self.received = true;
+        result
+    }
+}
+
+/// Waits for every task, then resumes the first panic among them on the calling thread.
+/// Tasks borrow from the enclosing scope, so none may be left running when unwinding starts;
+/// `on_panic` is called as soon as a panic is seen to make the remaining tasks finish early.
+#[cfg(not(target_arch = "wasm32"))]
+fn join_all(handles: Vec<ReceiverHandle>, on_panic: impl Fn()) {
+    let mut panic = None;
+
+    for handle in handles {
+        if let Err(payload) = handle.join() {
+            on_panic();
+            panic.get_or_insert(payload);
+        }
+    }
+
+    if let Some(payload) = panic {
+        std::panic::resume_unwind(payload);
     }
 }
 
@@ -301,7 +454,7 @@ impl<'scope, 'env> ScopeExt<'scope, 'env> for Scope<'scope, 'env> {
         {
             let (lock, _) = &*thread.comms.completion_signal;
             let mut completed = lock.lock().unwrap();
-            *completed = false;
+            *completed = None;
         }
 
         thread.comms.sender.send(f).expect("Failed to send function to worker thread");
@@ -320,10 +473,11 @@ fn make_worker_thread() -> WorkerThread {
 
     let handle = std::thread::spawn(move || {
         while let Ok(work) = receiver.receiver.recv() {
-            work();
+            // The panic message has already been printed by the hook at this point
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(work));
             let (lock, cvar) = &*receiver.completion_signal;
             let mut completed = lock.lock().unwrap();
-            *completed = true;
+            *completed = Some(result);
             drop(completed); // Release the lock before notifying
             cvar.notify_one();
         }
@@ -367,17 +521,22 @@ fn make_thread_data(shared: Arc<SharedContext>, worker_threads: &[WorkerThread])
             })
             .collect::<Vec<_>>();
 
-        let mut thread_data: Vec<ThreadData> = Vec::with_capacity(handles.len());
-        for (rx, handle) in handles {
-            let td = rx.recv().unwrap();
-            thread_data.push(*td);
-            handle.join();
-        }
+        let (receivers, handles): (Vec<_>, Vec<_>) = handles.into_iter().unzip();
+        join_all(handles, || ());
 
-        thread_data
+        receivers.into_iter().map(|rx| *rx.recv().unwrap()).collect()
     })
 }
 
+/// Gives every thread its own stream of the seed, restarted whenever the threads are rebuilt
+/// so that a new game plays the same as a fresh engine would.
+fn seed_rngs(data: &mut [ThreadData]) {
+    let seed = rng::seed();
+    for (index, td) in data.iter_mut().enumerate() {
+        td.rng = Rng::for_stream(seed, index);
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 pub struct WorkerThread;
 
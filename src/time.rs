@@ -1,10 +1,13 @@
-use std::time::Duration;
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 #[cfg(target_arch = "wasm32")]
 use web_time::Instant;
 
-use crate::thread::ThreadData;
+use crate::{parameters::*, thread::ThreadData, types::Color};
 
 #[derive(Clone, Debug)]
 pub enum Limits {
@@ -12,19 +15,178 @@ pub enum Limits {
     Depth(i32),
     Time(u64),
     Nodes(u64),
+    /// A soft node limit checked between iterations and a hard one checked during them.
+    SoftNodes(u64, u64),
     Mate(u64),
     Fischer(u64, u64),
-    Cyclic(u64, u64, u64),
+    /// The remaining time, the increment, the moves to the next time control and the moves
+    /// of a whole period of the repeating control.
+    Cyclic(u64, u64, u64, u64),
 }
 
 const TIME_OVERHEAD_MS: u64 = 15;
 
+/// The share of a period of a repeating time control that is kept in reserve, spread over the
+/// moves left to the control, so that it matters most right before it.
+const CYCLIC_RESERVE: f64 = 0.25;
+
+/// How far past a soft node limit an iteration may run before it's stopped, unless the hard
+/// limit is given.
+const SOFT_NODES_HARD_FACTOR: u64 = 8;
+
+impl Limits {
+    /// A soft node limit with the default hard limit.
+    pub const fn soft_nodes(soft: u64) -> Self {
+        Self::SoftNodes(soft, SOFT_NODES_HARD_FACTOR * soft)
+    }
+}
+
+/// Parses the limits of a `go` command for the side to move.
+pub fn parse_limits(color: Color, tokens: &[&str]) -> Limits {
+    if let ["infinite"] = tokens {
+        return Limits::Infinite;
+    }
+
+    let mut main = None;
+    let mut inc = None;
+    let mut moves = None;
+    let mut nodes = None;
+    let mut soft_nodes = None;
+
+    for chunk in tokens.chunks(2) {
+        if let [name, value] = *chunk {
+            let Ok(value) = value.parse::<u64>() else {
+                continue;
+            };
+
+            match name {
+                "depth" if value > 0 => return Limits::Depth(value as i32),
+                "movetime" if value > 0 => return Limits::Time(value),
+                "nodes" if value > 0 => nodes = Some(value),
+                "softnodes" if value > 0 => soft_nodes = Some(value),
+                "mate" if value > 0 => return Limits::Mate(value),
+
+                "wtime" if Color::White == color => main = Some(value),
+                "btime" if Color::Black == color => main = Some(value),
+                "winc" if Color::White == color => inc = Some(value),
+                "binc" if Color::Black == color => inc = Some(value),
+                "movestogo" => moves = Some(value),
+
+                _ => continue,
+            }
+        }
+    }
+
+    match (soft_nodes, nodes) {
+        (Some(soft), Some(hard)) => return Limits::SoftNodes(soft.min(hard), hard),
+        (Some(soft), None) => return Limits::soft_nodes(soft),
+        (None, Some(hard)) => return Limits::Nodes(hard),
+        (None, None) => (),
+    }
+
+    if main.is_none() && inc.is_none() {
+        return Limits::Infinite;
+    }
+
+    let main = main.unwrap_or_default();
+    let inc = inc.unwrap_or_default();
+
+    match moves {
+        Some(moves) => Limits::Cyclic(main, inc, moves, moves),
+        None => Limits::Fischer(main, inc),
+    }
+}
+
+/// The clock of a game played with a virtual time (`nodestime`). The clock of the GUI runs on
+/// the wall clock, so it's only read for the first search of the game, and the remaining time
+/// is kept from the nodes searched since.
+#[derive(Default)]
+pub struct VirtualClock {
+    /// The time of the first search, which is also added back at every `movestogo` control.
+    initial: Option<u64>,
+    remaining: u64,
+}
+
+impl VirtualClock {
+    /// Replaces the remaining time of a time control with the one of the virtual clock.
+    pub fn limits(&mut self, limits: Limits) -> Limits {
+        match limits {
+            Limits::Fischer(main, inc) => Limits::Fischer(self.remaining(main), inc),
+            Limits::Cyclic(main, inc, moves, period) => Limits::Cyclic(self.remaining(main), inc, moves, period),
+            _ => limits,
+        }
+    }
+
+    fn remaining(&mut self, main: u64) -> u64 {
+        if self.initial.is_none() {
+            self.initial = Some(main);
+            self.remaining = main;
+        }
+        self.remaining
+    }
+
+    /// Charges a search of `elapsed` virtual milliseconds under `limits` to the clock.
+    pub fn charge(&mut self, limits: &Limits, elapsed: u64) {
+        match *limits {
+            Limits::Fischer(_, inc) => self.remaining = self.remaining.saturating_sub(elapsed) + inc,
+            Limits::Cyclic(_, inc, moves, _) => {
+                self.remaining = self.remaining.saturating_sub(elapsed) + inc;
+                if moves == 1 {
+                    self.remaining += self.initial.unwrap_or_default();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The moment a ponder search turned into a normal one with `ponderhit`, in nanoseconds since
+/// `epoch`, or zero while there was none. Every time check reads it, so it takes no lock.
+pub struct PonderHit {
+    epoch: Instant,
+    nanos: AtomicU64,
+}
+
+impl PonderHit {
+    pub fn record(&self) {
+        let nanos = self.epoch.elapsed().as_nanos() as u64;
+        self.nanos.store(nanos.max(1), Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.nanos.store(0, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Option<Instant> {
+        match self.nanos.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(self.epoch + Duration::from_nanos(nanos)),
+        }
+    }
+}
+
+impl Default for PonderHit {
+    fn default() -> Self {
+        Self { epoch: Instant::now(), nanos: AtomicU64::new(0) }
+    }
+}
+
 #[derive(Clone)]
 pub struct TimeManager {
     limits: Limits,
     start_time: Instant,
     soft_bound: Duration,
     hard_bound: Duration,
+    /// Nodes per millisecond of a virtual clock, or zero for the wall clock.
+    nodes_time: u64,
+    /// The share of the time spent pondering before a `ponderhit` that counts against the bounds.
+    ponder_factor: f32,
+    /// Nodes after which a timed search stops, such as the budget of `UCI_LimitStrength`.
+    max_nodes: u64,
 }
 
 impl TimeManager {
@@ -34,8 +196,8 @@ impl TimeManager {
 
         match limits {
             Limits::Time(ms) => {
-                soft = ms;
-                hard = ms;
+                soft = ms.saturating_sub(move_overhead);
+                hard = ms.saturating_sub(move_overhead);
             }
             Limits::Fischer(main, inc) => {
                 let soft_scale = 0.0594 - 0.0492 * (-0.0386 * fullmove_number as f64).exp();
@@ -47,12 +209,17 @@ impl TimeManager {
                 soft = soft_bound.min(main.saturating_sub(move_overhead));
                 hard = hard_bound.min(main.saturating_sub(move_overhead));
             }
-            Limits::Cyclic(main, inc, moves) => {
+            Limits::Cyclic(main, inc, moves, period) => {
                 let main = main.saturating_sub(move_overhead);
-                let base = (main as f64 / moves as f64) + 0.75 * inc as f64;
 
-                soft = ((1.0 * base) as u64).min(main + inc);
-                hard = ((5.0 * base) as u64).min(main + inc);
+                // The time left at a control is banked for the next period, so the last moves
+                // before it keep a reserve instead of spending everything
+                let moves = moves.max(1) as f64;
+                let moves = moves + CYCLIC_RESERVE * period as f64 / moves;
+                let base = (main as f64 / moves) + 0.75 * inc as f64;
+
+                soft = ((1.0 * base) as u64).min(main);
+                hard = ((5.0 * base) as u64).min(main);
             }
             _ => {
                 soft = u64::MAX;
@@ -65,38 +232,148 @@ impl TimeManager {
             start_time: Instant::now(),
             soft_bound: Duration::from_millis(soft.saturating_sub(TIME_OVERHEAD_MS)),
             hard_bound: Duration::from_millis(hard.saturating_sub(TIME_OVERHEAD_MS)),
+            nodes_time: 0,
+            ponder_factor: 1.0,
+            max_nodes: u64::MAX,
         }
     }
 
-    pub fn elapsed(&self) -> Duration {
-        self.start_time.elapsed()
+    /// Measures the time with the nodes searched by all threads instead of the wall clock,
+    /// counting every `nodes_time` nodes as a millisecond, so that time controls are played
+    /// the same regardless of the hardware. Zero keeps the wall clock.
+    pub const fn with_nodes_time(mut self, nodes_time: u64) -> Self {
+        self.nodes_time = nodes_time;
+        self
+    }
+
+    /// Credits the time spent pondering before a `ponderhit` by `factor` instead of counting
+    /// all of it, as the clock of the engine only starts on the hit. The hard bound is always
+    /// measured from the hit, and the soft bound from it plus the credited ponder time.
+    pub const fn with_ponder_factor(mut self, factor: f32) -> Self {
+        self.ponder_factor = factor;
+        self
+    }
+
+    /// Stops timed, depth and mate searches after `max_nodes` nodes as well.
+    pub const fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    pub fn elapsed(&self, td: &ThreadData) -> Duration {
+        match (td.shared.nodes.aggregate() * 1000).checked_div(self.nodes_time) {
+            Some(micros) => Duration::from_micros(micros),
+            None => self.start_time.elapsed(),
+        }
     }
 
     pub fn soft_limit(&self, td: &ThreadData, multiplier: impl Fn() -> f32) -> bool {
+        if td.shared.pondering.load(Ordering::Relaxed) {
+            return false;
+        }
+
         match self.limits {
             Limits::Infinite | Limits::Depth(_) | Limits::Mate(_) => false,
-            Limits::Nodes(maximum) => td.shared.nodes.aggregate() >= maximum,
-            Limits::Time(maximum) => self.start_time.elapsed() >= Duration::from_millis(maximum),
-            _ => self.start_time.elapsed() >= Duration::from_secs_f32(self.soft_bound.as_secs_f32() * multiplier()),
+            Limits::Nodes(maximum) | Limits::SoftNodes(maximum, _) => td.shared.nodes.aggregate() >= maximum,
+            Limits::Time(_) => self.search_time(td).1 >= self.hard_bound,
+            _ => self.search_time(td).0 >= Duration::from_secs_f32(self.soft_bound.as_secs_f32() * multiplier()),
+        }
+    }
+
+    /// The time counted against the soft and the hard bound.
+    fn search_time(&self, td: &ThreadData) -> (Duration, Duration) {
+        let elapsed = self.elapsed(td);
+        if self.nodes_time > 0 {
+            return (elapsed, elapsed);
         }
+
+        match td.shared.ponderhit.get() {
+            Some(hit) => {
+                let since_hit = hit.elapsed();
+                let pondered = hit.saturating_duration_since(self.start_time);
+                (since_hit + pondered.mul_f32(self.ponder_factor), since_hit)
+            }
+            None => (elapsed, elapsed),
+        }
+    }
+
+    /// Scales the soft bound by the feedback of the completed iterations, given the number of
+    /// consecutive iterations with the same best move and with a similar score. The result can
+    /// exceed one, but the search is still stopped at the hard bound.
+    pub fn soft_bound_scale(td: &ThreadData, pv_stability: i32, eval_stability: i32) -> f32 {
+        Self::node_fraction_scale(td)
+            * Self::score_trend_scale(td)
+            * (tm_pv_stability_base() - tm_pv_stability_scale() * pv_stability as f32).max(tm_pv_stability_min())
+            * (tm_eval_stability_base() - tm_eval_stability_scale() * eval_stability as f32)
+                .max(tm_eval_stability_min())
+            * Self::best_move_changes_scale(td)
+    }
+
+    /// Scales the soft bound by the share of the root nodes spent on the best move: a move
+    /// that took most of the effort has held up against the alternatives, so the search can
+    /// stop early, while a low share means that others came close and deserve more time.
+    pub fn node_fraction_scale(td: &ThreadData) -> f32 {
+        let nodes = td.nodes();
+        if nodes == 0 {
+            return 1.0;
+        }
+
+        let fraction = td.root_moves[0].nodes as f32 / nodes as f32;
+        (tm_nodes_base() - tm_nodes_scale() * fraction).max(tm_nodes_min())
+    }
+
+    /// Extends the soft bound when the score drops from the previous move, and shrinks it
+    /// when the score rises.
+    fn score_trend_scale(td: &ThreadData) -> f32 {
+        let difference = (td.previous_best_score - td.root_moves[0].score) as f32;
+        (tm_score_trend_base() + tm_score_trend_scale() * difference).clamp(tm_score_trend_min(), tm_score_trend_max())
+    }
+
+    /// Extends the soft bound when the best move changed during the last iteration.
+    fn best_move_changes_scale(td: &ThreadData) -> f32 {
+        tm_best_move_changes_base() + (tm_best_move_changes_scale() * td.best_move_changes as f32).ln_1p()
     }
 
     pub fn check_time(&self, td: &ThreadData) -> bool {
-        if td.completed_depth == 0 {
+        if td.completed_depth == 0 || td.shared.pondering.load(Ordering::Relaxed) {
             return false;
         }
 
         match self.limits {
-            Limits::Infinite | Limits::Depth(_) | Limits::Mate(_) => false,
-            Limits::Nodes(maximum) => td.shared.nodes.aggregate() > maximum,
-            _ => td.nodes() & 2047 == 2047 && self.start_time.elapsed() >= self.hard_bound,
+            Limits::Infinite => false,
+            // Summing the counters of all threads on every node would slow down the main thread,
+            // so the check is batched unless the count has to be exact for a single thread
+            Limits::Nodes(maximum) | Limits::SoftNodes(_, maximum) => {
+                (td.shared.nodes.active() == 1 || td.nodes() & 255 == 0) && td.shared.nodes.aggregate() > maximum
+            }
+            Limits::Depth(_) | Limits::Mate(_) => {
+                self.max_nodes != u64::MAX
+                    && (td.shared.nodes.active() == 1 || td.nodes() & 255 == 0)
+                    && td.shared.nodes.aggregate() >= self.max_nodes
+            }
+            _ => {
+                Self::is_clock_check(td)
+                    && (self.search_time(td).1 >= self.hard_bound || td.shared.nodes.aggregate() >= self.max_nodes)
+            }
         }
     }
 
+    /// Whether the clock is read at this node of the main thread. Reading it on every node would
+    /// slow down the search, so it's done every 2048 nodes.
+    pub fn is_clock_check(td: &ThreadData) -> bool {
+        td.nodes() & 2047 == 2047
+    }
+
     pub fn limits(&self) -> Limits {
         self.limits.clone()
     }
 
+    /// Whether the search is looking for a forced mate (`go mate N`), in which case
+    /// pruning based on the static evaluation is disabled so that it can't hide mates.
+    pub const fn is_mate_search(&self) -> bool {
+        matches!(self.limits, Limits::Mate(_))
+    }
+
     pub fn use_time_management(&self) -> bool {
         matches!(self.limits, Limits::Fischer(..) | Limits::Cyclic(..) | Limits::Time(_))
     }
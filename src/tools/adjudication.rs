@@ -0,0 +1,206 @@
+//! Adjudication of self-play and match games, which would otherwise be played out long after
+//! their result is clear. The rules are set with `<key>=<value>` arguments:
+//!
+//! - `win_score` and `win_plies`: a win once the score stays beyond the threshold in favor of
+//!   the same side for that many consecutive plies.
+//! - `draw_score`, `draw_plies` and `draw_min_ply`: a draw once the game is long enough and the
+//!   score stays within the window for that many consecutive plies.
+//! - `max_plies`: a draw at this game length, with `0` for no limit.
+//! - `tb`: the path of Syzygy tablebases, which decide positions as soon as they're covered.
+//!
+//! Scores are from White's point of view. A rule with a threshold of zero plies is disabled.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::board::Board;
+
+#[derive(Copy, Clone)]
+pub(super) struct Rules {
+    win_score: i32,
+    win_plies: i32,
+    draw_score: i32,
+    draw_plies: usize,
+    draw_min_ply: usize,
+    max_plies: usize,
+    tablebases: bool,
+}
+
+impl Rules {
+    pub(super) const DATAGEN: Self = Self {
+        win_score: 2000,
+        win_plies: 4,
+        draw_score: 10,
+        draw_plies: 8,
+        draw_min_ply: 80,
+        max_plies: 0,
+        tablebases: false,
+    };
+
+    pub(super) const MATCH: Self = Self {
+        win_score: 1000,
+        win_plies: 4,
+        draw_score: 10,
+        draw_plies: 8,
+        draw_min_ply: 80,
+        max_plies: 400,
+        tablebases: false,
+    };
+
+    /// Applies a `<key>=<value>` argument, returning `None` if the key isn't an adjudication rule.
+    pub(super) fn parse(&mut self, key: &str, value: &str) -> Option<Result<(), String>> {
+        Some(match key {
+            "win_score" => parse_into(&mut self.win_score, key, value),
+            "win_plies" => parse_into(&mut self.win_plies, key, value),
+            "draw_score" => parse_into(&mut self.draw_score, key, value),
+            "draw_plies" => parse_into(&mut self.draw_plies, key, value),
+            "draw_min_ply" => parse_into(&mut self.draw_min_ply, key, value),
+            "max_plies" => parse_into(&mut self.max_plies, key, value),
+            "tb" => self.load_tablebases(value),
+            _ => return None,
+        })
+    }
+
+    #[cfg(feature = "syzygy")]
+    fn load_tablebases(&mut self, path: &str) -> Result<(), String> {
+        match crate::tb::initialize(path) {
+            Some(size) => {
+                println!("Loaded Syzygy tablebases with {size} pieces");
+                self.tablebases = true;
+                Ok(())
+            }
+            None => Err(format!("Failed to load Syzygy tablebases from '{path}'")),
+        }
+    }
+
+    #[cfg(not(feature = "syzygy"))]
+    fn load_tablebases(&mut self, _: &str) -> Result<(), String> {
+        Err("Tablebase adjudication requires the 'syzygy' feature".to_string())
+    }
+}
+
+fn parse_into<T: std::str::FromStr>(field: &mut T, key: &str, value: &str) -> Result<(), String> {
+    *field = value.parse().map_err(|_| format!("Invalid value for '{key}': '{value}'"))?;
+    Ok(())
+}
+
+#[derive(Copy, Clone)]
+pub(super) enum Rule {
+    Win,
+    Draw,
+    Tablebase,
+    MaxLength,
+}
+
+impl Rule {
+    const ALL: [Self; 4] = [Self::Win, Self::Draw, Self::Tablebase, Self::MaxLength];
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Win => "adjudicated win",
+            Self::Draw => "adjudicated draw",
+            Self::Tablebase => "tablebase",
+            Self::MaxLength => "maximum length",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Tracks the scores of a single game against the rules.
+pub(super) struct Adjudicator {
+    rules: Rules,
+    /// Consecutive plies beyond the win threshold, negative when in favor of Black.
+    win_plies: i32,
+    draw_plies: usize,
+}
+
+impl Adjudicator {
+    pub(super) const fn new(rules: Rules) -> Self {
+        Self { rules, win_plies: 0, draw_plies: 0 }
+    }
+
+    /// Adjudicates a position before it's searched, returning the result from White's point of
+    /// view. `ply` is the number of plies played so far.
+    pub(super) fn adjudicate_position(&self, board: &Board, ply: usize) -> Option<(f64, Rule)> {
+        if self.rules.max_plies > 0 && ply >= self.rules.max_plies {
+            return Some((0.5, Rule::MaxLength));
+        }
+
+        #[cfg(feature = "syzygy")]
+        if self.rules.tablebases
+            && board.fiftymove_clock() == 0
+            && board.castling().raw() == 0
+            && board.occupancies().popcount() <= crate::tb::size()
+            && let Some(outcome) = crate::tb::probe(board)
+        {
+            let win = if board.side_to_move() == crate::types::Color::White { 1.0 } else { 0.0 };
+            let result = match outcome {
+                crate::tb::GameOutcome::Win => win,
+                crate::tb::GameOutcome::Loss => 1.0 - win,
+                crate::tb::GameOutcome::Draw => 0.5,
+            };
+            return Some((result, Rule::Tablebase));
+        }
+
+        #[cfg(not(feature = "syzygy"))]
+        let _ = (board, self.rules.tablebases);
+
+        None
+    }
+
+    /// Adjudicates the game after a search of the position at `ply` returned `score`.
+    pub(super) fn adjudicate_score(&mut self, score: i32, ply: usize) -> Option<(f64, Rule)> {
+        let rules = &self.rules;
+
+        self.win_plies =
+            if score.abs() >= rules.win_score && (self.win_plies == 0 || self.win_plies.signum() == score.signum()) {
+                self.win_plies + score.signum()
+            } else {
+                0
+            };
+        self.draw_plies = if score.abs() <= rules.draw_score { self.draw_plies + 1 } else { 0 };
+
+        if rules.win_plies > 0 && self.win_plies.abs() >= rules.win_plies {
+            return Some((if self.win_plies > 0 { 1.0 } else { 0.0 }, Rule::Win));
+        }
+
+        if rules.draw_plies > 0 && ply >= rules.draw_min_ply && self.draw_plies >= rules.draw_plies {
+            return Some((0.5, Rule::Draw));
+        }
+
+        None
+    }
+}
+
+/// Counts of the games ended by each rule, shared between the threads.
+#[derive(Default)]
+pub(super) struct Stats {
+    games: AtomicUsize,
+    rules: [AtomicUsize; Rule::ALL.len()],
+}
+
+impl Stats {
+    /// Records a finished game, with the rule that ended it if it was adjudicated.
+    pub(super) fn record(&self, rule: Option<Rule>) {
+        self.games.fetch_add(1, Ordering::Relaxed);
+        if let Some(rule) = rule {
+            self.rules[rule as usize].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let games = self.games.load(Ordering::Relaxed).max(1) as f64;
+
+        let counts = Rule::ALL.map(|rule| {
+            let count = self.rules[rule as usize].load(Ordering::Relaxed);
+            format!("{rule}: {count} ({:.1}%)", 100.0 * count as f64 / games)
+        });
+        write!(f, "Adjudications: {}", counts.join(", "))
+    }
+}
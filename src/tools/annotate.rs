@@ -0,0 +1,242 @@
+//! Annotates games in [Portable Game Notation][pgn] by searching every position for a
+//! fixed time. Each move is followed by a comment with its evaluation from White's point
+//! of view and the search depth, along with the engine's preferred move when it differs.
+//! Moves that lose too much compared to the best move are marked as mistakes.
+//!
+//! [pgn]: https://en.wikipedia.org/wiki/Portable_Game_Notation
+
+use std::sync::Arc;
+
+use crate::{
+    board::{Board, NullBoardObserver},
+    search::Report,
+    thread::SharedContext,
+    threadpool::ThreadPool,
+    time::{Limits, TimeManager},
+    types::{Color, Move, Score, is_loss, is_win, normalize_to_cp, parse_san, to_san},
+};
+
+const DEFAULT_MOVETIME: u64 = 1000;
+const LINE_WIDTH: usize = 80;
+
+/// Thresholds in centipawns lost for marking a move as `??`, `?` and `?!` respectively.
+const BLUNDER: i32 = 300;
+const MISTAKE: i32 = 150;
+const INACCURACY: i32 = 75;
+
+/// Scores are clamped when measuring the loss of a move so that mates don't dominate.
+const MAX_LOSS_SCORE: i32 = 2000;
+
+#[derive(Default)]
+pub(super) struct Game {
+    pub(super) tags: Vec<(String, String)>,
+    pub(super) moves: Vec<String>,
+    result: String,
+}
+
+pub fn annotate(args: &[&str]) {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: annotate <game.pgn> [movetime]");
+        return;
+    };
+
+    let movetime = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_MOVETIME);
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Failed to read '{path}': {e}");
+            return;
+        }
+    };
+
+    let shared = Arc::new(SharedContext::default());
+    let mut pool = ThreadPool::new(shared.clone());
+
+    for game in parse_pgn(&contents) {
+        pool.clear();
+        pool.clear_hash();
+
+        annotate_game(&mut pool, &shared, &game, movetime);
+    }
+}
+
+fn annotate_game(pool: &mut ThreadPool, shared: &Arc<SharedContext>, game: &Game, movetime: u64) {
+    let fen = game.tags.iter().find(|(name, _)| name == "FEN").map(|(_, value)| value.as_str());
+    let mut board = match fen.map(Board::from_fen) {
+        Some(Ok(board)) => board,
+        Some(Err(e)) => {
+            eprintln!("Invalid FEN: {e:?}");
+            return;
+        }
+        None => Board::starting_position(),
+    };
+
+    let mut tokens = Vec::new();
+
+    for (index, san) in game.moves.iter().enumerate() {
+        let Some(mv) = parse_san(&board, san) else {
+            eprintln!("Illegal move '{san}' in position '{}'", board.to_fen());
+            break;
+        };
+
+        let (best, best_score, depth) = search(pool, shared, &board, &[], movetime);
+        let played_score = if mv == best { best_score } else { search(pool, shared, &board, &[mv], movetime).1 };
+
+        if board.side_to_move() == Color::White {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if index == 0 {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+
+        let clamp = |score: i32| score.clamp(-MAX_LOSS_SCORE, MAX_LOSS_SCORE);
+        let loss = normalize_to_cp(clamp(best_score) - clamp(played_score), &board);
+
+        let marker = match loss {
+            _ if mv == best => "",
+            loss if loss >= BLUNDER => "??",
+            loss if loss >= MISTAKE => "?",
+            loss if loss >= INACCURACY => "?!",
+            _ => "",
+        };
+
+        let mut comment = format!("{{{}/{depth}", format_score(played_score, &board));
+        if mv != best {
+            comment.push_str(&format!("; best {} {}", to_san(&board, best), format_score(best_score, &board)));
+        }
+        comment.push('}');
+
+        tokens.push(format!("{}{marker}", to_san(&board, mv)));
+        tokens.push(comment);
+
+        board.make_move(mv, &mut NullBoardObserver);
+    }
+
+    tokens.push(game.result.clone());
+
+    for (name, value) in game.tags.iter().filter(|(name, _)| name != "Annotator") {
+        println!("[{name} \"{value}\"]");
+    }
+    println!("[Annotator \"Reckless\"]");
+    println!();
+
+    let mut line = String::new();
+    for word in tokens.iter().flat_map(|token| token.split(' ')) {
+        if !line.is_empty() && line.len() + word.len() + 1 > LINE_WIDTH {
+            println!("{line}");
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    println!("{line}");
+    println!();
+}
+
+/// Searches the position and returns the best move, its score and the completed depth.
+fn search(
+    pool: &mut ThreadPool, shared: &Arc<SharedContext>, board: &Board, search_moves: &[Move], movetime: u64,
+) -> (Move, i32, i32) {
+    let time_manager = TimeManager::new(Limits::Time(movetime), board.fullmove_number(), 0);
+    pool.execute_searches(time_manager, Report::None, 1, board, search_moves, shared);
+
+    let td = pool.main_thread();
+    (td.root_moves[0].mv, td.root_moves[0].score, td.completed_depth)
+}
+
+/// Formats a score of the side to move from White's point of view, either in pawns or as a mate distance.
+fn format_score(score: i32, board: &Board) -> String {
+    let score = if board.side_to_move() == Color::White { score } else { -score };
+
+    if is_win(score) && score >= Score::MATE_IN_MAX {
+        format!("#{}", (Score::MATE - score + 1) / 2)
+    } else if is_loss(score) && score <= -Score::MATE_IN_MAX {
+        format!("#-{}", (Score::MATE + score + 1) / 2)
+    } else {
+        format!("{:+.2}", normalize_to_cp(score, board) as f64 / 100.0)
+    }
+}
+
+/// Parses the games of a PGN file, skipping comments, variations and numeric annotation glyphs.
+pub(super) fn parse_pgn(contents: &str) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut game = Game::default();
+    let mut movetext = String::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if let Some(tag) = line.strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) {
+            if !movetext.trim().is_empty() {
+                finish_game(&mut games, std::mem::take(&mut game), &std::mem::take(&mut movetext));
+            }
+
+            if let Some((name, value)) = tag.split_once(' ') {
+                game.tags.push((name.to_string(), value.trim().trim_matches('"').to_string()));
+            }
+        } else if !line.starts_with('%') {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+
+    if !movetext.trim().is_empty() || !game.tags.is_empty() {
+        finish_game(&mut games, game, &movetext);
+    }
+
+    games
+}
+
+fn finish_game(games: &mut Vec<Game>, mut game: Game, movetext: &str) {
+    let mut depth = 0;
+    let mut text = String::new();
+    let mut chars = movetext.chars();
+
+    // Strip comments and variations, which may be nested
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+                text.push(' ');
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                text.push(' ');
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => text.push(c),
+            _ => (),
+        }
+    }
+
+    for token in text.split_whitespace() {
+        if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+            game.result = token.to_string();
+            continue;
+        }
+
+        // Move numbers may be attached to the move, as in `12.e4`
+        let token = token.rsplit_once('.').map_or(token, |(_, san)| san);
+
+        if !token.is_empty() && !token.starts_with('$') {
+            game.moves.push(token.to_string());
+        }
+    }
+
+    if game.result.is_empty() {
+        game.result = game.tags.iter().find(|(name, _)| name == "Result").map_or("*", |(_, value)| value).to_string();
+    }
+
+    games.push(game);
+}
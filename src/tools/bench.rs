@@ -6,6 +6,11 @@
 //!
 //! Note that although it can be used as a benchmarking tool,
 //! it is not comprehensive enough to be definitive.
+//!
+//! Run from the command line as `reckless bench`, it prints the nodes of each
+//! position and ends with the `<nodes> nodes <nps> nps` line OpenBench reads,
+//! the total node count doubling as the signature of the build. With `OutputFormat=json`,
+//! every position and the summary are printed as JSON objects instead.
 
 use std::{sync::Arc, time::Instant};
 
@@ -17,7 +22,7 @@ use crate::{
     time::{Limits, TimeManager},
 };
 
-const POSITIONS: &[&str] = &[
+pub const POSITIONS: &[&str] = &[
     "2k5/2P3p1/3r1p2/7p/2RB2rP/3K2P1/5P2/8 w - - 1 48",
     "8/8/1k1NK3/r7/2R2P1P/3n2P1/8/8 b - - 0 59",
     "r1r3k1/1bqnbp1N/ppn1p1p1/4P1B1/8/2N5/PPB1QPPP/R3R1K1 w - - 3 9",
@@ -74,19 +79,29 @@ const DEFAULT_DEPTH: i32 = 12;
 const DEFAULT_HASH: usize = 16;
 const DEFAULT_THREADS: usize = 1;
 
-pub fn bench<const PRETTY: bool>(args: &[&str]) {
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BenchOutput {
+    /// A table, for the interactive UCI mode.
+    Table,
+    /// The plain lines read by OpenBench, for the command line.
+    Plain,
+    Json,
+}
+
+pub fn bench(args: &[&str], output: BenchOutput) {
     #[allow(clippy::get_first)]
     let hash = args.get(0).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_HASH);
     let threads = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_THREADS);
     let depth = args.get(2).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_DEPTH);
 
     let shared = Arc::new(SharedContext::default());
-    shared.tt.resize(threads, hash);
+    shared.tt.resize(hash);
 
     let mut pool = ThreadPool::new(shared.clone());
     pool.set_count(threads);
+    pool.clear_hash();
 
-    if PRETTY {
+    if output == BenchOutput::Table {
         println!("{}", "-".repeat(50));
         println!("{:>15} {:>13} {:>15}", "Nodes", "Elapsed", "NPS");
         println!("{}", "-".repeat(50));
@@ -102,26 +117,41 @@ pub fn bench<const PRETTY: bool>(args: &[&str]) {
         let board = Board::from_fen(position).unwrap();
         let time_manager = TimeManager::new(Limits::Depth(depth), 0, 0);
 
-        pool.execute_searches(time_manager, Report::None, 1, &board, &shared);
+        pool.execute_searches(time_manager, Report::None, 1, &board, &[], &shared);
 
         nodes += shared.nodes.aggregate();
 
-        if PRETTY {
-            let seconds = now.elapsed().as_secs_f64();
-            let nps = shared.nodes.aggregate() as f64 / seconds;
-            println!("{index:>3} {:>11} {seconds:>12.3}s {nps:>15.0} N/s", shared.nodes.aggregate());
+        let seconds = now.elapsed().as_secs_f64();
+        let position_nodes = shared.nodes.aggregate();
+
+        match output {
+            BenchOutput::Table => {
+                let nps = if seconds > 0.0 { position_nodes as f64 / seconds } else { 0.0 };
+                println!("{index:>3} {position_nodes:>11} {seconds:>12.3}s {nps:>15.0} N/s");
+            }
+            // Kept free of "nps", so that OpenBench only picks up the summary line
+            BenchOutput::Plain => println!("Position {:>2}/{}: {position_nodes} nodes", index + 1, POSITIONS.len()),
+            BenchOutput::Json => println!(
+                r#"{{"type":"benchposition","position":{},"nodes":{position_nodes},"time":{}}}"#,
+                index + 1,
+                (seconds * 1000.0) as u64
+            ),
         }
     }
 
     let seconds = time.elapsed().as_secs_f64();
-    let nps = nodes as f64 / seconds;
+    let nps = if seconds > 0.0 { nodes as f64 / seconds } else { 0.0 };
 
-    if PRETTY {
-        println!("{}", "-".repeat(50));
-        println!("{nodes:>15} {seconds:>12.3}s {nps:>15.0} N/s");
-        println!("{}", "-".repeat(50));
-    } else {
-        println!("Bench: {nodes} nodes {nps:.0} nps");
+    match output {
+        BenchOutput::Table => {
+            println!("{}", "-".repeat(50));
+            println!("{nodes:>15} {seconds:>12.3}s {nps:>15.0} N/s");
+            println!("{}", "-".repeat(50));
+        }
+        BenchOutput::Plain => println!("Bench: {nodes} nodes {nps:.0} nps"),
+        BenchOutput::Json => {
+            println!(r#"{{"type":"bench","nodes":{nodes},"time":{},"nps":{nps:.0}}}"#, (seconds * 1000.0) as u64)
+        }
     }
 
     crate::misc::dbg_print();
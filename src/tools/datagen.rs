@@ -0,0 +1,291 @@
+//! Self-play data generation for training NNUE networks.
+//!
+//! Games are played from randomized openings at a fixed node limit, which becomes the hard
+//! limit with a soft one given by `soft_nodes=<n>`, and the quiet positions are written in the
+//! text format accepted by bullet's data tools:
+//!
+//! ```text
+//! <fen> | <score> | <result>
+//! ```
+//!
+//! Both the score (in internal units) and the result (`1.0`, `0.5` or `0.0`) are
+//! from White's point of view. Openings are varied by random plies, and optionally by sampling
+//! the first moves of the games with `temperature=<t>` and `temperature_plies=<n>`, see
+//! [`Temperature`]. Outputs ending in `.bullet` or `.binpack` are written in those
+//! formats instead, see [`super::trainingdata`]. Binpacks get every position of the games,
+//! as their trainers do the filtering and the chains of moves compress well.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+
+use super::{
+    adjudication::{Adjudicator, Rule, Rules, Stats},
+    trainingdata::{Entry, Format, Writer},
+};
+use crate::{
+    board::{Board, NullBoardObserver},
+    rng::{self, Rng},
+    search::Report,
+    skill::Temperature,
+    thread::SharedContext,
+    threadpool::ThreadPool,
+    time::{Limits, TimeManager},
+    types::{Color, Move, is_decisive},
+};
+
+const DEFAULT_THREADS: usize = 1;
+const DEFAULT_GAMES: usize = 100;
+const DEFAULT_NODES: u64 = 5000;
+
+/// Number of random plies played from the starting position (one more is added at random
+/// so that both sides get to move first out of the opening).
+const RANDOM_PLIES: usize = 8;
+
+/// Openings that are already too unbalanced after the random plies are discarded.
+const MAX_OPENING_SCORE: i32 = 1000;
+
+const REPORT_INTERVAL: usize = 10;
+
+#[derive(Copy, Clone)]
+struct Settings {
+    nodes: u64,
+    /// A soft node limit below `nodes`, or zero to search every move to `nodes`.
+    soft_nodes: u64,
+    rules: Rules,
+    temperature: Temperature,
+    /// Whether every position is recorded rather than only the quiet ones.
+    whole_games: bool,
+}
+
+impl Settings {
+    fn limits(&self) -> Limits {
+        match self.soft_nodes {
+            0 => Limits::Nodes(self.nodes),
+            soft => Limits::SoftNodes(soft.min(self.nodes), self.nodes),
+        }
+    }
+}
+
+pub fn datagen(args: &[&str]) {
+    // Adjudication rules and the temperature are given as `<key>=<value>` after the positional arguments
+    let (rule_args, args): (Vec<&str>, Vec<&str>) = args.iter().partition(|arg| arg.contains('='));
+
+    let Some(path) = args.first() else {
+        eprintln!("Usage: datagen <output> [threads] [games] [nodes] [<rule>=<value>...]");
+        return;
+    };
+
+    let threads = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_THREADS);
+    let games = args.get(2).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_GAMES);
+    let nodes = args.get(3).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_NODES);
+
+    let mut settings = Settings {
+        nodes,
+        soft_nodes: 0,
+        rules: Rules::DATAGEN,
+        temperature: Temperature::default(),
+        whole_games: Format::from_path(path).keeps_whole_games(),
+    };
+
+    for arg in rule_args {
+        let (key, value) = arg.split_once('=').unwrap();
+        let invalid = || format!("Invalid value for '{key}': '{value}'");
+
+        let parsed = match key {
+            "temperature" => value.parse().map(|v| settings.temperature.temperature = v).map_err(|_| invalid()),
+            "temperature_plies" => value.parse().map(|v| settings.temperature.plies = v).map_err(|_| invalid()),
+            "soft_nodes" => value.parse().map(|v| settings.soft_nodes = v).map_err(|_| invalid()),
+            _ => settings.rules.parse(key, value).unwrap_or_else(|| Err(format!("Unknown argument: '{key}'"))),
+        };
+
+        if let Err(e) = parsed {
+            eprintln!("{e}");
+            return;
+        }
+    }
+
+    let writer = match Writer::create(path) {
+        Ok(writer) => writer,
+        Err(e) => {
+            eprintln!("Failed to create '{path}': {e}");
+            return;
+        }
+    };
+
+    let seed = rng::seed();
+
+    println!("Generating {games} games with {threads} threads at {nodes} nodes per move into '{path}' (seed {seed})");
+
+    let output = Mutex::new(writer);
+    let started = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let positions = AtomicUsize::new(0);
+    let stats = Stats::default();
+    let time = Instant::now();
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let (output, started, finished, positions, stats) = (&output, &started, &finished, &positions, &stats);
+
+            scope.spawn(move || {
+                let shared = Arc::new(SharedContext::default());
+                let mut pool = ThreadPool::new(shared.clone());
+
+                loop {
+                    let game = started.fetch_add(1, Ordering::Relaxed);
+                    if game >= games {
+                        break;
+                    }
+
+                    pool.clear();
+                    pool.clear_hash();
+
+                    for corrhist in shared.history.all() {
+                        corrhist.pawn.clear();
+                        corrhist.non_pawn[Color::White].clear();
+                        corrhist.non_pawn[Color::Black].clear();
+                    }
+
+                    // Every game has its own stream, so a seed reproduces the same games with any number of threads
+                    let mut rng = Rng::for_stream(seed, game);
+
+                    let (entries, rule) = play_game(&mut pool, &shared, &mut rng, settings);
+                    stats.record(rule);
+
+                    let mut output = output.lock().unwrap();
+                    for entry in &entries {
+                        output.write(entry).expect("Failed to write training data");
+                    }
+                    drop(output);
+
+                    let positions = positions.fetch_add(entries.len(), Ordering::Relaxed) + entries.len();
+                    let finished = finished.fetch_add(1, Ordering::Relaxed) + 1;
+
+                    if finished % REPORT_INTERVAL == 0 || finished == games {
+                        let seconds = time.elapsed().as_secs_f64();
+                        println!(
+                            "Games: {finished:>8} Positions: {positions:>10} Speed: {:>8.0} pos/s",
+                            positions as f64 / seconds
+                        );
+                    }
+                }
+            });
+        }
+    });
+
+    output.lock().unwrap().finish().expect("Failed to write training data");
+    println!("{stats}");
+}
+
+/// Plays a single self-play game and returns the recorded positions, along with the rule that
+/// adjudicated the game.
+fn play_game(
+    pool: &mut ThreadPool, shared: &Arc<SharedContext>, rng: &mut Rng, settings: Settings,
+) -> (Vec<Entry>, Option<Rule>) {
+    let limits = settings.limits();
+    let mut board = random_opening(pool, shared, rng, &limits);
+
+    let mut positions = Vec::new();
+    let mut ply = 0;
+    let mut adjudicator = Adjudicator::new(settings.rules);
+    let mut rule = None;
+
+    let result = loop {
+        if !board.has_legal_moves() {
+            break match board.in_check() {
+                true if board.side_to_move() == Color::White => 0.0,
+                true => 1.0,
+                false => 0.5,
+            };
+        }
+
+        if board.is_draw(0) {
+            break 0.5;
+        }
+
+        if let Some((result, adjudicated)) = adjudicator.adjudicate_position(&board, ply) {
+            rule = Some(adjudicated);
+            break result as f32;
+        }
+
+        let (best, score, mv) = if settings.temperature.is_active(ply) {
+            sample(pool, shared, &board, &limits, settings.temperature, rng)
+        } else {
+            let (best, score) = search(pool, shared, &board, limits.clone());
+            (best, score, best)
+        };
+        let score = if board.side_to_move() == Color::White { score } else { -score };
+
+        if is_decisive(score) {
+            break if score > 0 { 1.0 } else { 0.0 };
+        }
+
+        if let Some((result, adjudicated)) = adjudicator.adjudicate_score(score, ply) {
+            rule = Some(adjudicated);
+            break result as f32;
+        }
+
+        // Positions where the best move is tactical are poor targets for a static evaluation
+        if settings.whole_games || (!board.in_check() && !best.is_noisy()) {
+            positions.push((board.clone(), score, mv));
+        }
+
+        board.make_move(mv, &mut NullBoardObserver);
+        ply += 1;
+    };
+
+    let entries = positions.into_iter().map(|(board, score, mv)| Entry { board, score, result, mv }).collect();
+    (entries, rule)
+}
+
+/// Plays random moves from the starting position until a reasonably balanced position is found.
+fn random_opening(pool: &mut ThreadPool, shared: &Arc<SharedContext>, rng: &mut Rng, limits: &Limits) -> Board {
+    'retry: loop {
+        let mut board = Board::starting_position();
+
+        for _ in 0..RANDOM_PLIES + (rng.next() & 1) as usize {
+            let moves = board.generate_all_moves();
+            if moves.is_empty() {
+                continue 'retry;
+            }
+
+            let mv = moves[rng.next() as usize % moves.len()].mv;
+            board.make_move(mv, &mut NullBoardObserver);
+        }
+
+        if !board.has_legal_moves() {
+            continue;
+        }
+
+        let (_, score) = search(pool, shared, &board, limits.clone());
+        if score.abs() <= MAX_OPENING_SCORE {
+            return board;
+        }
+    }
+}
+
+pub(super) fn search(pool: &mut ThreadPool, shared: &Arc<SharedContext>, board: &Board, limits: Limits) -> (Move, i32) {
+    let time_manager = TimeManager::new(limits, board.fullmove_number(), 0);
+    pool.execute_searches(time_manager, Report::None, 1, board, &[], shared);
+
+    let best = &pool.main_thread().root_moves[0];
+    (best.mv, best.score)
+}
+
+/// Searches several principal variations and samples the move to play from them, returning
+/// the best move, its score and the sampled move.
+fn sample(
+    pool: &mut ThreadPool, shared: &Arc<SharedContext>, board: &Board, limits: &Limits, temperature: Temperature,
+    rng: &mut Rng,
+) -> (Move, i32, Move) {
+    let time_manager = TimeManager::new(limits.clone(), board.fullmove_number(), 0);
+    pool.execute_searches(time_manager, Report::None, Temperature::MULTI_PV, board, &[], shared);
+
+    let root_moves = &pool.main_thread().root_moves;
+    (root_moves[0].mv, root_moves[0].score, temperature.pick_move(root_moves, rng))
+}
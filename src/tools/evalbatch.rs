@@ -0,0 +1,49 @@
+//! Evaluates positions read from standard input with the network, one FEN per line, until
+//! an empty line or the end of input. Each position is printed back along with its static
+//! evaluation from White's point of view:
+//!
+//! ```text
+//! <fen> | <score>
+//! ```
+
+use std::sync::{Arc, mpsc::Receiver};
+
+use crate::{board::Board, thread::SharedContext, threadpool::ThreadPool, types::Color};
+
+const BATCH_SIZE: usize = 64;
+
+pub fn evalbatch(rx: &Receiver<String>) {
+    let shared = Arc::new(SharedContext::default());
+    let mut pool = ThreadPool::new(shared);
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    while let Ok(line) = rx.recv() {
+        let fen = line.trim();
+        if fen.is_empty() || fen == "quit" {
+            break;
+        }
+
+        match Board::from_fen(fen) {
+            Ok(board) => batch.push(board),
+            Err(e) => eprintln!("Invalid FEN '{fen}': {e:?}"),
+        }
+
+        if batch.len() == BATCH_SIZE {
+            evaluate(&mut pool, &mut batch);
+        }
+    }
+
+    evaluate(&mut pool, &mut batch);
+}
+
+fn evaluate(pool: &mut ThreadPool, batch: &mut Vec<Board>) {
+    let scores = pool.main_thread().nnue.evaluate_batch(batch);
+
+    for (board, score) in batch.iter().zip(scores) {
+        let score = if board.side_to_move() == Color::White { score } else { -score };
+        println!("{} | {score}", board.to_fen());
+    }
+
+    batch.clear();
+}
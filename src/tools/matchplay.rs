@@ -0,0 +1,456 @@
+//! Plays matches between this engine and another UCI engine, or between two configurations
+//! of this engine, for quick local testing of patches:
+//!
+//! ```text
+//! match games=200 movetime=50 openings=book.epd engine=./reckless-master a.Hash=32
+//! ```
+//!
+//! Both players run as subprocesses speaking UCI. The first is this binary and the second is
+//! `engine`, this binary again unless given, with options set by `a.<Name>=<Value>` and
+//! `b.<Name>=<Value>` respectively. Every opening, read from an EPD or PGN file, is played
+//! twice with the colors reversed. Results are from the point of view of the first player.
+//!
+//! Games are adjudicated by the rules of [`super::adjudication`], on the scores reported by
+//! the engines.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use super::adjudication::{Adjudicator, Rule, Rules, Stats};
+use crate::{
+    board::{Board, NullBoardObserver},
+    types::{Color, parse_san},
+};
+
+const DEFAULT_GAMES: usize = 100;
+const DEFAULT_MOVETIME: u64 = 100;
+
+const MATE_SCORE: i32 = 100_000;
+
+/// Type I and II error rates of the sequential probability ratio test.
+const SPRT_ALPHA: f64 = 0.05;
+const SPRT_BETA: f64 = 0.05;
+
+const REPORT_INTERVAL: usize = 10;
+
+const USAGE: &str = "Usage: match [games=<n>] [movetime=<ms> | nodes=<n>] [openings=<file>] [engine=<path>] \
+                     [concurrency=<n>] [elo0=<elo>] [elo1=<elo>] [sprt=true] [<rule>=<value>] [a.<Name>=<Value>] [b.<Name>=<Value>]";
+
+struct Config {
+    games: usize,
+    limit: String,
+    openings: Vec<String>,
+    engines: [String; 2],
+    options: [Vec<(String, String)>; 2],
+    concurrency: usize,
+    elo0: f64,
+    elo1: f64,
+    sprt: bool,
+    rules: Rules,
+}
+
+#[derive(Default)]
+struct Tally {
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+pub fn matchplay(args: &[&str]) {
+    let config = match parse_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return;
+        }
+    };
+
+    println!(
+        "Playing {} games of '{}' against '{}' with {} openings at '{}'",
+        config.games,
+        config.engines[0],
+        config.engines[1],
+        config.openings.len(),
+        config.limit
+    );
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let tally = Mutex::new(Tally::default());
+    let stats = Stats::default();
+
+    std::thread::scope(|scope| {
+        for _ in 0..config.concurrency.max(1) {
+            scope.spawn(|| {
+                let mut players = [None, None];
+
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games {
+                        break;
+                    }
+
+                    for (id, player) in players.iter_mut().enumerate() {
+                        if player.is_none() {
+                            match Engine::spawn(&config.engines[id], &config.options[id]) {
+                                Ok(engine) => *player = Some(engine),
+                                Err(e) => {
+                                    eprintln!("Failed to start '{}': {e}", config.engines[id]);
+                                    stop.store(true, Ordering::Relaxed);
+                                    return;
+                                }
+                            }
+                        }
+                    }
+
+                    // The first player has White in even games, and the opening changes every other game
+                    let opening = &config.openings[index / 2 % config.openings.len()];
+                    let first_is_white = index.is_multiple_of(2);
+
+                    let (result, reason, rule) =
+                        play_game(&mut players, first_is_white, opening, &config.limit, config.rules);
+                    stats.record(rule);
+                    let score = if first_is_white { result } else { 1.0 - result };
+
+                    let mut tally = tally.lock().unwrap();
+                    match score {
+                        1.0 => tally.wins += 1,
+                        0.0 => tally.losses += 1,
+                        _ => tally.draws += 1,
+                    }
+
+                    let played = tally.wins + tally.draws + tally.losses;
+                    let (white, black) = if first_is_white { ("A", "B") } else { ("B", "A") };
+                    println!("Game {:>5}: {white} vs {black} {} ({reason})", index + 1, format_result(result));
+
+                    if played % REPORT_INTERVAL == 0 {
+                        println!("{}", summary(&tally, &config));
+                    }
+
+                    let ratio = llr(&tally, config.elo0, config.elo1);
+                    if config.sprt && !(sprt_bounds().0..sprt_bounds().1).contains(&ratio) {
+                        if !stop.swap(true, Ordering::Relaxed) {
+                            println!("SPRT accepted {} after {played} games", if ratio > 0.0 { "H1" } else { "H0" });
+                        }
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    let tally = tally.into_inner().unwrap();
+    println!("{}", "-".repeat(60));
+    println!("{}", summary(&tally, &config));
+    println!("{stats}");
+}
+
+fn parse_config(args: &[&str]) -> Result<Config, String> {
+    let current = std::env::current_exe().map_or_else(|_| "reckless".to_string(), |path| path.display().to_string());
+
+    let mut config = Config {
+        games: DEFAULT_GAMES,
+        limit: format!("movetime {DEFAULT_MOVETIME}"),
+        openings: vec![Board::starting_position().to_fen()],
+        engines: [current.clone(), current],
+        options: [Vec::new(), Vec::new()],
+        concurrency: 1,
+        elo0: 0.0,
+        elo1: 5.0,
+        sprt: false,
+        rules: Rules::MATCH,
+    };
+
+    for arg in args {
+        let Some((key, value)) = arg.split_once('=') else {
+            return Err(format!("Invalid argument: '{arg}'"));
+        };
+
+        let invalid = || format!("Invalid value for '{key}': '{value}'");
+
+        match key {
+            "games" => config.games = value.parse().map_err(|_| invalid())?,
+            "movetime" => config.limit = format!("movetime {}", value.parse::<u64>().map_err(|_| invalid())?),
+            "nodes" => config.limit = format!("nodes {}", value.parse::<u64>().map_err(|_| invalid())?),
+            "openings" => config.openings = read_openings(value)?,
+            "engine" => config.engines[1] = value.to_string(),
+            "concurrency" => config.concurrency = value.parse().map_err(|_| invalid())?,
+            "elo0" => config.elo0 = value.parse().map_err(|_| invalid())?,
+            "elo1" => config.elo1 = value.parse().map_err(|_| invalid())?,
+            "sprt" => config.sprt = value.parse().map_err(|_| invalid())?,
+            _ => match key.split_once('.') {
+                Some(("a", name)) => config.options[0].push((name.to_string(), value.to_string())),
+                Some(("b", name)) => config.options[1].push((name.to_string(), value.to_string())),
+                _ => config.rules.parse(key, value).ok_or_else(|| format!("Unknown argument: '{key}'"))??,
+            },
+        }
+    }
+
+    Ok(config)
+}
+
+/// Reads the starting positions of the openings, either from the games of a PGN file or from
+/// the positions of an EPD file.
+fn read_openings(path: &str) -> Result<Vec<String>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{path}': {e}"))?;
+
+    let openings = if path.ends_with(".pgn") {
+        super::annotate::parse_pgn(&contents)
+            .iter()
+            .filter_map(|game| {
+                let fen = game.tags.iter().find(|(name, _)| name == "FEN").map(|(_, value)| value.as_str());
+                let mut board = fen.map_or_else(|| Ok(Board::starting_position()), Board::from_fen).ok()?;
+
+                for san in &game.moves {
+                    let mv = parse_san(&board, san)?;
+                    board.make_move(mv, &mut NullBoardObserver);
+                }
+                Some(board.to_fen())
+            })
+            .collect::<Vec<_>>()
+    } else {
+        contents
+            .lines()
+            .filter_map(|line| {
+                let fen = line.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+                Board::from_fen(&fen).ok().map(|board| board.to_fen())
+            })
+            .collect()
+    };
+
+    if openings.is_empty() {
+        return Err(format!("No openings found in '{path}'"));
+    }
+    Ok(openings)
+}
+
+/// Plays a game from the opening and returns its result from White's point of view, along
+/// with the reason it ended and the rule if it was adjudicated. An engine that crashes is
+/// restarted for the next game.
+fn play_game(
+    players: &mut [Option<Engine>; 2], first_is_white: bool, opening: &str, limit: &str, rules: Rules,
+) -> (f64, String, Option<Rule>) {
+    let mut board = Board::from_fen(opening).unwrap();
+    let mut moves = Vec::new();
+    let mut adjudicator = Adjudicator::new(rules);
+
+    // An engine that fails here has crashed, which the first search finds out
+    for player in players.iter_mut().flatten() {
+        let _ = player.new_game();
+    }
+
+    loop {
+        let white_to_move = board.side_to_move() == Color::White;
+        let loss = if white_to_move { 0.0 } else { 1.0 };
+
+        if !board.has_legal_moves() {
+            let (result, reason) = if board.in_check() { (loss, "checkmate") } else { (0.5, "stalemate") };
+            return (result, reason.to_string(), None);
+        }
+
+        if let Some(reason) = board.draw_reason() {
+            return (0.5, reason.to_string(), None);
+        }
+
+        if let Some((result, rule)) = adjudicator.adjudicate_position(&board, moves.len()) {
+            return (result, rule.to_string(), Some(rule));
+        }
+
+        let id = if white_to_move == first_is_white { 0 } else { 1 };
+        let engine = players[id].as_mut().unwrap();
+
+        let position = if moves.is_empty() {
+            format!("position fen {opening}")
+        } else {
+            format!("position fen {opening} moves {}", moves.join(" "))
+        };
+
+        let (bestmove, score) = match engine.go(&position, limit) {
+            Ok(result) => result,
+            Err(_) => {
+                players[id] = None;
+                return (loss, "engine crashed".to_string(), None);
+            }
+        };
+
+        let Some(mv) = board.generate_all_moves().iter().map(|entry| entry.mv).find(|mv| mv.to_uci(&board) == bestmove)
+        else {
+            return (loss, format!("illegal move {bestmove}"), None);
+        };
+
+        // Scores are tracked from White's point of view for the adjudication
+        let score = if white_to_move { score } else { -score };
+
+        board.make_move(mv, &mut NullBoardObserver);
+        moves.push(bestmove);
+
+        if let Some((result, rule)) = adjudicator.adjudicate_score(score, moves.len()) {
+            return (result, rule.to_string(), Some(rule));
+        }
+    }
+}
+
+fn format_result(result: f64) -> &'static str {
+    match result {
+        1.0 => "1-0",
+        0.0 => "0-1",
+        _ => "1/2-1/2",
+    }
+}
+
+fn summary(tally: &Tally, config: &Config) -> String {
+    let games = tally.wins + tally.draws + tally.losses;
+    let (score, variance) = score_and_variance(tally);
+
+    let margin = 1.96 * (variance / games as f64).sqrt();
+    let error = (elo(score + margin) - elo(score - margin)) / 2.0;
+    let (lower, upper) = sprt_bounds();
+
+    format!(
+        "Games: {games} W: {} L: {} D: {} Elo: {:.1} +/- {error:.1} LLR: {:.2} ({lower:.2}, {upper:.2}) [{}, {}]",
+        tally.wins,
+        tally.losses,
+        tally.draws,
+        elo(score),
+        llr(tally, config.elo0, config.elo1),
+        config.elo0,
+        config.elo1
+    )
+}
+
+/// The mean score per game and its variance.
+fn score_and_variance(tally: &Tally) -> (f64, f64) {
+    let games = (tally.wins + tally.draws + tally.losses).max(1) as f64;
+    let score = (tally.wins as f64 + tally.draws as f64 / 2.0) / games;
+
+    let variance = (tally.wins as f64 * (1.0 - score).powi(2)
+        + tally.draws as f64 * (0.5 - score).powi(2)
+        + tally.losses as f64 * score.powi(2))
+        / games;
+
+    (score, variance)
+}
+
+fn elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The log-likelihood ratio of the results under `elo1` against `elo0`, approximated for a
+/// normally distributed score.
+fn llr(tally: &Tally, elo0: f64, elo1: f64) -> f64 {
+    let games = (tally.wins + tally.draws + tally.losses) as f64;
+    let (score, variance) = score_and_variance(tally);
+
+    if variance == 0.0 {
+        return 0.0;
+    }
+
+    let (s0, s1) = (expected_score(elo0), expected_score(elo1));
+    games * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+}
+
+fn sprt_bounds() -> (f64, f64) {
+    ((SPRT_BETA / (1.0 - SPRT_ALPHA)).ln(), ((1.0 - SPRT_BETA) / SPRT_ALPHA).ln())
+}
+
+/// A UCI engine running as a subprocess.
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    crashed: bool,
+}
+
+impl Engine {
+    fn spawn(path: &str, options: &[(String, String)]) -> std::io::Result<Self> {
+        let mut child =
+            Command::new(path).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut engine = Self { child, stdin, stdout, crashed: false };
+
+        engine.send("uci")?;
+        engine.wait_for("uciok")?;
+
+        for (name, value) in options {
+            engine.send(&format!("setoption name {name} value {value}"))?;
+        }
+
+        Ok(engine)
+    }
+
+    fn new_game(&mut self) -> std::io::Result<()> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.wait_for("readyok")
+    }
+
+    /// Searches the position and returns the best move with the last reported score.
+    fn go(&mut self, position: &str, limit: &str) -> std::io::Result<(String, i32)> {
+        self.send(position)?;
+        self.send(&format!("go {limit}"))?;
+
+        let mut score = 0;
+        loop {
+            let line = self.read_line()?;
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+            match tokens.as_slice() {
+                ["bestmove", mv, ..] => return Ok((mv.to_string(), score)),
+                ["info", ..] => {
+                    if let Some(index) = tokens.iter().position(|&token| token == "score") {
+                        score = match tokens.get(index + 1..index + 3) {
+                            Some(["cp", cp]) => cp.parse().unwrap_or(score),
+                            Some(["mate", moves]) => {
+                                moves.parse::<i32>().map_or(score, |moves| moves.signum() * MATE_SCORE)
+                            }
+                            _ => score,
+                        };
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> std::io::Result<()> {
+        writeln!(self.stdin, "{command}").inspect_err(|_| self.crashed = true)
+    }
+
+    fn wait_for(&mut self, token: &str) -> std::io::Result<()> {
+        while self.read_line()?.trim() != token {}
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> std::io::Result<String> {
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            self.crashed = true;
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        if self.child.try_wait().ok().flatten().is_none() && self.crashed {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
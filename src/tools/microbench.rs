@@ -0,0 +1,181 @@
+//! Micro-benchmarks of single engine components, for comparing implementations of hot paths
+//! without the noise of a full search.
+
+use std::{
+    hint::black_box,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{bench::POSITIONS, perft::perft_internal};
+use crate::{board::Board, thread::SharedContext, threadpool::ThreadPool, types::MoveEntry};
+
+/// List lengths covering typical noisy lists, quiet lists and the longest positions.
+const LIST_LENGTHS: [usize; 6] = [4, 8, 16, 32, 48, 96];
+const SCANS: usize = 2_000_000;
+
+type ScanFn = fn(&[MoveEntry]) -> usize;
+
+/// Times the scans for the highest scored move of each compiled variant, and checks that they agree.
+pub fn bench_picker() {
+    let variants: Vec<(&str, ScanFn)> = vec![
+        ("scalar", crate::types::find_best_score_index_scalar),
+        #[cfg(target_feature = "avx2")]
+        ("avx2", |entries| unsafe { crate::types::find_best_score_index_avx2(entries) }),
+        #[cfg(target_feature = "avx512f")]
+        ("avx512", |entries| unsafe { crate::types::find_best_score_index_avx512(entries) }),
+    ];
+
+    let lists = LIST_LENGTHS.map(|length| {
+        let mut seed = length as i32;
+        let mut list = realistic_moves(length);
+        for entry in list.iter_mut() {
+            // Plenty of equal scores, so that the tie breaking is checked too
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            entry.score = (seed >> 16) % 64 - 32;
+        }
+        list
+    });
+
+    for list in &lists {
+        let expected = crate::types::find_best_score_index_scalar(list);
+        for (name, scan) in &variants {
+            assert_eq!(scan(list), expected, "{name} disagrees with the scalar scan on {} entries", list.len());
+        }
+    }
+
+    print!("{:>8}", "length");
+    for (name, _) in &variants {
+        print!("{name:>12}");
+    }
+    println!();
+
+    for list in &lists {
+        print!("{:>8}", list.len());
+
+        for (_, scan) in &variants {
+            let start = Instant::now();
+            for _ in 0..SCANS {
+                black_box(scan(black_box(list)));
+            }
+            print!("{:>9.2} ns", start.elapsed().as_nanos() as f64 / SCANS as f64);
+        }
+        println!();
+    }
+}
+
+/// Moves from the bench positions, taken in order to fill a list of the given length.
+fn realistic_moves(length: usize) -> Vec<MoveEntry> {
+    boards()
+        .into_iter()
+        .flat_map(|board| board.generate_all_moves().iter().copied().collect::<Vec<_>>())
+        .take(length)
+        .collect()
+}
+
+/// Times legal move generation on the bench positions.
+pub fn bench_movegen() {
+    const ROUNDS: usize = 20_000;
+
+    let boards = boards();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for board in &boards {
+            black_box(black_box(board).generate_all_moves());
+        }
+    }
+    report("movegen", ROUNDS * boards.len(), start);
+
+    let start = Instant::now();
+    let mut nodes = 0;
+    for board in &boards {
+        nodes += perft_internal(&|board| board.generate_all_moves(), 3, &mut board.clone());
+    }
+    report("perft 3 nodes", nodes as usize, start);
+}
+
+/// Times full accumulator refreshes, forward passes on accurate accumulators and incremental
+/// updates followed by a forward pass, on the bench positions.
+pub fn bench_nnue() {
+    const ROUNDS: usize = 2_000;
+
+    let mut pool = ThreadPool::new(Arc::new(SharedContext::default()));
+    let nnue = &mut pool.main_thread().nnue;
+    let boards = boards();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS / 10 {
+        for board in &boards {
+            nnue.full_refresh(board);
+        }
+    }
+    report("refresh", ROUNDS / 10 * boards.len(), start);
+
+    let mut forwards = 0;
+    let mut elapsed = Duration::ZERO;
+    for board in &boards {
+        nnue.full_refresh(board);
+
+        // The accumulators are accurate after the first call, leaving only the forward pass
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            black_box(nnue.evaluate(black_box(board)));
+        }
+        elapsed += start.elapsed();
+        forwards += ROUNDS;
+    }
+    report_elapsed("forward", forwards, elapsed);
+
+    let start = Instant::now();
+    let mut updates = 0;
+    for board in &boards {
+        let mut board = board.clone();
+        nnue.full_refresh(&board);
+
+        for _ in 0..ROUNDS / 100 {
+            for entry in board.generate_all_moves().iter() {
+                nnue.push(entry.mv, &board);
+                board.make_move(entry.mv, nnue);
+                black_box(nnue.evaluate(&board));
+                nnue.pop();
+                board.undo_move(entry.mv);
+                updates += 1;
+            }
+        }
+    }
+    report("update + forward", updates, start);
+}
+
+/// Times the static exchange evaluation of every legal move of the bench positions.
+pub fn bench_see() {
+    const ROUNDS: usize = 2_000;
+
+    let boards = boards();
+    let moves = boards.iter().map(|board| board.generate_all_moves()).collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let mut calls = 0;
+    for _ in 0..ROUNDS {
+        for (board, moves) in boards.iter().zip(&moves) {
+            for entry in moves.iter() {
+                black_box(board.see(black_box(entry.mv), 0));
+            }
+            calls += moves.len();
+        }
+    }
+    report("see", calls, start);
+}
+
+fn boards() -> Vec<Board> {
+    POSITIONS.iter().filter_map(|fen| Board::from_fen(fen).ok()).collect()
+}
+
+fn report(name: &str, ops: usize, start: Instant) {
+    report_elapsed(name, ops, start.elapsed());
+}
+
+fn report_elapsed(name: &str, ops: usize, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    println!("{name:<18} {:>14.0} ops/s {:>10.2} ns/op", ops as f64 / seconds, seconds * 1e9 / ops as f64);
+}
@@ -1,9 +1,33 @@
+mod adjudication;
+mod annotate;
 mod bench;
+mod datagen;
+mod evalbatch;
+mod matchplay;
+mod microbench;
 mod perft;
+mod pretty;
+mod repl;
+mod rescore;
+mod selftest;
 mod speedtest;
+mod testsuite;
+mod trainingdata;
+mod verify;
 
-pub use bench::bench;
+pub use annotate::annotate;
+pub use bench::{BenchOutput, bench};
+pub use datagen::datagen;
+pub use evalbatch::evalbatch;
+pub use matchplay::matchplay;
+pub use microbench::{bench_movegen, bench_nnue, bench_picker, bench_see};
 pub use perft::is_legal_perft;
 pub use perft::perft;
 pub use perft::simple_perft;
+pub use pretty::PrettyWriter;
+pub use repl::repl;
+pub use rescore::rescore;
+pub use selftest::selftest;
 pub use speedtest::speedtest;
+pub use testsuite::testsuite;
+pub use verify::verify_network;
@@ -3,40 +3,56 @@
 //!
 //! See [Perft](https://www.chessprogramming.org/Perft) for more information.
 
-use std::time::Instant;
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Instant,
+};
 
 use crate::{
     board::{Board, NullBoardObserver},
+    threadpool::ThreadPool,
     types::{Move, MoveList},
 };
 
-pub fn perft(depth: usize, board: &mut Board) {
+/// Size of the perft table in MiB, unless one is given on the command line.
+const DEFAULT_TABLE_SIZE: usize = 64;
+
+/// Counts the leaf nodes after every root move, splitting the root moves among the worker
+/// threads of the pool, which share a table of the subtree counts seen so far.
+pub fn perft(depth: usize, threads: &ThreadPool, megabytes: Option<usize>, board: &Board) {
+    let table = PerftTable::new(megabytes.unwrap_or(DEFAULT_TABLE_SIZE));
+    let moves = board.generate_all_moves().iter().map(|entry| entry.mv).collect::<Vec<_>>();
+
     println!("{}", "-".repeat(60));
-    println!("{:>12} {:>12} {:>13} {:>15}", "Move", "Nodes", "Elapsed", "NPS");
+    println!("{:>12} {:>12}", "Move", "Nodes");
     println!("{}", "-".repeat(60));
 
     let now = Instant::now();
 
-    let mut nodes = 0;
-    let mut index = 0;
+    let next = AtomicUsize::new(0);
+    let counts = (0..moves.len()).map(|_| AtomicU64::new(0)).collect::<Vec<_>>();
 
-    for entry in board.generate_all_moves().iter() {
-        let now = Instant::now();
+    threads.broadcast(|_| {
+        let mut board = board.clone();
 
-        let mv = entry.mv;
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(&mv) = moves.get(index) else {
+                break;
+            };
 
-        board.make_move(mv, &mut NullBoardObserver);
+            board.make_move(mv, &mut NullBoardObserver);
+            counts[index].store(hashed_perft(&table, depth - 1, &mut board), Ordering::Relaxed);
+            board.undo_move(mv);
+        }
+    });
 
-        let count = perft_internal(&|board| board.generate_all_moves(), depth - 1, board);
+    let mut nodes = 0;
+    for (index, (mv, count)) in moves.iter().zip(&counts).enumerate() {
+        let count = count.load(Ordering::Relaxed);
         nodes += count;
-        index += 1;
 
-        board.undo_move(mv);
-
-        let seconds = now.elapsed().as_secs_f64();
-        let knps = count as f64 / seconds / 1000.0;
-
-        println!("{index:>3} {:>8} {count:>12} {seconds:>12.3}s {knps:>15.3} kN/s", mv.to_uci(board));
+        println!("{:>3} {:>8} {count:>12}", index + 1, mv.to_uci(board));
     }
 
     let seconds = now.elapsed().as_secs_f64();
@@ -47,6 +63,61 @@ pub fn perft(depth: usize, board: &mut Board) {
     println!("{}", "-".repeat(60));
 }
 
+fn hashed_perft(table: &PerftTable, depth: usize, board: &mut Board) -> u64 {
+    // The last two plies are bulk counted, which is cheaper than probing the table
+    if depth <= 1 {
+        return perft_internal(&|board| board.generate_all_moves(), depth, board);
+    }
+
+    if let Some(nodes) = table.probe(board.hash(), depth) {
+        return nodes;
+    }
+
+    let mut nodes = 0;
+    for entry in board.generate_all_moves().iter() {
+        board.make_move(entry.mv, &mut NullBoardObserver);
+        nodes += hashed_perft(table, depth - 1, board);
+        board.undo_move(entry.mv);
+    }
+
+    table.store(board.hash(), depth, nodes);
+    nodes
+}
+
+/// A lock-free table of subtree counts. The key is stored xored with the data, so that an entry
+/// torn by a concurrent write fails to verify instead of returning a wrong count.
+struct PerftTable {
+    entries: Vec<[AtomicU64; 2]>,
+}
+
+impl PerftTable {
+    fn new(megabytes: usize) -> Self {
+        let len = (megabytes.max(1) << 20) / std::mem::size_of::<[AtomicU64; 2]>();
+        Self {
+            entries: (0..len).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+        }
+    }
+
+    fn entry(&self, key: u64) -> &[AtomicU64; 2] {
+        &self.entries[((key as u128 * self.entries.len() as u128) >> 64) as usize]
+    }
+
+    fn probe(&self, key: u64, depth: usize) -> Option<u64> {
+        let [stored, data] = self.entry(key);
+        let data = data.load(Ordering::Relaxed);
+
+        (stored.load(Ordering::Relaxed) ^ data == key && data as u8 as usize == depth).then_some(data >> 8)
+    }
+
+    fn store(&self, key: u64, depth: usize, nodes: u64) {
+        let [stored, data] = self.entry(key);
+        let packed = (nodes << 8) | depth as u64;
+
+        stored.store(key ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+}
+
 pub fn simple_perft(depth: usize, board: &mut Board) {
     let mut nodes = 0;
 
@@ -85,7 +156,7 @@ pub fn is_legal_perft(depth: usize, board: &mut Board) {
     println!("total: {nodes}");
 }
 
-fn perft_internal<F: Fn(&Board) -> MoveList>(move_gen: &F, depth: usize, board: &mut Board) -> u64 {
+pub fn perft_internal<F: Fn(&Board) -> MoveList>(move_gen: &F, depth: usize, board: &mut Board) -> u64 {
     if depth == 0 {
         return 1;
     }
@@ -106,7 +177,7 @@ fn perft_internal<F: Fn(&Board) -> MoveList>(move_gen: &F, depth: usize, board:
     nodes
 }
 
-fn is_legal_movegen(board: &Board) -> MoveList {
+pub(super) fn is_legal_movegen(board: &Board) -> MoveList {
     let mut moves = MoveList::new();
     for i in 0..=u16::MAX {
         let j = i >> 12;
@@ -120,3 +191,23 @@ fn is_legal_movegen(board: &Board) -> MoveList {
     }
     moves
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashed_perft_matches_plain_perft() {
+        // A table of a single entry forces constant replacement
+        let tables = [PerftTable::new(1), PerftTable { entries: vec![[AtomicU64::new(0), AtomicU64::new(0)]] }];
+        let mut board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+
+        for table in &tables {
+            for depth in 1..=4 {
+                let expected = perft_internal(&|board| board.generate_all_moves(), depth, &mut board);
+                assert_eq!(hashed_perft(table, depth, &mut board), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Search progress for humans at a terminal, used by the interactive mode and by the UCI loop
+//! with `OutputFormat=pretty`. Every iteration is a row of an aligned table with the score from
+//! White's point of view and the principal variation in SAN:
+//!
+//! ```text
+//!  depth     score      nodes        nps     time  pv
+//!  18/24     +0.31       4.1M       2.1M    1.92s  Nc6 Bb5 Nf6 O-O Nxe4 Re1 Nd6 Nxe5 Be7
+//! ```
+//!
+//! Rows of aspiration windows that failed are rewritten in place by the row that follows them.
+//! Colors are only used when the output is a terminal, and a GUI reading the output through a
+//! pipe gets the plain UCI lines instead, see [`PrettyWriter::is_supported`].
+
+use std::io::{IsTerminal, Write};
+
+use crate::{
+    board::{Board, NullBoardObserver},
+    thread::{InfoScore, SearchInfo, UciWriter},
+    types::{Color, Move, parse_san, to_san},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+
+/// Clears the previous line, with the cursor ending up at its start.
+const REWRITE: &str = "\x1b[1A\x1b[2K\r";
+
+pub struct PrettyWriter {
+    board: Board,
+    color: bool,
+    /// Whether the last row was a failed aspiration window, to be replaced by the next one.
+    provisional: bool,
+}
+
+impl PrettyWriter {
+    pub fn new(board: &Board) -> Self {
+        Self {
+            board: board.clone(),
+            color: std::io::stdout().is_terminal(),
+            provisional: false,
+        }
+    }
+
+    /// Whether the output is a terminal, as anything else is better served by UCI lines.
+    pub fn is_supported() -> bool {
+        std::io::stdout().is_terminal()
+    }
+
+    pub fn print_header(&self) {
+        let header = format!("{:>6}  {:>8}  {:>9}  {:>9}  {:>7}  pv", "depth", "score", "nodes", "nps", "time");
+        println!("{}", self.paint(DIM, &header));
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color && !style.is_empty() { format!("{style}{text}{RESET}") } else { text.to_string() }
+    }
+
+    fn format_score(&self, info: &SearchInfo) -> String {
+        let sign = if self.board.side_to_move() == Color::White { 1 } else { -1 };
+        let (text, style) = match info.score {
+            InfoScore::Cp(cp) => {
+                let style = match sign * cp {
+                    cp if cp > 50 => GREEN,
+                    cp if cp < -50 => RED,
+                    _ => "",
+                };
+                (format!("{:+.2}", (sign * cp) as f64 / 100.0), style)
+            }
+            InfoScore::Mate(moves) => (format!("#{}", sign * moves), YELLOW),
+        };
+
+        // The bound is given for the side to move, so it flips with the score
+        let bound = match (info.lowerbound, info.upperbound) {
+            (true, _) if sign == 1 => "↑",
+            (true, _) => "↓",
+            (_, true) if sign == 1 => "↓",
+            (_, true) => "↑",
+            _ => " ",
+        };
+
+        format!("{}{bound}", self.paint(style, &format!("{text:>7}")))
+    }
+
+    fn format_pv(&self, pv: &[String]) -> String {
+        let mut board = self.board.clone();
+        let mut line = Vec::new();
+
+        for text in pv {
+            let Some(mv) = parse_move(&board, text) else {
+                break;
+            };
+
+            line.push(to_san(&board, mv));
+            board.make_move(mv, &mut NullBoardObserver);
+        }
+
+        match line.split_first() {
+            Some((first, rest)) if !rest.is_empty() => format!("{} {}", self.paint(BOLD, first), rest.join(" ")),
+            Some((first, _)) => self.paint(BOLD, first),
+            None => String::new(),
+        }
+    }
+}
+
+impl UciWriter for PrettyWriter {
+    fn write_line(&mut self, _: &str) {}
+
+    fn write_info(&mut self, info: &SearchInfo) {
+        let depth = format!("{}/{}", info.depth, info.seldepth);
+
+        let row = format!(
+            "{depth:>6}  {}  {:>9}  {:>9}  {:>7}  {}",
+            self.format_score(info),
+            format_nodes(info.nodes),
+            format_nodes(info.nps),
+            format!("{:.2}s", info.time as f64 / 1000.0),
+            self.format_pv(&info.pv)
+        );
+
+        let prefix = if self.provisional && self.color { REWRITE } else { "" };
+        self.provisional = info.upperbound || info.lowerbound;
+
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{prefix}{row}");
+        let _ = stdout.flush();
+    }
+}
+
+pub fn parse_move(board: &Board, text: &str) -> Option<Move> {
+    let moves = board.generate_all_moves();
+    moves.iter().map(|entry| entry.mv).find(|mv| mv.to_uci(board) == text).or_else(|| parse_san(board, text))
+}
+
+fn format_nodes(nodes: u64) -> String {
+    match nodes {
+        0..1_000 => nodes.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", nodes as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1}M", nodes as f64 / 1e6),
+        _ => format!("{:.1}G", nodes as f64 / 1e9),
+    }
+}
@@ -0,0 +1,147 @@
+//! Interactive analysis mode for poking at positions without a GUI, started with `reckless cli`.
+//! Moves are accepted in either SAN or UCI notation, and search progress is printed by the
+//! [`PrettyWriter`]:
+//!
+//! ```text
+//! > play e4 e5 Nf3
+//! > go 5s
+//!  depth     score      nodes        nps     time  pv
+//!  18/24     +0.31       4.1M       2.1M    1.92s  Nc6 Bb5 Nf6 O-O Nxe4 Re1 Nd6 Nxe5 Be7
+//! ```
+
+use std::{
+    io::Write,
+    sync::{Arc, mpsc::Receiver},
+};
+
+use super::pretty::{PrettyWriter, parse_move};
+use crate::{
+    board::{Board, NullBoardObserver},
+    search::Report,
+    thread::SharedContext,
+    threadpool::ThreadPool,
+    time::{Limits, TimeManager},
+    types::to_san,
+};
+
+const HELP: &str = "\
+Commands:
+  d                Show the board
+  fen [<fen>]      Print the FEN of the position, or set up a new one
+  new              Reset to the starting position
+  moves            List the legal moves
+  play <moves>     Play one or more moves in SAN or UCI notation
+  undo [n]         Take back the last n moves (default 1)
+  go [limit]       Search until 'stop', or for a limit such as 5s, 300ms, d12 or 1000000n
+  stop             Stop the search in progress
+  help             Show this message
+  quit             Leave the interactive mode";
+
+pub fn repl(threads: &mut ThreadPool, shared: &Arc<SharedContext>, rx: &Receiver<String>, board: &mut Board) {
+    let mut history = Vec::new();
+
+    println!("Reckless {} interactive mode, type 'help' for a list of commands", env!("ENGINE_VERSION"));
+
+    loop {
+        print!("> ");
+        let _ = std::io::stdout().flush();
+
+        let Ok(message) = rx.recv() else {
+            break;
+        };
+
+        let tokens = message.split_whitespace().collect::<Vec<_>>();
+        match tokens.as_slice() {
+            ["d"] => println!("{board}"),
+            ["fen"] => println!("{}", board.to_fen()),
+            ["fen", fen @ ..] => match Board::from_fen(&fen.join(" ")) {
+                Ok(new_board) => {
+                    *board = new_board;
+                    history.clear();
+                }
+                Err(e) => println!("Invalid FEN: {e:?}"),
+            },
+            ["new"] => {
+                *board = Board::starting_position();
+                history.clear();
+            }
+            ["moves"] => {
+                let mut moves =
+                    board.generate_all_moves().iter().map(|entry| to_san(board, entry.mv)).collect::<Vec<_>>();
+                moves.sort();
+                println!("{} legal moves: {}", moves.len(), moves.join(" "));
+            }
+            ["play", moves @ ..] => {
+                for text in moves {
+                    let Some(mv) = parse_move(board, text) else {
+                        println!("Illegal move: {text}");
+                        break;
+                    };
+
+                    board.make_move(mv, &mut NullBoardObserver);
+                    history.push(mv);
+                }
+            }
+            ["undo", count @ ..] => {
+                let count = count.first().and_then(|count| count.parse().ok()).unwrap_or(1);
+
+                for _ in 0..count {
+                    match history.pop() {
+                        Some(mv) => board.undo_move(mv),
+                        None => break,
+                    }
+                }
+            }
+            ["go", limit @ ..] => match parse_limits(limit) {
+                Some(limits) => search(threads, shared, board, limits),
+                None => println!("Invalid limit: '{}'", limit.join(" ")),
+            },
+            ["help"] => println!("{HELP}"),
+            ["quit" | "exit"] => break,
+            [] => (),
+            _ => println!("Unknown command: '{}', type 'help' for a list of commands", message.trim_end()),
+        }
+    }
+}
+
+fn search(threads: &mut ThreadPool, shared: &Arc<SharedContext>, board: &Board, limits: Limits) {
+    if !board.has_legal_moves() {
+        println!("No legal moves");
+        return;
+    }
+
+    let time_manager = TimeManager::new(limits, board.fullmove_number(), 0);
+
+    let pretty = PrettyWriter::new(board);
+    pretty.print_header();
+
+    let writer = std::mem::replace(&mut threads.main_thread().writer, Box::new(pretty));
+    threads.execute_searches(time_manager, Report::Full, 1, board, &[], shared);
+    threads.main_thread().writer = writer;
+
+    let td = &threads[threads.best_thread()];
+    println!("Best move: {}", to_san(board, td.root_moves[0].mv));
+}
+
+/// Parses the limit of the `go` command, running an infinite search when there is none.
+fn parse_limits(tokens: &[&str]) -> Option<Limits> {
+    let limit = match tokens {
+        [] => return Some(Limits::Infinite),
+        ["depth", depth] => return depth.parse().ok().map(Limits::Depth),
+        ["nodes", nodes] => return nodes.parse().ok().map(Limits::Nodes),
+        [limit] => limit,
+        _ => return None,
+    };
+
+    if let Some(ms) = limit.strip_suffix("ms") {
+        ms.parse().ok().map(Limits::Time)
+    } else if let Some(seconds) = limit.strip_suffix('s') {
+        seconds.parse::<f64>().ok().map(|seconds| Limits::Time((seconds * 1000.0) as u64))
+    } else if let Some(depth) = limit.strip_prefix('d') {
+        depth.parse().ok().map(Limits::Depth)
+    } else if let Some(nodes) = limit.strip_suffix('n') {
+        nodes.parse().ok().map(Limits::Nodes)
+    } else {
+        None
+    }
+}
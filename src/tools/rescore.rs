@@ -0,0 +1,110 @@
+//! Rescores a training dataset in any of the formats of [`super::trainingdata`], which are
+//! chosen by the file extensions, so it also converts between them.
+//!
+//! The input is streamed in batches to the threads, which search every position at a soft
+//! node limit and replace its score, leaving the result untouched. Batches are written as
+//! they finish, so the order of the positions isn't kept.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use super::{
+    datagen::search,
+    trainingdata::{Entry, Reader, Writer},
+};
+use crate::{thread::SharedContext, threadpool::ThreadPool, time::Limits, types::Color};
+
+const DEFAULT_NODES: u64 = 5000;
+const BATCH_SIZE: usize = 1024;
+
+pub fn rescore(args: &[&str]) {
+    let [input, output, rest @ ..] = args else {
+        eprintln!("Usage: rescore <input> <output> [threads] [nodes]");
+        return;
+    };
+
+    // Every thread searches with a table of its own, so there's one per core by default
+    let threads = rest
+        .first()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, ThreadPool::available_threads());
+    let nodes = rest.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_NODES);
+
+    let reader = match Reader::open(input) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!("Failed to open '{input}': {e}");
+            return;
+        }
+    };
+
+    let writer = match Writer::create(output) {
+        Ok(writer) => writer,
+        Err(e) => {
+            eprintln!("Failed to create '{output}': {e}");
+            return;
+        }
+    };
+
+    println!("Rescoring '{input}' into '{output}' with {threads} threads at {nodes} soft nodes per position");
+
+    let entries = Mutex::new(reader);
+    let output = Mutex::new((writer, 0, 0));
+    let time = Instant::now();
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let shared = Arc::new(SharedContext::default());
+                let mut pool = ThreadPool::new(shared.clone());
+
+                loop {
+                    let batch = entries.lock().unwrap().by_ref().take(BATCH_SIZE).collect::<Vec<_>>();
+                    if batch.is_empty() {
+                        break;
+                    }
+
+                    let rescored = batch
+                        .into_iter()
+                        .map(|entry| rescore_entry(&mut pool, &shared, entry?, nodes))
+                        .collect::<Vec<_>>();
+
+                    let mut output = output.lock().unwrap();
+                    let (writer, positions, skipped) = &mut *output;
+
+                    for entry in &rescored {
+                        match entry {
+                            Some(entry) => writer.write(entry).expect("Failed to write training data"),
+                            None => *skipped += 1,
+                        }
+                    }
+                    *positions += rescored.len();
+
+                    let seconds = time.elapsed().as_secs_f64();
+                    println!(
+                        "Positions: {positions:>10} Skipped: {skipped:>8} Speed: {:>8.0} pos/s",
+                        *positions as f64 / seconds
+                    );
+                }
+            });
+        }
+    });
+
+    output.lock().unwrap().0.finish().expect("Failed to write training data");
+}
+
+/// Searches the position of an entry and returns the entry with the new score, or `None` if
+/// the position has no legal moves.
+fn rescore_entry(pool: &mut ThreadPool, shared: &Arc<SharedContext>, entry: Entry, nodes: u64) -> Option<Entry> {
+    if !entry.board.has_legal_moves() {
+        return None;
+    }
+
+    let (_, score) = search(pool, shared, &entry.board, Limits::soft_nodes(nodes));
+    let score = if entry.board.side_to_move() == Color::White { score } else { -score };
+
+    Some(Entry { score, ..entry })
+}
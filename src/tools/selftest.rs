@@ -0,0 +1,193 @@
+//! Consistency checks of the move generator, the incremental board state and the network,
+//! run over random games from the bench positions. Meant to be run after touching the
+//! vectorized movegen or accumulator code:
+//!
+//! - the noisy and quiet moves together match a slow reference generator that tries every
+//!   possible move with `is_legal`,
+//! - every move undoes back to the same position and hash keys, and the incrementally updated
+//!   keys match a recomputation,
+//! - the incrementally updated accumulators evaluate the same as a full refresh,
+//! - the evaluation and the number of legal moves are unchanged when the colors are flipped.
+
+use std::{collections::HashSet, sync::Arc, time::Instant};
+
+use super::{bench::POSITIONS, perft::is_legal_movegen};
+use crate::{
+    board::{Board, NullBoardObserver},
+    rng::Rng,
+    thread::SharedContext,
+    threadpool::ThreadPool,
+    types::{Color, Move, MoveList},
+};
+
+const DEFAULT_GAMES: usize = 200;
+const MAX_PLIES: usize = 120;
+
+/// Failures beyond this are only counted, so that a broken build doesn't flood the output.
+const MAX_REPORTED: usize = 10;
+
+pub fn selftest(args: &[&str]) {
+    let games = args.first().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_GAMES);
+    let seed = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    // Separate networks for the incremental updates and the full refreshes
+    let mut incremental = ThreadPool::new(Arc::new(SharedContext::default()));
+    let mut refreshed = ThreadPool::new(Arc::new(SharedContext::default()));
+    let incremental = &mut incremental.main_thread().nnue;
+    let refreshed = &mut refreshed.main_thread().nnue;
+
+    let mut rng = Rng::new(seed);
+    let mut positions = 0;
+    let mut failures = 0;
+    let time = Instant::now();
+
+    let mut fail = |board: &Board, message: String| {
+        failures += 1;
+        if failures <= MAX_REPORTED {
+            println!("FAIL {message}: {}", board.to_fen());
+        }
+    };
+
+    for game in 0..games {
+        let mut board = Board::from_fen(POSITIONS[game % POSITIONS.len()]).unwrap();
+        let mut history = Vec::new();
+
+        incremental.full_refresh(&board);
+
+        for _ in 0..MAX_PLIES {
+            positions += 1;
+
+            let moves = check_movegen(&board, &mut fail);
+            check_undo(&mut board, &moves, &mut fail);
+
+            refreshed.full_refresh(&board);
+            let expected = refreshed.evaluate(&board);
+
+            let eval = incremental.evaluate(&board);
+            if eval != expected {
+                fail(&board, format!("incremental eval {eval} differs from refreshed eval {expected}"));
+            }
+
+            match Board::from_fen(&flip_fen(&board.to_fen())) {
+                Ok(flipped) => {
+                    refreshed.full_refresh(&flipped);
+                    let eval = refreshed.evaluate(&flipped);
+                    if eval != expected {
+                        fail(&board, format!("eval {expected} changes to {eval} when the colors are flipped"));
+                    }
+
+                    let count = flipped.generate_all_moves().len();
+                    if count != moves.len() {
+                        fail(&board, format!("{} legal moves become {count} when the colors are flipped", moves.len()));
+                    }
+                }
+                Err(e) => fail(&board, format!("flipped position doesn't parse ({e:?})")),
+            }
+
+            if moves.is_empty() || board.draw_by_fifty_move_rule() {
+                break;
+            }
+
+            let mv = moves[rng.next() as usize % moves.len()];
+            incremental.push(mv, &board);
+            board.make_move(mv, incremental);
+            history.push(mv);
+
+            check_keys(&board, &mut fail);
+        }
+
+        for &mv in history.iter().rev() {
+            incremental.pop();
+            board.undo_move(mv);
+        }
+    }
+
+    println!("Games:     {games}");
+    println!("Positions: {positions}");
+    println!("Failures:  {failures}");
+    println!("Time:      {:.2}s", time.elapsed().as_secs_f64());
+}
+
+/// Checks the noisy and quiet moves against the reference generator, returning the legal moves.
+fn check_movegen(board: &Board, fail: &mut impl FnMut(&Board, String)) -> Vec<Move> {
+    let mut noisy = MoveList::new();
+    let mut quiet = MoveList::new();
+    board.append_noisy_moves(&mut noisy);
+    board.append_quiet_moves(&mut quiet);
+
+    if let Some(entry) = quiet.iter().find(|entry| entry.mv.is_capture()) {
+        fail(board, format!("capture {} among the quiet moves", entry.mv.to_uci(board)));
+    }
+
+    let moves = noisy.iter().chain(quiet.iter()).map(|entry| entry.mv).collect::<Vec<_>>();
+    let generated = moves.iter().copied().collect::<HashSet<_>>();
+    let reference = is_legal_movegen(board).iter().map(|entry| entry.mv).collect::<HashSet<_>>();
+
+    if generated.len() != moves.len() {
+        fail(board, "duplicate moves generated".to_string());
+    }
+
+    for mv in reference.difference(&generated) {
+        fail(board, format!("legal move {} not generated", mv.to_uci(board)));
+    }
+
+    for mv in generated.difference(&reference) {
+        fail(board, format!("illegal move {} generated", mv.to_uci(board)));
+    }
+
+    moves
+}
+
+/// Checks that making and undoing every move restores the position and its hash keys.
+fn check_undo(board: &mut Board, moves: &[Move], fail: &mut impl FnMut(&Board, String)) {
+    let fen = board.to_fen();
+    let hash = board.hash();
+
+    for &mv in moves {
+        board.make_move(mv, &mut NullBoardObserver);
+        check_keys(board, fail);
+        board.undo_move(mv);
+
+        if board.to_fen() != fen || board.hash() != hash {
+            fail(board, format!("undoing {} doesn't restore the position", mv.to_uci(board)));
+        }
+    }
+}
+
+fn check_keys(board: &Board, fail: &mut impl FnMut(&Board, String)) {
+    let mut recomputed = board.clone();
+    recomputed.update_hash_keys();
+
+    let keys = |board: &Board| {
+        (board.hash(), board.pawn_key(), board.non_pawn_key(Color::White), board.non_pawn_key(Color::Black))
+    };
+
+    if keys(board) != keys(&recomputed) {
+        fail(board, "incremental hash keys differ from a recomputation".to_string());
+    }
+}
+
+/// Mirrors the position vertically and swaps the colors of the pieces and the side to move.
+fn flip_fen(fen: &str) -> String {
+    let fields = fen.split_whitespace().collect::<Vec<_>>();
+    let swap_case = |text: &str| {
+        text.chars()
+            .map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+            .collect::<String>()
+    };
+
+    let placement = fields[0].split('/').rev().map(swap_case).collect::<Vec<_>>();
+    let side = if fields[1] == "w" { "b" } else { "w" };
+
+    let mut castling = swap_case(fields[2]).chars().collect::<Vec<_>>();
+    castling.sort_by_key(|c| c.is_ascii_lowercase());
+
+    let en_passant = match fields[3].as_bytes() {
+        [file, rank] => format!("{}{}", *file as char, (b'1' + b'8' - rank) as char),
+        _ => fields[3].to_string(),
+    };
+
+    let mut flipped = vec![placement.join("/"), side.to_string(), castling.into_iter().collect(), en_passant];
+    flipped.extend(fields[4..].iter().map(|field| field.to_string()));
+    flipped.join(" ")
+}
@@ -73,41 +73,55 @@ pub fn speedtest(args: &[&str]) {
     let hash = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_HASH_PER_THREAD * threads);
     let duration = args.get(2).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_DURATION);
 
+    if threads == 0 {
+        eprintln!("Speedtest needs at least one thread");
+        return;
+    }
+
     let ms_per_position = duration * 1000 / POSITIONS.len() as u64; // milliseconds
 
     println!("Engine Version:    {}", env!("ENGINE_VERSION"));
     println!("Compiler Version:  {}", env!("COMPILER_VERSION"));
     println!("Compiler Target:   {}", env!("COMPILER_TARGET"));
     println!("Compiler Features: {}", env!("COMPILER_FEATURES"));
-    println!("Threads:           {threads}");
-    println!("Hash (MiB):        {hash}");
 
     let shared = Arc::new(SharedContext::default());
-    shared.tt.resize(threads, hash);
+    shared.tt.resize(hash);
 
     let mut pool = ThreadPool::new(shared.clone());
     pool.set_count(threads);
+    pool.clear_hash();
+
+    // The pool is limited to the available threads
+    let threads = pool.len();
+
+    println!("Threads:           {threads}");
+    println!("Hash (MiB):        {hash}");
 
     for (index, &position) in POSITIONS.iter().take(WARMUP_POSITIONS_COUNT).enumerate() {
         eprint!("Warmup {} of {}\r", index + 1, WARMUP_POSITIONS_COUNT);
 
         let board = Board::from_fen(position).unwrap();
         let time_manager = TimeManager::new(Limits::Time(ms_per_position), 0, 0);
-        pool.execute_searches(time_manager, Report::None, 1, &board, &shared);
+        pool.execute_searches(time_manager, Report::None, 1, &board, &[], &shared);
     }
     eprintln!();
 
     let time = Instant::now();
     let mut nodes = 0;
+    let mut thread_nodes = vec![0; threads];
 
     for (index, &position) in POSITIONS.iter().enumerate() {
         eprint!("Position {} of {}\r", index + 1, POSITIONS.len());
 
         let board = Board::from_fen(position).unwrap();
         let time_manager = TimeManager::new(Limits::Time(ms_per_position), 0, 0);
-        pool.execute_searches(time_manager, Report::None, 1, &board, &shared);
+        pool.execute_searches(time_manager, Report::None, 1, &board, &[], &shared);
 
         nodes += shared.nodes.aggregate();
+        for (id, count) in thread_nodes.iter_mut().enumerate() {
+            *count += shared.nodes.get(id);
+        }
     }
     eprintln!();
 
@@ -118,5 +132,33 @@ pub fn speedtest(args: &[&str]) {
     println!("Total time (sec):  {seconds:.2}");
     println!("Nodes/second:      {}", nps as u64);
 
+    // An uneven spread across threads hints at oversubscription or threads sharing cores
+    let thread_nps = thread_nodes.iter().map(|&count| count as f64 / seconds).collect::<Vec<_>>();
+    let mean = nps / threads as f64;
+    let deviation = (thread_nps.iter().map(|nps| (nps - mean).powi(2)).sum::<f64>() / threads as f64).sqrt();
+    let min = thread_nps.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = thread_nps.iter().copied().fold(0.0, f64::max);
+
+    println!(
+        "Thread NPS:        mean {}, min {}, max {}, deviation {:.1}%",
+        mean as u64,
+        min as u64,
+        max as u64,
+        100.0 * deviation / mean
+    );
+
+    println!("{}", shared.numa_context.describe(threads));
+
+    let cfg = shared.numa_context.get_numa_config();
+    if cfg.suggests_binding_threads(threads) {
+        let mut node_nodes = vec![0; cfg.num_numa_nodes()];
+        for (id, node) in cfg.distribute_threads_among_numa_nodes(threads).into_iter().enumerate() {
+            node_nodes[node] += thread_nodes[id];
+        }
+
+        let node_nps = node_nodes.iter().map(|&count| (count as f64 / seconds) as u64).map(|nps| nps.to_string());
+        println!("NUMA node NPS:     {}", node_nps.collect::<Vec<_>>().join(":"));
+    }
+
     crate::misc::dbg_print();
 }
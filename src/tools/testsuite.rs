@@ -0,0 +1,145 @@
+//! Runs test suites in [Extended Position Description][epd] format, where each position
+//! is searched for a fixed time and the move played is checked against the `bm` (best
+//! move) and `am` (avoid move) operations.
+//!
+//! Suites such as STS also award partial credit through a `c0` comment that lists
+//! moves with their points, e.g. `c0 "Nf3=10, Nc3=5"`, which is then used for scoring.
+//!
+//! [epd]: https://www.chessprogramming.org/Extended_Position_Description
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    board::Board,
+    search::Report,
+    thread::SharedContext,
+    threadpool::ThreadPool,
+    time::{Limits, TimeManager},
+    types::{Move, parse_san},
+};
+
+const DEFAULT_MOVETIME: u64 = 1000;
+
+struct Position {
+    board: Board,
+    id: String,
+    best_moves: Vec<Move>,
+    avoid_moves: Vec<Move>,
+    points: HashMap<Move, u32>,
+}
+
+pub fn testsuite(args: &[&str]) {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: testsuite <file.epd> [movetime]");
+        return;
+    };
+
+    let movetime = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_MOVETIME);
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Failed to read '{path}': {e}");
+            return;
+        }
+    };
+
+    let shared = Arc::new(SharedContext::default());
+    let mut pool = ThreadPool::new(shared.clone());
+
+    let mut total = 0;
+    let mut solved = 0;
+    let mut points = 0;
+    let mut max_points = 0;
+
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let Some(position) = parse_epd(line) else {
+            eprintln!("Invalid EPD: '{line}'");
+            continue;
+        };
+
+        pool.clear();
+        pool.clear_hash();
+
+        let time_manager = TimeManager::new(Limits::Time(movetime), position.board.fullmove_number(), 0);
+        pool.execute_searches(time_manager, Report::None, 1, &position.board, &[], &shared);
+
+        let mv = pool.main_thread().root_moves[0].mv;
+
+        let passed = (position.best_moves.is_empty() || position.best_moves.contains(&mv))
+            && !position.avoid_moves.contains(&mv);
+
+        total += 1;
+        solved += passed as usize;
+
+        if position.points.is_empty() {
+            points += passed as u32;
+            max_points += 1;
+        } else {
+            points += position.points.get(&mv).copied().unwrap_or_default();
+            max_points += position.points.values().max().copied().unwrap_or_default();
+        }
+
+        println!(
+            "{total:>5} {:<4} {:<24} {:<6} {}",
+            if passed { "pass" } else { "fail" },
+            position.id,
+            mv.to_uci(&position.board),
+            expected(&position)
+        );
+    }
+
+    println!("{}", "-".repeat(60));
+    println!("Solved: {solved}/{total}");
+    println!("Score:  {points}/{max_points}");
+}
+
+fn parse_epd(line: &str) -> Option<Position> {
+    let mut fields = line.splitn(5, ' ');
+    let fen = fields.by_ref().take(4).collect::<Vec<_>>().join(" ");
+    let board = Board::from_fen(&fen).ok()?;
+
+    let mut id = String::new();
+    let mut best_moves = Vec::new();
+    let mut avoid_moves = Vec::new();
+    let mut points = HashMap::new();
+
+    let parse_moves = |operands: &str| operands.split_whitespace().filter_map(|san| parse_san(&board, san)).collect();
+
+    for operation in fields.next().unwrap_or_default().split(';') {
+        let operation = operation.trim();
+        let (opcode, operands) = operation.split_once(' ').unwrap_or((operation, ""));
+        let operands = operands.trim().trim_matches('"');
+
+        match opcode {
+            "id" => id = operands.to_string(),
+            "bm" => best_moves = parse_moves(operands),
+            "am" => avoid_moves = parse_moves(operands),
+            "c0" => {
+                for entry in operands.split(',') {
+                    if let Some((san, value)) = entry.trim().rsplit_once('=')
+                        && let (Some(mv), Ok(value)) = (parse_san(&board, san), value.parse())
+                    {
+                        points.insert(mv, value);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Some(Position { board, id, best_moves, avoid_moves, points })
+}
+
+fn expected(position: &Position) -> String {
+    let format = |moves: &[Move]| moves.iter().map(|mv| mv.to_uci(&position.board)).collect::<Vec<_>>().join(" ");
+
+    let mut expected = Vec::new();
+    if !position.best_moves.is_empty() {
+        expected.push(format!("bm {}", format(&position.best_moves)));
+    }
+    if !position.avoid_moves.is_empty() {
+        expected.push(format!("am {}", format(&position.avoid_moves)));
+    }
+    expected.join("; ")
+}
@@ -0,0 +1,820 @@
+//! Readers and writers of training data, chosen by the file extension:
+//!
+//! - `.binpack`: the [binpack format][binpack] of Stockfish, in which consecutive positions of a
+//!   game are stored as the moves between them, along with the score deltas.
+//! - `.bullet` or `.data`: the fixed 32 byte records of [bulletformat], with the position seen
+//!   from the side to move.
+//! - Anything else: the `<fen> | <score> | <result>` lines written by `datagen`.
+//!
+//! Binpack only covers standard chess, and bulletformat keeps neither castling rights nor the
+//! en passant square.
+//!
+//! [binpack]: https://github.com/official-stockfish/nnue-pytorch/blob/master/lib/nnue_training_data_formats.h
+//! [bulletformat]: https://github.com/jw1912/bulletformat
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Lines, Read, Write},
+};
+
+use crate::{
+    board::{Board, NullBoardObserver},
+    lookup::{attacks, king_attacks, pawn_attacks},
+    types::{Bitboard, CastlingKind, Color, Move, PieceType, Rank, Square},
+};
+
+/// Chunks are written once they reach this size, and chains never span chunks.
+const CHUNK_SIZE: usize = 1 << 20;
+
+const STEM_SIZE: usize = 32;
+const BULLET_RECORD_SIZE: usize = 32;
+const SCORE_VLE_BLOCK_SIZE: u32 = 4;
+const MAX_CHAIN_PLIES: u16 = u16::MAX;
+
+/// A position with its score and game result, both from White's point of view.
+#[derive(Clone)]
+pub(super) struct Entry {
+    pub(super) board: Board,
+    pub(super) score: i32,
+    pub(super) result: f32,
+    /// The move played in the position, if known. Binpack chains are built from these.
+    pub(super) mv: Move,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub(super) enum Format {
+    Text,
+    Bullet,
+    Binpack,
+}
+
+impl Format {
+    pub(super) fn from_path(path: &str) -> Self {
+        match path.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("binpack") => Self::Binpack,
+            Some("bullet" | "data") => Self::Bullet,
+            _ => Self::Text,
+        }
+    }
+
+    /// Whether the format benefits from every position of a game rather than only the ones
+    /// worth training on, which the trainers filter themselves.
+    pub(super) fn keeps_whole_games(self) -> bool {
+        self == Self::Binpack
+    }
+}
+
+pub(super) enum Reader {
+    Text(Lines<BufReader<File>>),
+    Bullet(BufReader<File>),
+    Binpack(BinpackReader),
+}
+
+impl Reader {
+    pub(super) fn open(path: &str) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+
+        Ok(match Format::from_path(path) {
+            Format::Text => Self::Text(reader.lines()),
+            Format::Bullet => Self::Bullet(reader),
+            Format::Binpack => Self::Binpack(BinpackReader { reader, entries: Vec::new() }),
+        })
+    }
+}
+
+/// Yields the entries of the file, with `None` for records that can't be understood.
+impl Iterator for Reader {
+    type Item = Option<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Text(lines) => lines.next()?.ok().map(|line| parse_text(&line)),
+            Self::Bullet(reader) => {
+                let mut record = [0; BULLET_RECORD_SIZE];
+                reader.read_exact(&mut record).ok()?;
+                Some(decode_bullet(&record))
+            }
+            Self::Binpack(reader) => reader.next(),
+        }
+    }
+}
+
+pub(super) enum Writer {
+    Text(BufWriter<File>),
+    Bullet(BufWriter<File>),
+    Binpack(BinpackWriter),
+}
+
+impl Writer {
+    pub(super) fn create(path: &str) -> std::io::Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+
+        Ok(match Format::from_path(path) {
+            Format::Text => Self::Text(writer),
+            Format::Bullet => Self::Bullet(writer),
+            Format::Binpack => Self::Binpack(BinpackWriter { writer, chunk: Vec::new(), chain: None }),
+        })
+    }
+
+    pub(super) fn write(&mut self, entry: &Entry) -> std::io::Result<()> {
+        match self {
+            Self::Text(writer) => {
+                writeln!(writer, "{} | {} | {:.1}", entry.board.to_fen(), entry.score, entry.result)
+            }
+            Self::Bullet(writer) => writer.write_all(&encode_bullet(entry)),
+            Self::Binpack(writer) => writer.write(entry),
+        }
+    }
+
+    pub(super) fn finish(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Text(writer) | Self::Bullet(writer) => writer.flush(),
+            Self::Binpack(writer) => {
+                writer.end_chain();
+                writer.flush_chunk()?;
+                writer.writer.flush()
+            }
+        }
+    }
+}
+
+fn parse_text(line: &str) -> Option<Entry> {
+    let [fen, score, result] = line.split('|').map(str::trim).collect::<Vec<_>>()[..] else {
+        return None;
+    };
+
+    Some(Entry {
+        board: Board::from_fen(fen).ok()?,
+        score: score.parse().ok()?,
+        result: result.parse().ok()?,
+        mv: Move::NULL,
+    })
+}
+
+/// Converts between the point of view of White and of the side to move.
+fn relative(board: &Board, score: i32, result: f32) -> (i32, f32) {
+    match board.side_to_move() {
+        Color::White => (score, result),
+        Color::Black => (-score, 1.0 - result),
+    }
+}
+
+fn decode_bullet(record: &[u8; BULLET_RECORD_SIZE]) -> Option<Entry> {
+    let occupancies = Bitboard(u64::from_le_bytes(record[0..8].try_into().unwrap()));
+    let score = i16::from_le_bytes([record[24], record[25]]);
+    let result = record[26];
+
+    let mut pieces = [None; 64];
+    for (index, square) in occupancies.enumerate() {
+        let nibble = (record[8 + index / 2] >> (4 * (index & 1))) & 15;
+        let c = *PIECE_CHARS.get(nibble as usize & 7)?;
+
+        // The side to move is always stored as White
+        pieces[square as usize] = Some(if nibble & 8 == 0 { c.to_ascii_uppercase() } else { c });
+    }
+
+    let fen = format!("{} w - - 0 1", placement(&pieces));
+    Some(Entry {
+        board: Board::from_fen(&fen).ok()?,
+        score: score as i32,
+        result: result as f32 / 2.0,
+        mv: Move::NULL,
+    })
+}
+
+fn encode_bullet(entry: &Entry) -> [u8; BULLET_RECORD_SIZE] {
+    let board = &entry.board;
+    let stm = board.side_to_move();
+    let (score, result) = relative(board, entry.score, entry.result);
+
+    // The board is mirrored for Black, so that the side to move is always White
+    let flip = |square: Square| if stm == Color::White { square as usize } else { square as usize ^ 56 };
+
+    let mut pieces = [None; 64];
+    for square in board.occupancies() {
+        let piece = board.piece_on(square);
+        pieces[flip(square)] = Some(((piece.color() != stm) as u8) << 3 | piece.piece_type() as u8);
+    }
+
+    let mut record = [0; BULLET_RECORD_SIZE];
+    let mut occupancies = 0u64;
+
+    for (index, (square, nibble)) in
+        pieces.iter().enumerate().filter_map(|(square, nibble)| Some((square, (*nibble)?))).enumerate()
+    {
+        occupancies |= 1 << square;
+        record[8 + index / 2] |= nibble << (4 * (index & 1));
+    }
+
+    record[0..8].copy_from_slice(&occupancies.to_le_bytes());
+    record[24..26].copy_from_slice(&(score.clamp(i16::MIN as i32, i16::MAX as i32) as i16).to_le_bytes());
+    record[26] = (2.0 * result).round() as u8;
+    record[27] = flip(board.king_square(stm)) as u8;
+    record[28] = (flip(board.king_square(!stm)) ^ 56) as u8;
+    record
+}
+
+const PIECE_CHARS: [char; 6] = ['p', 'n', 'b', 'r', 'q', 'k'];
+
+/// The piece placement field of a FEN, from pieces indexed by square.
+fn placement(pieces: &[Option<char>; 64]) -> String {
+    let mut ranks = Vec::new();
+
+    for rank in (0..8).rev() {
+        let mut text = String::new();
+        let mut empty = 0;
+
+        for file in 0..8 {
+            match pieces[rank * 8 + file] {
+                Some(c) => {
+                    if empty > 0 {
+                        text.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    text.push(c);
+                }
+                None => empty += 1,
+            }
+        }
+
+        if empty > 0 {
+            text.push_str(&empty.to_string());
+        }
+        ranks.push(text);
+    }
+
+    ranks.join("/")
+}
+
+pub(super) struct BinpackReader {
+    reader: BufReader<File>,
+    /// The entries of the current chunk, in reverse order.
+    entries: Vec<Option<Entry>>,
+}
+
+impl BinpackReader {
+    fn next(&mut self) -> Option<Option<Entry>> {
+        while self.entries.is_empty() {
+            let mut header = [0; 8];
+            self.reader.read_exact(&mut header).ok()?;
+
+            if &header[0..4] != b"BINP" {
+                return None;
+            }
+
+            let mut chunk = vec![0; u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize];
+            self.reader.read_exact(&mut chunk).ok()?;
+
+            self.entries = decode_chunk(&chunk);
+            self.entries.reverse();
+        }
+
+        self.entries.pop()
+    }
+}
+
+fn decode_chunk(chunk: &[u8]) -> Vec<Option<Entry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+
+    while offset + STEM_SIZE + 2 <= chunk.len() {
+        let stem = &chunk[offset..offset + STEM_SIZE];
+        let plies = u16::from_be_bytes([chunk[offset + STEM_SIZE], chunk[offset + STEM_SIZE + 1]]);
+        offset += STEM_SIZE + 2;
+
+        let Some(mut entry) = decode_stem(stem) else {
+            // Without the position the length of the movetext is unknown
+            entries.push(None);
+            break;
+        };
+
+        let mut reader = BitReader { bytes: &chunk[offset..], position: 0 };
+        let mut last_score = -relative(&entry.board, entry.score, entry.result).0;
+
+        entries.push(Some(entry.clone()));
+
+        for _ in 0..plies {
+            let (_, result) = relative(&entry.board, entry.score, entry.result);
+            entry.board.make_move(entry.mv, &mut NullBoardObserver);
+
+            let Some(mv) = decode_move(&entry.board, &mut reader) else {
+                entries.push(None);
+                return entries;
+            };
+
+            let score = (last_score + unsigned_to_signed(reader.read_vle()) as i32) as i16 as i32;
+            last_score = -score;
+
+            // The result of the side to move flips with every ply
+            let (score, result) = relative(&entry.board, score, 1.0 - result);
+            entry = Entry { board: entry.board, score, result, mv };
+            entries.push(Some(entry.clone()));
+        }
+
+        offset += reader.position.div_ceil(8);
+    }
+
+    entries
+}
+
+fn decode_stem(stem: &[u8]) -> Option<Entry> {
+    let occupancies = Bitboard(u64::from_be_bytes(stem[0..8].try_into().unwrap()));
+
+    let mut pieces = [None; 64];
+    let mut castling = String::new();
+    let mut en_passant = None;
+    let mut black_to_move = false;
+
+    for (index, square) in occupancies.enumerate() {
+        let nibble = (stem[8 + index / 2] >> (4 * (index & 1))) & 15;
+
+        let c = match nibble {
+            0..12 => {
+                let c = PIECE_CHARS[nibble as usize / 2];
+                if nibble & 1 == 0 { c.to_ascii_uppercase() } else { c }
+            }
+            // A pawn that has just made a double push
+            12 if square.rank() == Rank::R4 => {
+                en_passant = Some(square.shift(-8));
+                'P'
+            }
+            12 => {
+                en_passant = Some(square.shift(8));
+                'p'
+            }
+            13 | 14 => {
+                let right = match square {
+                    Square::H1 => 'K',
+                    Square::A1 => 'Q',
+                    Square::H8 => 'k',
+                    _ => 'q',
+                };
+                castling.push(right);
+                if nibble == 13 { 'R' } else { 'r' }
+            }
+            _ => {
+                black_to_move = true;
+                'k'
+            }
+        };
+        pieces[square as usize] = Some(c);
+    }
+
+    let mut castling = castling.chars().collect::<Vec<_>>();
+    castling.sort_by_key(|c| "KQkq".find(*c));
+    let castling = if castling.is_empty() { "-".to_string() } else { castling.into_iter().collect() };
+
+    let mv = u16::from_be_bytes([stem[24], stem[25]]);
+    let score = unsigned_to_signed(u16::from_be_bytes([stem[26], stem[27]]));
+    let ply_result = u16::from_be_bytes([stem[28], stem[29]]);
+    let rule50 = u16::from_be_bytes([stem[30], stem[31]]);
+
+    let ply = (ply_result & 0x3FFF) as usize;
+    let result = unsigned_to_signed(ply_result >> 14);
+
+    let fen = format!(
+        "{} {} {castling} {} {rule50} {}",
+        placement(&pieces),
+        if black_to_move { 'b' } else { 'w' },
+        en_passant.map_or_else(|| "-".to_string(), |square| square.to_string()),
+        ply / 2 + 1
+    );
+    let board = Board::from_fen(&fen).ok()?;
+
+    let mv = if mv == 0 {
+        Move::NULL
+    } else {
+        find_move(&board, mv >> 14, Square::new((mv >> 8) as u8 & 63), Square::new((mv >> 2) as u8 & 63), mv & 3)?
+    };
+    let (score, result) = relative(&board, score as i32, (result as f32 + 1.0) / 2.0);
+
+    Some(Entry { board, score, result, mv })
+}
+
+/// Finds the legal move matching a compressed binpack move, in which castling is encoded as
+/// the king moving to the rook's square.
+fn find_move(board: &Board, kind: u16, from: Square, to: Square, promotion: u16) -> Option<Move> {
+    board.generate_all_moves().iter().map(|entry| entry.mv).find(|&mv| {
+        mv.from() == from
+            && binpack_target(board, mv) == to
+            && kind == binpack_kind(mv)
+            && (!mv.is_promotion() || mv.promo_piece_type() as u16 - PieceType::Knight as u16 == promotion)
+    })
+}
+
+fn binpack_target(board: &Board, mv: Move) -> Square {
+    if mv.is_castling() { board.get_castling_rook(mv.to()).0 } else { mv.to() }
+}
+
+const fn binpack_kind(mv: Move) -> u16 {
+    if mv.is_promotion() {
+        1
+    } else if mv.is_castling() {
+        2
+    } else if mv.is_en_passant() {
+        3
+    } else {
+        0
+    }
+}
+
+fn encode_move(board: &Board, mv: Move) -> u16 {
+    if mv.is_null() {
+        return 0;
+    }
+
+    let promotion = if mv.is_promotion() { mv.promo_piece_type() as u16 - PieceType::Knight as u16 } else { 0 };
+    (binpack_kind(mv) << 14) | ((mv.from() as u16) << 8) | ((binpack_target(board, mv) as u16) << 2) | promotion
+}
+
+/// The squares a piece could move to in the movetext encoding, which counts pseudo-legal
+/// destinations, and for kings the castling rights instead of the castling moves.
+fn destinations(board: &Board, from: Square) -> (Bitboard, usize) {
+    let stm = board.side_to_move();
+    let ours = board.colors(stm);
+    let occupancies = board.occupancies();
+
+    match board.type_on(from) {
+        PieceType::Pawn => {
+            let mut targets = board.colors(!stm);
+            if board.en_passant() != Square::None {
+                targets |= board.en_passant().to_bb();
+            }
+
+            let mut destinations = pawn_attacks(from, stm) & targets;
+
+            let forward = if stm == Color::White { 8 } else { -8 };
+            let single = from.shift(forward);
+            if !occupancies.contains(single) {
+                destinations |= single.to_bb();
+
+                let start = if stm == Color::White { Rank::R2 } else { Rank::R7 };
+                if from.rank() == start && !occupancies.contains(single.shift(forward)) {
+                    destinations |= single.shift(forward).to_bb();
+                }
+            }
+
+            (destinations, 0)
+        }
+        PieceType::King => {
+            let rights = CastlingKind::KINDS[stm].iter().filter(|&&kind| board.castling().is_allowed(kind)).count();
+            (king_attacks(from) & !ours, rights)
+        }
+        _ => (attacks(board.piece_on(from), from, occupancies) & !ours, 0),
+    }
+}
+
+fn is_promoting(board: &Board, from: Square) -> bool {
+    let rank = if board.side_to_move() == Color::White { Rank::R7 } else { Rank::R2 };
+    board.type_on(from) == PieceType::Pawn && from.rank() == rank
+}
+
+fn before(bitboard: Bitboard, square: Square) -> usize {
+    (bitboard & Bitboard((1 << square as u64) - 1)).popcount()
+}
+
+fn nth(bitboard: Bitboard, index: usize) -> Option<Square> {
+    bitboard.into_iter().nth(index)
+}
+
+fn used_bits(value: usize) -> u32 {
+    usize::BITS - value.leading_zeros()
+}
+
+fn encode_ply(writer: &mut BitWriter, board: &Board, mv: Move) {
+    let ours = board.colors(board.side_to_move());
+    let from = mv.from();
+    let (destinations, castlings) = destinations(board, from);
+
+    let (id, count) = if is_promoting(board, from) {
+        let promotion = mv.promo_piece_type() as usize - PieceType::Knight as usize;
+        (before(destinations, mv.to()) * 4 + promotion, destinations.popcount() * 4)
+    } else if mv.is_castling() {
+        let queenside = CastlingKind::KINDS[board.side_to_move()][0];
+        let short = mv.to().file() > from.file();
+        let id = destinations.popcount() - 1 + board.castling().is_allowed(queenside) as usize + short as usize;
+        (id, destinations.popcount() + castlings)
+    } else {
+        (before(destinations, mv.to()), destinations.popcount() + castlings)
+    };
+
+    writer.write(before(ours, from) as u8, used_bits(ours.popcount() - 1));
+    writer.write(id as u8, used_bits(count - 1));
+}
+
+fn decode_move(board: &Board, reader: &mut BitReader) -> Option<Move> {
+    let ours = board.colors(board.side_to_move());
+    let from = nth(ours, reader.read(used_bits(ours.popcount() - 1)) as usize)?;
+    let (destinations, castlings) = destinations(board, from);
+
+    let moves = board.generate_all_moves();
+    let mut candidates = moves.iter().map(|entry| entry.mv).filter(|mv| mv.from() == from);
+
+    if is_promoting(board, from) {
+        let id = reader.read(used_bits(destinations.popcount() * 4 - 1)) as usize;
+        let to = nth(destinations, id / 4)?;
+        candidates.find(|mv| mv.to() == to && mv.promo_piece_type() as usize - PieceType::Knight as usize == id % 4)
+    } else {
+        let count = destinations.popcount() + castlings;
+        if count == 0 {
+            return None;
+        }
+
+        let id = reader.read(used_bits(count - 1)) as usize;
+        match nth(destinations, id) {
+            Some(to) => candidates.find(|mv| mv.to() == to && !mv.is_castling()),
+            None => {
+                let queenside = CastlingKind::KINDS[board.side_to_move()][0];
+                let short = id - destinations.popcount() == board.castling().is_allowed(queenside) as usize;
+                candidates.find(|mv| mv.is_castling() && (mv.to().file() > from.file()) == short)
+            }
+        }
+    }
+}
+
+/// Maps small magnitudes of either sign to small unsigned values, as binpack does for scores.
+const fn signed_to_unsigned(value: i16) -> u16 {
+    let mut bits = value as u16;
+    if bits & 0x8000 != 0 {
+        bits ^= 0x7FFF;
+    }
+    bits.rotate_left(1)
+}
+
+const fn unsigned_to_signed(bits: u16) -> i16 {
+    let mut bits = bits.rotate_right(1);
+    if bits & 0x8000 != 0 {
+        bits ^= 0x7FFF;
+    }
+    bits as i16
+}
+
+pub(super) struct BinpackWriter {
+    writer: BufWriter<File>,
+    chunk: Vec<u8>,
+    chain: Option<Box<Chain>>,
+}
+
+/// A stem followed by the moves and scores of the positions after it.
+struct Chain {
+    bytes: Vec<u8>,
+    plies: u16,
+    movetext: BitWriter,
+    /// The last entry of the chain, which a continuation has to follow.
+    last: Entry,
+    last_score: i32,
+}
+
+impl BinpackWriter {
+    fn write(&mut self, entry: &Entry) -> std::io::Result<()> {
+        let (score, _) = relative(&entry.board, entry.score, entry.result);
+        let score = score.clamp(i16::MIN as i32, i16::MAX as i32);
+
+        if let Some(chain) = &mut self.chain
+            && chain.plies < MAX_CHAIN_PLIES
+            && entry.mv.is_present()
+            && chain.last.mv.is_present()
+            && chain.last.result == entry.result
+            && follows(&chain.last, entry)
+        {
+            encode_ply(&mut chain.movetext, &entry.board, entry.mv);
+            chain.movetext.write_vle(signed_to_unsigned((score - chain.last_score) as i16));
+            chain.plies += 1;
+            chain.last = entry.clone();
+            chain.last_score = -score;
+            return Ok(());
+        }
+
+        self.end_chain();
+        if self.chunk.len() >= CHUNK_SIZE {
+            self.flush_chunk()?;
+        }
+
+        self.chain = Some(Box::new(Chain {
+            bytes: encode_stem(entry, score).to_vec(),
+            plies: 0,
+            movetext: BitWriter::default(),
+            last: entry.clone(),
+            last_score: -score,
+        }));
+        Ok(())
+    }
+
+    fn end_chain(&mut self) {
+        if let Some(chain) = self.chain.take() {
+            self.chunk.extend_from_slice(&chain.bytes);
+            self.chunk.extend_from_slice(&chain.plies.to_be_bytes());
+            self.chunk.extend_from_slice(&chain.movetext.bytes);
+        }
+    }
+
+    fn flush_chunk(&mut self) -> std::io::Result<()> {
+        if !self.chunk.is_empty() {
+            self.writer.write_all(b"BINP")?;
+            self.writer.write_all(&(self.chunk.len() as u32).to_le_bytes())?;
+            self.writer.write_all(&self.chunk)?;
+            self.chunk.clear();
+        }
+        Ok(())
+    }
+}
+
+fn follows(previous: &Entry, entry: &Entry) -> bool {
+    if !previous.board.generate_all_moves().iter().any(|e| e.mv == previous.mv) {
+        return false;
+    }
+
+    let mut board = previous.board.clone();
+    board.make_move(previous.mv, &mut NullBoardObserver);
+    board.to_fen() == entry.board.to_fen()
+}
+
+fn encode_stem(entry: &Entry, score: i32) -> [u8; STEM_SIZE] {
+    let board = &entry.board;
+    let mut stem = [0; STEM_SIZE];
+
+    for (index, square) in board.occupancies().enumerate() {
+        let piece = board.piece_on(square);
+        let color = piece.color();
+
+        let mut nibble = piece as u8;
+        match piece.piece_type() {
+            PieceType::Pawn
+                if board.en_passant() != Square::None
+                    && board.en_passant() == square.shift(if color == Color::White { -8 } else { 8 }) =>
+            {
+                nibble = 12
+            }
+            PieceType::Rook if has_castling_rook(board, square) => nibble = if color == Color::White { 13 } else { 14 },
+            PieceType::King if color == Color::Black && board.side_to_move() == Color::Black => nibble = 15,
+            _ => (),
+        }
+
+        stem[8 + index / 2] |= nibble << (4 * (index & 1));
+    }
+
+    let (_, result) = relative(board, entry.score, entry.result);
+    let ply = 2 * board.fullmove_number().saturating_sub(1) + (board.side_to_move() == Color::Black) as usize;
+    let result = (2.0 * result).round() as i16 - 1;
+
+    stem[0..8].copy_from_slice(&board.occupancies().0.to_be_bytes());
+    stem[24..26].copy_from_slice(&encode_move(board, entry.mv).to_be_bytes());
+    stem[26..28].copy_from_slice(&signed_to_unsigned(score as i16).to_be_bytes());
+    stem[28..30].copy_from_slice(&((ply as u16 & 0x3FFF) | (signed_to_unsigned(result) << 14)).to_be_bytes());
+    stem[30..32].copy_from_slice(&(board.fiftymove_clock() as u16).to_be_bytes());
+    stem
+}
+
+fn has_castling_rook(board: &Board, square: Square) -> bool {
+    let rights = [
+        (Square::H1, CastlingKind::WhiteKingside),
+        (Square::A1, CastlingKind::WhiteQueenside),
+        (Square::H8, CastlingKind::BlackKingside),
+        (Square::A8, CastlingKind::BlackQueenside),
+    ];
+    rights.iter().any(|&(rook, kind)| rook == square && board.castling().is_allowed(kind))
+}
+
+/// Writes bits starting from the most significant bit of every byte.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u8, count: u32) {
+        for bit in (0..count).rev() {
+            if self.position.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            *self.bytes.last_mut().unwrap() |= ((value >> bit) & 1) << (7 - self.position % 8);
+            self.position += 1;
+        }
+    }
+
+    /// Writes the value in blocks of four bits from the lowest, each followed by a bit telling
+    /// whether another block follows.
+    fn write_vle(&mut self, mut value: u16) {
+        let mask = (1 << SCORE_VLE_BLOCK_SIZE) - 1;
+        loop {
+            let block = (value & mask) as u8 | ((value > mask) as u8) << SCORE_VLE_BLOCK_SIZE;
+            self.write(block, SCORE_VLE_BLOCK_SIZE + 1);
+            value >>= SCORE_VLE_BLOCK_SIZE;
+            if value == 0 {
+                break;
+            }
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u32) -> u8 {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self.bytes.get(self.position / 8).copied().unwrap_or_default();
+            value = (value << 1) | ((byte >> (7 - self.position % 8)) & 1);
+            self.position += 1;
+        }
+        value
+    }
+
+    fn read_vle(&mut self) -> u16 {
+        let mask = (1 << SCORE_VLE_BLOCK_SIZE) - 1;
+        let mut value = 0;
+        let mut offset = 0;
+        loop {
+            let block = self.read(SCORE_VLE_BLOCK_SIZE + 1) as u16;
+            value |= (block & mask) << offset;
+            if block >> SCORE_VLE_BLOCK_SIZE == 0 || offset >= 16 {
+                return value;
+            }
+            offset += SCORE_VLE_BLOCK_SIZE;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rng::Rng, tools::bench::POSITIONS};
+
+    /// Random games from the bench positions, with a break in the chain between games.
+    fn random_entries() -> Vec<Entry> {
+        let mut rng = Rng::new(7);
+        let mut entries = Vec::new();
+
+        for fen in POSITIONS.iter().take(16) {
+            let mut board = Board::from_fen(fen).unwrap();
+            let result = [0.0, 0.5, 1.0][rng.next() as usize % 3];
+
+            for _ in 0..60 {
+                let moves = board.generate_all_moves();
+                if moves.is_empty() {
+                    break;
+                }
+
+                let mv = moves[rng.next() as usize % moves.len()].mv;
+                let score = (rng.next() % 2001) as i32 - 1000;
+                entries.push(Entry { board: board.clone(), score, result, mv });
+                board.make_move(mv, &mut NullBoardObserver);
+            }
+        }
+
+        entries
+    }
+
+    fn round_trip(extension: &str, entries: &[Entry]) -> Vec<Entry> {
+        let path = std::env::temp_dir().join(format!("reckless-trainingdata-{}.{extension}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut writer = Writer::create(path).unwrap();
+        for entry in entries {
+            writer.write(entry).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let decoded = Reader::open(path).unwrap().map(Option::unwrap).collect();
+        std::fs::remove_file(path).unwrap();
+        decoded
+    }
+
+    #[test]
+    fn binpack_round_trip() {
+        let entries = random_entries();
+        let decoded = round_trip("binpack", &entries);
+
+        assert_eq!(decoded.len(), entries.len());
+        for (entry, decoded) in entries.iter().zip(&decoded) {
+            assert_eq!(decoded.board.to_fen(), entry.board.to_fen());
+            assert_eq!((decoded.score, decoded.result, decoded.mv), (entry.score, entry.result, entry.mv));
+        }
+    }
+
+    #[test]
+    fn bullet_round_trip() {
+        let entries = random_entries();
+        let decoded = round_trip("bullet", &entries);
+
+        assert_eq!(decoded.len(), entries.len());
+        for (entry, decoded) in entries.iter().zip(&decoded) {
+            let (score, result) = relative(&entry.board, entry.score, entry.result);
+            assert_eq!((decoded.score, decoded.result), (score, result));
+
+            // Only the pieces are kept, seen from the side to move
+            let flip =
+                |square: Square| if entry.board.side_to_move() == Color::White { square } else { square.flip_rank() };
+            for square in entry.board.occupancies() {
+                let piece = entry.board.piece_on(square);
+                let expected = (piece.color() == entry.board.side_to_move(), piece.piece_type());
+                let piece = decoded.board.piece_on(flip(square));
+                assert_eq!((piece.color() == Color::White, piece.piece_type()), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Compares the quantized network inference against the floating point reference over the
+//! bench positions, reporting the largest and the mean absolute error.
+
+use std::sync::Arc;
+
+use super::bench::POSITIONS;
+use crate::{board::Board, thread::SharedContext, threadpool::ThreadPool};
+
+pub fn verify_network() {
+    let shared = Arc::new(SharedContext::default());
+    let mut pool = ThreadPool::new(shared);
+    let nnue = &mut pool.main_thread().nnue;
+
+    let mut total = 0.0;
+    let mut worst = (0.0, "");
+
+    for &fen in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+
+        nnue.full_refresh(&board);
+        let quantized = nnue.evaluate(&board);
+        let reference = nnue.evaluate_reference(&board);
+
+        let error = (quantized as f32 - reference).abs();
+        total += error;
+
+        if error > worst.0 {
+            worst = (error, fen);
+        }
+    }
+
+    println!("Positions:  {}", POSITIONS.len());
+    println!("Mean error: {:.2}", total / POSITIONS.len() as f32);
+    println!("Max error:  {:.2}", worst.0);
+
+    if !worst.1.is_empty() {
+        println!("Worst:      {}", worst.1);
+    }
+}
@@ -0,0 +1,347 @@
+//! Tracing of the search tree into a file for debugging pruning, built with the `tree-trace`
+//! feature. `debug tree on <file>` makes the main thread record the nodes of every following
+//! search until `debug tree off`, each search overwriting the file; qsearch isn't traced.
+//!
+//! The trace is a sequence of 12-byte little-endian records:
+//!
+//! | Offset | Type  | Field                                                      |
+//! |--------|-------|------------------------------------------------------------|
+//! | 0      | `u8`  | Event kind                                                 |
+//! | 1      | `u8`  | Ply                                                        |
+//! | 2      | `u8`  | Pruning outcome or bound, depending on the event           |
+//! | 3      | `u8`  | Promotion piece of the move as a lowercase letter, or zero |
+//! | 4      | `u8`  | Origin square of the move                                  |
+//! | 5      | `u8`  | Target square of the move                                  |
+//! | 6      | `i16` | Depth                                                      |
+//! | 8      | `i16` | First value: alpha, the static eval or a score             |
+//! | 10     | `i16` | Second value: beta or the adjusted eval                    |
+//!
+//! `tree <file> [text|dot]` converts a trace into indented text or a Graphviz graph.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+};
+
+use crate::{
+    transposition::Bound,
+    types::{Move, PieceType, Square},
+};
+
+const RECORD_SIZE: usize = 12;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    /// A node was entered with the move leading to it, alpha and beta.
+    Node,
+    /// The static evaluation of the node and the one adjusted by the TT score.
+    Eval,
+    /// A heuristic cut the node off, with the returned score, or skipped a move, with the
+    /// value that was compared against the margin.
+    Prune,
+    /// A move was searched and got a score.
+    Searched,
+    /// The node was searched to the end, with its best move, score and bound.
+    Exit,
+}
+
+impl Kind {
+    const ALL: [Self; 5] = [Self::Node, Self::Eval, Self::Prune, Self::Searched, Self::Exit];
+}
+
+/// The heuristic that pruned a node or a move.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    TtCutoff,
+    Tablebase,
+    Razoring,
+    ReverseFutility,
+    NullMove,
+    ProbCut,
+    MultiCut,
+    LateMove,
+    Futility,
+    BadNoisyFutility,
+    History,
+    See,
+}
+
+impl Outcome {
+    const ALL: [Self; 12] = [
+        Self::TtCutoff,
+        Self::Tablebase,
+        Self::Razoring,
+        Self::ReverseFutility,
+        Self::NullMove,
+        Self::ProbCut,
+        Self::MultiCut,
+        Self::LateMove,
+        Self::Futility,
+        Self::BadNoisyFutility,
+        Self::History,
+        Self::See,
+    ];
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::TtCutoff => "tt",
+            Self::Tablebase => "tb",
+            Self::Razoring => "razoring",
+            Self::ReverseFutility => "rfp",
+            Self::NullMove => "nmp",
+            Self::ProbCut => "probcut",
+            Self::MultiCut => "multicut",
+            Self::LateMove => "lmp",
+            Self::Futility => "fp",
+            Self::BadNoisyFutility => "bnfp",
+            Self::History => "hp",
+            Self::See => "see",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Record {
+    kind: Kind,
+    ply: u8,
+    code: u8,
+    promotion: u8,
+    from: u8,
+    to: u8,
+    depth: i16,
+    values: [i16; 2],
+}
+
+impl Record {
+    fn new(kind: Kind, ply: isize, depth: i32, code: u8, mv: Move, values: [i32; 2]) -> Self {
+        let promotion = if mv.is_promotion() {
+            match mv.promo_piece_type() {
+                PieceType::Knight => b'n',
+                PieceType::Bishop => b'b',
+                PieceType::Rook => b'r',
+                _ => b'q',
+            }
+        } else {
+            0
+        };
+        let clamp = |value: i32| value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+
+        Self {
+            kind,
+            ply: ply as u8,
+            code,
+            promotion,
+            from: mv.from() as u8,
+            to: mv.to() as u8,
+            depth: clamp(depth),
+            values: values.map(clamp),
+        }
+    }
+
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[..6].copy_from_slice(&[self.kind as u8, self.ply, self.code, self.promotion, self.from, self.to]);
+        bytes[6..8].copy_from_slice(&self.depth.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.values[0].to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.values[1].to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8; RECORD_SIZE]) -> Option<Self> {
+        Some(Self {
+            kind: *Kind::ALL.get(bytes[0] as usize)?,
+            ply: bytes[1],
+            code: bytes[2],
+            promotion: bytes[3],
+            from: bytes[4],
+            to: bytes[5],
+            depth: i16::from_le_bytes([bytes[6], bytes[7]]),
+            values: [i16::from_le_bytes([bytes[8], bytes[9]]), i16::from_le_bytes([bytes[10], bytes[11]])],
+        })
+    }
+
+    fn move_text(&self) -> String {
+        if self.from == self.to {
+            return "0000".to_string();
+        }
+
+        let mut text = format!("{}{}", Square::new(self.from), Square::new(self.to));
+        if self.promotion != 0 {
+            text.push(self.promotion as char);
+        }
+        text
+    }
+
+    fn outcome(&self) -> String {
+        Outcome::ALL.get(self.code as usize).map_or_else(|| "?".to_string(), Outcome::to_string)
+    }
+
+    fn bound(&self) -> &'static str {
+        match self.code {
+            code if code == Bound::Exact as u8 => "exact",
+            code if code == Bound::Lower as u8 => "lower",
+            code if code == Bound::Upper as u8 => "upper",
+            _ => "none",
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [first, second] = self.values;
+        match self.kind {
+            Kind::Node => {
+                write!(f, "node {} depth {} window [{first}, {second}]", self.move_text(), self.depth)
+            }
+            Kind::Eval => write!(f, "eval {first} adjusted {second}"),
+            Kind::Prune if self.code < Outcome::LateMove as u8 => {
+                write!(f, "pruned by {} with {first}", self.outcome())?;
+                if self.from != self.to {
+                    write!(f, " after {}", self.move_text())?;
+                }
+                Ok(())
+            }
+            Kind::Prune => write!(f, "skipped {} by {} at {first}", self.move_text(), self.outcome()),
+            Kind::Searched => write!(f, "searched {} score {first}", self.move_text()),
+            Kind::Exit => write!(f, "exit best {} score {first} {}", self.move_text(), self.bound()),
+        }
+    }
+}
+
+/// Writes the trace of the main thread.
+pub struct Tracer {
+    writer: BufWriter<File>,
+}
+
+impl Tracer {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    fn write(&mut self, record: Record) {
+        // A trace that can't be written is of no use, but mustn't stop the search
+        let _ = self.writer.write_all(&record.encode());
+    }
+
+    pub fn node(&mut self, ply: isize, depth: i32, mv: Move, alpha: i32, beta: i32) {
+        self.write(Record::new(Kind::Node, ply, depth, 0, mv, [alpha, beta]));
+    }
+
+    pub fn eval(&mut self, ply: isize, depth: i32, eval: i32, adjusted: i32) {
+        self.write(Record::new(Kind::Eval, ply, depth, 0, Move::NULL, [eval, adjusted]));
+    }
+
+    /// Records a heuristic that cut off the node, or that skipped `mv` with the value it
+    /// compared, like the futility value or the SEE threshold.
+    pub fn prune(&mut self, ply: isize, depth: i32, outcome: Outcome, mv: Move, score: i32) {
+        self.write(Record::new(Kind::Prune, ply, depth, outcome as u8, mv, [score, 0]));
+    }
+
+    pub fn searched(&mut self, ply: isize, depth: i32, mv: Move, score: i32) {
+        self.write(Record::new(Kind::Searched, ply, depth, 0, mv, [score, 0]));
+    }
+
+    pub fn exit(&mut self, ply: isize, depth: i32, bound: Bound, mv: Move, score: i32) {
+        self.write(Record::new(Kind::Exit, ply, depth, bound as u8, mv, [score, 0]));
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+pub fn tree(args: &[&str]) {
+    let (path, dot) = match args {
+        [path] | [path, "text"] => (path, false),
+        [path, "dot"] => (path, true),
+        _ => {
+            eprintln!("Usage: tree <file> [text|dot]");
+            return;
+        }
+    };
+
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(e) => {
+            eprintln!("Failed to open '{path}': {e}");
+            return;
+        }
+    };
+
+    let mut records = Vec::new();
+    let mut bytes = [0; RECORD_SIZE];
+    while reader.read_exact(&mut bytes).is_ok() {
+        match Record::decode(&bytes) {
+            Some(record) => records.push(record),
+            None => {
+                eprintln!("Invalid record {} in '{path}'", records.len());
+                return;
+            }
+        }
+    }
+
+    if dot { print_dot(&records) } else { print_text(&records) }
+}
+
+fn print_text(records: &[Record]) {
+    for record in records {
+        let indent = 2 * record.ply as usize + if record.kind == Kind::Node { 0 } else { 2 };
+        println!("{:indent$}{record}", "");
+    }
+}
+
+/// Prints the nodes as a graph, labelled with everything recorded for them, and the moves
+/// between them as edges.
+fn print_dot(records: &[Record]) {
+    let mut labels = Vec::<String>::new();
+    let mut path = Vec::<usize>::new();
+
+    println!("digraph tree {{");
+    println!("  node [shape=box, fontname=monospace];");
+
+    for record in records {
+        if record.kind == Kind::Node {
+            path.truncate(record.ply as usize);
+
+            let id = labels.len();
+            if let Some(&parent) = path.last() {
+                println!("  n{parent} -> n{id} [label=\"{}\"];", record.move_text());
+            }
+
+            labels.push(format!(
+                "ply {} depth {} [{}, {}]",
+                record.ply, record.depth, record.values[0], record.values[1]
+            ));
+            path.push(id);
+        } else if let Some(&id) = path.get(record.ply as usize) {
+            labels[id].push_str(&format!("\\n{record}"));
+        }
+    }
+
+    for (id, label) in labels.iter().enumerate() {
+        println!("  n{id} [label=\"{label}\"];");
+    }
+    println!("}}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MoveKind;
+
+    #[test]
+    fn test_record_round_trip() {
+        let mv = Move::new(Square::E7, Square::E8, MoveKind::PromotionQ);
+        let record = Record::new(Kind::Prune, 3, 5, Outcome::See as u8, mv, [-40000, 0]);
+        let decoded = Record::decode(&record.encode()).unwrap();
+
+        assert_eq!(decoded.to_string(), "skipped e7e8q by see at -32768");
+        assert!(Record::decode(&[0xff; RECORD_SIZE]).is_none());
+    }
+}
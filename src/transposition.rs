@@ -1,10 +1,17 @@
-use std::sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+#[cfg(feature = "tt-stats")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering};
 
-use crate::types::{Move, Score, is_decisive, is_loss, is_valid, is_win};
+use crate::{
+    misc::prefetch,
+    types::{Move, Score, is_decisive, is_loss, is_valid, is_win},
+};
 
 pub const DEFAULT_TT_SIZE: usize = 16;
 
 const MEGABYTE: usize = 1024 * 1024;
+#[cfg(target_os = "linux")]
+const HUGE_PAGE_SIZE: usize = 2 * MEGABYTE;
 const CLUSTER_SIZE: usize = std::mem::size_of::<Cluster>();
 
 const ENTRIES_PER_CLUSTER: usize = 3;
@@ -65,6 +72,126 @@ pub enum Bound {
     Upper,
 }
 
+/// Policy deciding which entry of a full cluster is overwritten by a new position.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Replacement {
+    /// Replaces the shallowest entry, treating entries from older searches as shallower.
+    AgedDepth,
+    /// Replaces the shallowest entry regardless of its age.
+    Depth,
+    /// Always stores the new result, even over a deeper one for the same position.
+    Always,
+}
+
+impl Replacement {
+    pub const ALL: [Self; 3] = [Self::AgedDepth, Self::Depth, Self::Always];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AgedDepth => "aged",
+            Self::Depth => "depth",
+            Self::Always => "always",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name() == name)
+    }
+}
+
+/// Compile-time counterpart of [`Replacement`], so that each policy and cluster size gets its
+/// own specialized copy of the replacement scan.
+trait ReplacementPolicy {
+    /// Whether a shallower result for a position stored in the current search is discarded.
+    const KEEPS_DEEPER: bool = true;
+
+    /// Picks the entry to overwrite among the first `WAYS` entries of the cluster.
+    fn select<const WAYS: usize>(cluster: &Cluster, key: u16, tt_age: u8) -> usize;
+}
+
+struct AgedDepth;
+struct Depth;
+struct Always;
+
+impl ReplacementPolicy for AgedDepth {
+    fn select<const WAYS: usize>(cluster: &Cluster, _: u16, tt_age: u8) -> usize {
+        lowest_quality::<WAYS>(cluster, |entry| entry.depth() - 4 * entry.relative_age(tt_age))
+    }
+}
+
+impl ReplacementPolicy for Depth {
+    fn select<const WAYS: usize>(cluster: &Cluster, _: u16, _: u8) -> usize {
+        lowest_quality::<WAYS>(cluster, InternalEntry::depth)
+    }
+}
+
+impl ReplacementPolicy for Always {
+    const KEEPS_DEEPER: bool = false;
+
+    fn select<const WAYS: usize>(cluster: &Cluster, key: u16, _: u8) -> usize {
+        // Spread the positions over the entries by key, so that none of them is left stale
+        let empty = cluster.entries[..WAYS].iter().position(|entry| entry.flags.bound() == Bound::None);
+        empty.unwrap_or(key as usize % WAYS)
+    }
+}
+
+/// Returns the first empty entry, or otherwise the one with the lowest quality.
+fn lowest_quality<const WAYS: usize>(cluster: &Cluster, quality: impl Fn(&InternalEntry) -> i32) -> usize {
+    let mut replacement_index = 0;
+    let mut lowest_quality = i32::MAX;
+
+    for (index, candidate) in cluster.entries[..WAYS].iter().enumerate() {
+        if candidate.depth() == TtDepth::NONE {
+            return index;
+        }
+
+        let quality = quality(candidate);
+        if quality < lowest_quality {
+            replacement_index = index;
+            lowest_quality = quality;
+        }
+    }
+
+    replacement_index
+}
+
+fn select<P: ReplacementPolicy, const WAYS: usize>(cluster: &Cluster, key: u16, tt_age: u8) -> (usize, bool) {
+    (P::select::<WAYS>(cluster, key, tt_age), P::KEEPS_DEEPER)
+}
+
+/// The replacement policy and the bucket size, packed so that a probe loads both at once.
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct Config(u8);
+
+impl Config {
+    const DEFAULT: Self = Self::new(Replacement::AgedDepth, ENTRIES_PER_CLUSTER);
+
+    const fn new(replacement: Replacement, ways: usize) -> Self {
+        Self((replacement as u8) << 2 | ways as u8)
+    }
+
+    const fn replacement(self) -> Replacement {
+        Replacement::ALL[(self.0 >> 2) as usize]
+    }
+
+    const fn ways(self) -> usize {
+        (self.0 & 3) as usize
+    }
+}
+
+/// Counters for comparing replacement policies, printed by the `ttstats` command.
+#[cfg(feature = "tt-stats")]
+#[derive(Default)]
+struct Stats {
+    probes: AtomicU64,
+    hits: AtomicU64,
+    writes: AtomicU64,
+    /// Writes that evicted another position.
+    replacements: AtomicU64,
+    /// Writes dropped in favour of a deeper result for the same position.
+    rejections: AtomicU64,
+}
+
 /// Internal representation of a transposition table entry (8 bytes).
 #[derive(Clone)]
 #[repr(C)]
@@ -134,26 +261,100 @@ pub struct TranspositionTable {
     ptr: AtomicPtr<Cluster>,
     len: AtomicUsize,
     age: AtomicU8,
+    large_pages: AtomicBool,
+    huge: AtomicBool,
+    config: AtomicU8,
+    #[cfg(feature = "tt-stats")]
+    stats: Stats,
 }
 
 unsafe impl Sync for TranspositionTable {}
 
 impl TranspositionTable {
-    /// Clears the transposition table. This will remove all entries but keep the allocated memory.
-    pub fn clear(&self, threads: usize) {
-        unsafe { parallel_clear(threads, self.ptr(), self.len()) };
+    /// Clears the `index`-th of `count` equally sized slices of the transposition table,
+    /// allowing the work to be distributed among the search threads.
+    pub fn clear_slice(&self, index: usize, count: usize) {
+        let chunk_size = self.len().div_ceil(count);
+        let start = (index * chunk_size).min(self.len());
+        let end = (start + chunk_size).min(self.len());
+
+        unsafe { self.ptr().add(start).write_bytes(0, end - start) };
+    }
+
+    pub fn reset_age(&self) {
         self.age.store(0, Ordering::Relaxed);
     }
 
     /// Resizes the transposition table to the specified size in megabytes. This will clear all entries.
-    pub fn resize(&self, threads: usize, megabytes: usize) {
-        unsafe { deallocate(self.ptr(), self.len()) };
+    ///
+    /// The memory is touched lazily, so it's recommended to follow this with
+    /// [`ThreadPool::clear_hash`](crate::threadpool::ThreadPool::clear_hash) to prefault it
+    /// on the threads that will access it.
+    pub fn resize(&self, megabytes: usize) {
+        unsafe { deallocate(self.ptr(), self.len(), self.huge.load(Ordering::Relaxed)) };
 
-        let (new_ptr, new_len) = unsafe { allocate(threads, megabytes) };
+        let (new_ptr, new_len, huge) = unsafe { allocate(megabytes, self.large_pages.load(Ordering::Relaxed)) };
 
         self.ptr.store(new_ptr, Ordering::Relaxed);
         self.len.store(new_len, Ordering::Relaxed);
         self.age.store(0, Ordering::Relaxed);
+        self.huge.store(huge, Ordering::Relaxed);
+    }
+
+    /// Enables or disables backing the table with explicitly reserved huge pages (`MAP_HUGETLB`).
+    /// The table is reallocated with the same size and cleared. Returns whether the new allocation
+    /// actually uses huge pages, which requires them to be reserved by the system administrator.
+    pub fn set_large_pages(&self, enabled: bool) -> bool {
+        self.large_pages.store(enabled, Ordering::Relaxed);
+        self.resize(self.len() * CLUSTER_SIZE / MEGABYTE);
+        self.huge.load(Ordering::Relaxed)
+    }
+
+    /// Changes the replacement policy. Entries of the previous policy stay valid.
+    pub fn set_replacement(&self, replacement: Replacement) {
+        self.set_config(Config::new(replacement, self.ways()));
+    }
+
+    pub fn replacement(&self) -> Replacement {
+        self.config().replacement()
+    }
+
+    /// Limits the number of entries used in each cluster, between 1 and 3. The table should be
+    /// cleared afterwards, since entries beyond the new size are no longer found.
+    pub fn set_bucket_size(&self, ways: usize) {
+        self.set_config(Config::new(self.replacement(), ways.clamp(1, ENTRIES_PER_CLUSTER)));
+    }
+
+    fn ways(&self) -> usize {
+        self.config().ways()
+    }
+
+    fn config(&self) -> Config {
+        Config(self.config.load(Ordering::Relaxed))
+    }
+
+    fn set_config(&self, config: Config) {
+        self.config.store(config.0, Ordering::Relaxed);
+    }
+
+    /// Describes the probe and write counters collected since the last call, and resets them.
+    #[cfg(feature = "tt-stats")]
+    pub fn take_stats(&self) -> String {
+        let take = |counter: &AtomicU64| counter.swap(0, Ordering::Relaxed);
+        let percent = |part: u64, total: u64| 100.0 * part as f64 / total.max(1) as f64;
+
+        let (probes, hits) = (take(&self.stats.probes), take(&self.stats.hits));
+        let (writes, replacements, rejections) =
+            (take(&self.stats.writes), take(&self.stats.replacements), take(&self.stats.rejections));
+
+        format!(
+            "policy {} bucket size {}: {probes} probes, {:.2}% hits, {writes} writes, {:.2}% replacements, {:.2}% rejections",
+            self.replacement().name(),
+            self.ways(),
+            percent(hits, probes),
+            percent(replacements, writes),
+            percent(rejections, writes),
+        )
     }
 
     /// Returns the approximate load factor of the transposition table in permille (on a scale of `0` to `1000`).
@@ -163,12 +364,12 @@ impl TranspositionTable {
 
         let mut count = 0;
         for cluster in clusters.iter().take(1000) {
-            for entry in &cluster.entries {
+            for entry in &cluster.entries[..self.ways()] {
                 count += (entry.flags.bound() != Bound::None && entry.flags.age() == age) as usize;
             }
         }
 
-        count / ENTRIES_PER_CLUSTER
+        count / self.ways()
     }
 
     pub fn increment_age(&self) {
@@ -183,8 +384,15 @@ impl TranspositionTable {
 
         let key = verification_key(hash);
         let index = cluster.lookup_key(key);
+        let ways = self.ways();
+
+        #[cfg(feature = "tt-stats")]
+        {
+            self.stats.probes.fetch_add(1, Ordering::Relaxed);
+            self.stats.hits.fetch_add((index < ways) as u64, Ordering::Relaxed);
+        }
 
-        if index < cluster.entries.len() {
+        if index < ways {
             let entry = &cluster.entries[index];
 
             let hit = Entry {
@@ -218,29 +426,23 @@ impl TranspositionTable {
         let key = verification_key(hash);
         let tt_age = self.age();
 
-        let replacement_index = {
-            let lookup_index = cluster.lookup_key(key);
-            if lookup_index < cluster.entries.len() {
-                lookup_index
-            } else {
-                let mut replacement_index = None;
-                let mut lowest_quality = i32::MAX;
-
-                for (index, candidate) in cluster.entries.iter().enumerate() {
-                    if candidate.depth() == TtDepth::NONE {
-                        replacement_index = Some(index);
-                        break;
-                    }
-
-                    let quality = candidate.depth() - 4 * candidate.relative_age(tt_age);
-                    if quality < lowest_quality {
-                        replacement_index = Some(index);
-                        lowest_quality = quality;
-                    }
-                }
-
-                replacement_index.unwrap()
-            }
+        let lookup_index = cluster.lookup_key(key);
+        let config = self.config();
+
+        // The default configuration is checked first, so that it's resolved at compile time
+        let (replacement_index, keeps_deeper) = match (config.replacement(), config.ways()) {
+            _ if config == Config::DEFAULT && lookup_index < ENTRIES_PER_CLUSTER => (lookup_index, true),
+            _ if config == Config::DEFAULT => select::<AgedDepth, ENTRIES_PER_CLUSTER>(cluster, key, tt_age),
+            (replacement, ways) if lookup_index < ways => (lookup_index, replacement != Replacement::Always),
+            (Replacement::AgedDepth, 3) => select::<AgedDepth, 3>(cluster, key, tt_age),
+            (Replacement::AgedDepth, 2) => select::<AgedDepth, 2>(cluster, key, tt_age),
+            (Replacement::AgedDepth, _) => select::<AgedDepth, 1>(cluster, key, tt_age),
+            (Replacement::Depth, 3) => select::<Depth, 3>(cluster, key, tt_age),
+            (Replacement::Depth, 2) => select::<Depth, 2>(cluster, key, tt_age),
+            (Replacement::Depth, _) => select::<Depth, 1>(cluster, key, tt_age),
+            (Replacement::Always, 3) => select::<Always, 3>(cluster, key, tt_age),
+            (Replacement::Always, 2) => select::<Always, 2>(cluster, key, tt_age),
+            (Replacement::Always, _) => select::<Always, 1>(cluster, key, tt_age),
         };
 
         let entry_key = cluster.key(replacement_index);
@@ -250,10 +452,24 @@ impl TranspositionTable {
             entry.mv = mv;
         }
 
-        if !force && key == entry_key && depth + 4 + 2 * tt_pv as i32 <= entry.depth() && entry.flags.age() == tt_age {
+        if keeps_deeper
+            && !force
+            && key == entry_key
+            && depth + 4 + 2 * tt_pv as i32 <= entry.depth()
+            && entry.flags.age() == tt_age
+        {
+            #[cfg(feature = "tt-stats")]
+            self.stats.rejections.fetch_add(1, Ordering::Relaxed);
             return;
         }
 
+        #[cfg(feature = "tt-stats")]
+        {
+            self.stats.writes.fetch_add(1, Ordering::Relaxed);
+            let evicts = entry_key != key && entry.flags.bound() != Bound::None;
+            self.stats.replacements.fetch_add(evicts as u64, Ordering::Relaxed);
+        }
+
         // Adjust mate distance from "plies from the root" to "plies from the current position"
         if is_decisive(score) && is_valid(score) {
             score += score.signum() * ply as i32;
@@ -267,18 +483,8 @@ impl TranspositionTable {
     }
 
     pub fn prefetch(&self, hash: u64) {
-        #[cfg(target_arch = "x86_64")]
-        unsafe {
-            use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
-
-            let index = index(hash, self.len());
-            let ptr = self.ptr().add(index).cast();
-            _mm_prefetch::<_MM_HINT_T0>(ptr);
-        }
-
-        // No prefetching for non-x86_64 architectures
-        #[cfg(not(target_arch = "x86_64"))]
-        let _ = hash;
+        let index = index(hash, self.len());
+        prefetch(unsafe { self.ptr().add(index) });
     }
 
     fn age(&self) -> u8 {
@@ -346,72 +552,121 @@ const fn score_from_tt(score: i32, ply: isize, halfmove_clock: u8) -> i32 {
 
 impl Default for TranspositionTable {
     fn default() -> Self {
-        let (ptr, len) = unsafe { allocate(1, DEFAULT_TT_SIZE) };
+        let (ptr, len, huge) = unsafe { allocate(DEFAULT_TT_SIZE, false) };
         Self {
             ptr: AtomicPtr::new(ptr),
             len: AtomicUsize::new(len),
             age: AtomicU8::new(0),
+            large_pages: AtomicBool::new(false),
+            huge: AtomicBool::new(huge),
+            config: AtomicU8::new(Config::DEFAULT.0),
+            #[cfg(feature = "tt-stats")]
+            stats: Stats::default(),
         }
     }
 }
 
 impl Drop for TranspositionTable {
     fn drop(&mut self) {
-        unsafe { deallocate(self.ptr(), self.len()) };
+        unsafe { deallocate(self.ptr(), self.len(), self.huge.load(Ordering::Relaxed)) };
     }
 }
 
-unsafe fn allocate(threads: usize, size_mb: usize) -> (*mut Cluster, usize) {
+unsafe fn allocate(size_mb: usize, large_pages: bool) -> (*mut Cluster, usize, bool) {
     #[cfg(target_os = "linux")]
-    use libc::{MADV_HUGEPAGE, MAP_ANONYMOUS, MAP_PRIVATE, PROT_READ, PROT_WRITE, madvise, mmap};
+    use libc::{
+        MADV_HUGEPAGE, MAP_ANONYMOUS, MAP_FAILED, MAP_HUGETLB, MAP_PRIVATE, PROT_READ, PROT_WRITE, madvise, mmap,
+    };
 
     let size = size_mb * MEGABYTE;
     let len = size / CLUSTER_SIZE;
 
     #[cfg(target_os = "linux")]
-    let ptr = {
-        let ptr = mmap(std::ptr::null_mut(), size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
-        madvise(ptr, size, MADV_HUGEPAGE);
-        ptr.cast()
+    let (ptr, huge) = {
+        let flags = MAP_PRIVATE | MAP_ANONYMOUS;
+
+        // Explicit huge pages fail to map if not enough of them are reserved,
+        // in which case fall back to transparent huge pages
+        let mut ptr = MAP_FAILED;
+        if large_pages {
+            ptr = mmap(std::ptr::null_mut(), huge_size(size), PROT_READ | PROT_WRITE, flags | MAP_HUGETLB, -1, 0);
+        }
+
+        let huge = ptr != MAP_FAILED;
+        if !huge {
+            ptr = mmap(std::ptr::null_mut(), size, PROT_READ | PROT_WRITE, flags, -1, 0);
+            if ptr == MAP_FAILED {
+                std::alloc::handle_alloc_error(std::alloc::Layout::from_size_align(size, CLUSTER_SIZE).unwrap());
+            }
+            madvise(ptr, size, MADV_HUGEPAGE);
+        }
+
+        (ptr.cast(), huge)
     };
 
     #[cfg(not(target_os = "linux"))]
-    let ptr = {
+    let (ptr, huge) = {
+        let _ = large_pages;
         let layout = std::alloc::Layout::from_size_align(size, std::mem::align_of::<Cluster>()).unwrap();
-        std::alloc::alloc_zeroed(layout).cast()
+        (std::alloc::alloc_zeroed(layout).cast(), false)
     };
 
-    unsafe { parallel_clear(threads, ptr, len) };
-    (ptr, len)
+    (ptr, len, huge)
 }
 
-unsafe fn deallocate(ptr: *mut Cluster, len: usize) {
+unsafe fn deallocate(ptr: *mut Cluster, len: usize, huge: bool) {
     let size = len * CLUSTER_SIZE;
 
     #[cfg(target_os = "linux")]
-    let _ = libc::munmap(ptr.cast(), size);
+    let _ = libc::munmap(ptr.cast(), if huge { huge_size(size) } else { size });
 
     #[cfg(not(target_os = "linux"))]
     {
+        let _ = huge;
         let layout = std::alloc::Layout::from_size_align(size, std::mem::align_of::<Cluster>()).unwrap();
         std::alloc::dealloc(ptr.cast(), layout);
     }
 }
 
-unsafe fn parallel_clear<T: std::marker::Send>(threads: usize, ptr: *mut T, len: usize) {
-    #[cfg(not(target_arch = "wasm32"))]
-    std::thread::scope(|scope| {
-        let slice = std::slice::from_raw_parts_mut(ptr, len);
+/// Rounds the size up to a multiple of the huge page size, as required by `MAP_HUGETLB` mappings.
+#[cfg(target_os = "linux")]
+const fn huge_size(size: usize) -> usize {
+    size.next_multiple_of(HUGE_PAGE_SIZE)
+}
 
-        let chunk_size = len.div_ceil(threads);
-        for chunk in slice.chunks_mut(chunk_size) {
-            scope.spawn(|| chunk.as_mut_ptr().write_bytes(0, chunk.len()));
-        }
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decisive_scores_respect_the_fifty_move_rule() {
+        // Mate in 10 plies from the stored position, read 3 plies from the root
+        let mate = Score::MATE - 10;
+        assert_eq!(score_from_tt(mate, 3, 0), mate - 3);
+        assert_eq!(score_from_tt(mate, 3, 90), mate - 3);
+        assert_eq!(score_from_tt(mate, 3, 91), Score::TB_WIN_IN_MAX - 1);
+        assert_eq!(score_from_tt(-mate, 3, 91), -Score::TB_WIN_IN_MAX + 1);
+
+        // Tablebase win converted in 30 plies
+        let tb_win = Score::TB_WIN - 30;
+        assert_eq!(score_from_tt(tb_win, 0, 70), tb_win);
+        assert_eq!(score_from_tt(tb_win, 0, 71), Score::TB_WIN_IN_MAX - 1);
+        assert_eq!(score_from_tt(-tb_win, 0, 71), -Score::TB_WIN_IN_MAX + 1);
+
+        assert_eq!(score_from_tt(150, 3, 99), 150);
+    }
 
-    #[cfg(target_arch = "wasm32")]
-    {
-        let _ = threads;
-        ptr.write_bytes(0, len);
+    #[test]
+    fn always_replace_overwrites_deeper_results() {
+        let hash = 0x1234_5678_9abc_def0;
+
+        for (replacement, depth) in [(Replacement::AgedDepth, 10), (Replacement::Depth, 10), (Replacement::Always, 2)] {
+            let tt = TranspositionTable::default();
+            tt.set_replacement(replacement);
+
+            tt.write(hash, 10, 0, 50, Bound::Exact, Move::NULL, 0, false, false);
+            tt.write(hash, 2, 0, 20, Bound::Lower, Move::NULL, 0, false, false);
+            assert_eq!(tt.read(hash, 0, 0).unwrap().depth, depth, "{}", replacement.name());
+        }
     }
 }
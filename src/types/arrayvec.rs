@@ -60,6 +60,16 @@ impl<T: Copy, const N: usize> ArrayVec<T, N> {
         }
     }
 
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        // SAFETY: data[len] was initialized before the length was decreased.
+        Some(unsafe { self.data.get_unchecked(self.len).assume_init() })
+    }
+
     pub fn iter(&self) -> std::slice::Iter<'_, T> {
         // SAFETY: data[..len] is fully initialized
         unsafe { std::slice::from_raw_parts(self.data.as_ptr().cast(), self.len) }.iter()
@@ -122,9 +122,36 @@ impl MoveList {
         self.inner.iter_mut()
     }
 
+    /// Returns the index of the highest scored entry, the last one among equal scores.
+    pub fn best_index(&self) -> usize {
+        find_best_score_index(self.inner.iter().as_slice())
+    }
+
     pub fn remove(&mut self, index: usize) -> MoveEntry {
         self.inner.swap_remove(index)
     }
+
+    /// Removes the last entry, which is the best one after [`MoveList::sort_by_score`].
+    pub fn pop(&mut self) -> Option<MoveEntry> {
+        self.inner.pop()
+    }
+
+    /// Sorts the entries by ascending score. An insertion sort beats selecting the best entry over
+    /// and over on the short lists of noisy moves, and being stable it keeps the same tie breaking.
+    pub fn sort_by_score(&mut self) {
+        let entries = self.inner.iter_mut().into_slice();
+
+        for i in 1..entries.len() {
+            let entry = entries[i];
+            let mut j = i;
+
+            while j > 0 && entries[j - 1].score > entry.score {
+                entries[j] = entries[j - 1];
+                j -= 1;
+            }
+            entries[j] = entry;
+        }
+    }
 }
 
 impl Index<usize> for MoveList {
@@ -140,3 +167,105 @@ impl Default for MoveList {
         Self::new()
     }
 }
+
+// The vectorized scans compare each entry as a 64-bit key, with the score in the upper half (where
+// `#[repr(C)]` puts it) and the index in the lower half, so that the maximum key is the best entry
+// and ties go to the highest index, exactly like the scalar scan.
+
+#[cfg(target_feature = "avx512f")]
+pub fn find_best_score_index(entries: &[MoveEntry]) -> usize {
+    unsafe { find_best_score_index_avx512(entries) }
+}
+
+#[cfg(all(target_feature = "avx2", not(target_feature = "avx512f")))]
+pub fn find_best_score_index(entries: &[MoveEntry]) -> usize {
+    unsafe { find_best_score_index_avx2(entries) }
+}
+
+#[cfg(not(target_feature = "avx2"))]
+pub fn find_best_score_index(entries: &[MoveEntry]) -> usize {
+    find_best_score_index_scalar(entries)
+}
+
+pub fn find_best_score_index_scalar(entries: &[MoveEntry]) -> usize {
+    let mut best_index = 0;
+    let mut best_score = i32::MIN;
+
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.score >= best_score {
+            best_index = index;
+            best_score = entry.score;
+        }
+    }
+    best_index
+}
+
+#[cfg(target_feature = "avx2")]
+pub unsafe fn find_best_score_index_avx2(entries: &[MoveEntry]) -> usize {
+    use std::arch::x86_64::*;
+
+    let chunks = entries.len() / 4;
+    let score_mask = _mm256_set1_epi64x(-1 << 32);
+    let step = _mm256_set1_epi64x(4);
+
+    let mut index = _mm256_setr_epi64x(0, 1, 2, 3);
+    let mut best = _mm256_set1_epi64x(i64::MIN);
+
+    for chunk in 0..chunks {
+        let vector = _mm256_loadu_si256(entries.as_ptr().add(4 * chunk).cast());
+        let key = _mm256_or_si256(_mm256_and_si256(vector, score_mask), index);
+
+        best = _mm256_blendv_epi8(best, key, _mm256_cmpgt_epi64(key, best));
+        index = _mm256_add_epi64(index, step);
+    }
+
+    let mut lanes = [0i64; 4];
+    _mm256_storeu_si256(lanes.as_mut_ptr().cast(), best);
+
+    let tail = entries.iter().enumerate().skip(4 * chunks).map(|(index, &entry)| key(entry, index));
+    lanes.into_iter().chain(tail).max().unwrap() as u32 as usize
+}
+
+#[cfg(target_feature = "avx512f")]
+pub unsafe fn find_best_score_index_avx512(entries: &[MoveEntry]) -> usize {
+    use std::arch::x86_64::*;
+
+    let ptr = entries.as_ptr();
+    let len = entries.len();
+
+    let score_mask = _mm512_set1_epi64(-1 << 32);
+    let step = _mm512_set1_epi64(16);
+
+    let mut index = [_mm512_setr_epi64(0, 1, 2, 3, 4, 5, 6, 7), _mm512_setr_epi64(8, 9, 10, 11, 12, 13, 14, 15)];
+    let mut best = [_mm512_set1_epi64(i64::MIN); 2];
+
+    let mut offset = 0;
+    while offset + 16 <= len {
+        for k in 0..2 {
+            let vector = _mm512_loadu_si512(ptr.add(offset + 8 * k).cast());
+            let key = _mm512_or_si512(_mm512_and_si512(vector, score_mask), index[k]);
+
+            best[k] = _mm512_max_epi64(best[k], key);
+            index[k] = _mm512_add_epi64(index[k], step);
+        }
+        offset += 16;
+    }
+
+    // The last 15 entries at most, with the lanes past the end masked off
+    for k in 0..2 {
+        let remaining = (len - offset).saturating_sub(8 * k).min(8);
+        let mask = ((1u16 << remaining) - 1) as u8;
+
+        let vector = _mm512_maskz_loadu_epi64(mask, ptr.wrapping_add(offset + 8 * k).cast());
+        let key = _mm512_or_si512(_mm512_and_si512(vector, score_mask), index[k]);
+
+        best[k] = _mm512_mask_max_epi64(best[k], mask, best[k], key);
+    }
+
+    _mm512_reduce_max_epi64(_mm512_max_epi64(best[0], best[1])) as u32 as usize
+}
+
+#[cfg(target_feature = "avx2")]
+fn key(entry: MoveEntry, index: usize) -> i64 {
+    ((entry.score as i64) << 32) | index as i64
+}
@@ -0,0 +1,164 @@
+//! [Standard Algebraic Notation][san] used by EPD and PGN files.
+//!
+//! [san]: https://en.wikipedia.org/wiki/Algebraic_notation_(chess)
+
+use super::{Move, PieceType, Square};
+use crate::board::{Board, NullBoardObserver};
+
+/// Formats a legal move in Standard Algebraic Notation, including the minimal
+/// disambiguation required and a `+` or `#` suffix for checks and checkmates.
+pub fn to_san(board: &Board, mv: Move) -> String {
+    let mut san = if mv.is_castling() {
+        if mv.to().is_kingside() { "O-O" } else { "O-O-O" }.to_string()
+    } else {
+        let piece_type = board.moved_piece(mv).piece_type();
+        let mut san = String::new();
+
+        if piece_type == PieceType::Pawn {
+            if mv.is_capture() {
+                san.push(file_char(mv.from()));
+            }
+        } else {
+            san.push(piece_char(piece_type));
+
+            let others = board
+                .generate_all_moves()
+                .iter()
+                .map(|entry| entry.mv)
+                .filter(|&other| {
+                    other != mv
+                        && !other.is_castling()
+                        && other.to() == mv.to()
+                        && board.moved_piece(other).piece_type() == piece_type
+                })
+                .collect::<Vec<_>>();
+
+            if !others.is_empty() {
+                if others.iter().all(|other| other.from() as u8 & 7 != mv.from() as u8 & 7) {
+                    san.push(file_char(mv.from()));
+                } else if others.iter().all(|other| other.from() as u8 >> 3 != mv.from() as u8 >> 3) {
+                    san.push(rank_char(mv.from()));
+                } else {
+                    san.push(file_char(mv.from()));
+                    san.push(rank_char(mv.from()));
+                }
+            }
+        }
+
+        if mv.is_capture() {
+            san.push('x');
+        }
+
+        san.push_str(&mv.to().to_string());
+
+        if mv.is_promotion() {
+            san.push('=');
+            san.push(piece_char(mv.promo_piece_type()));
+        }
+
+        san
+    };
+
+    let mut board = board.clone();
+    board.make_move(mv, &mut NullBoardObserver);
+
+    if board.in_check() {
+        san.push(if board.has_legal_moves() { '+' } else { '#' });
+    }
+
+    san
+}
+
+/// Parses a move in Standard Algebraic Notation by matching it against the legal moves
+/// of the position. Check and annotation suffixes are ignored, and both `O-O` and `0-0`
+/// are accepted for castling.
+pub fn parse_san(board: &Board, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let moves = board.generate_all_moves();
+
+    let castling = match san {
+        "O-O" | "0-0" => Some(true),
+        "O-O-O" | "0-0-0" => Some(false),
+        _ => None,
+    };
+
+    if let Some(kingside) = castling {
+        return moves.iter().map(|entry| entry.mv).find(|mv| mv.is_castling() && mv.to().is_kingside() == kingside);
+    }
+
+    let (piece_type, rest) = match san.chars().next()? {
+        'N' => (PieceType::Knight, &san[1..]),
+        'B' => (PieceType::Bishop, &san[1..]),
+        'R' => (PieceType::Rook, &san[1..]),
+        'Q' => (PieceType::Queen, &san[1..]),
+        'K' => (PieceType::King, &san[1..]),
+        _ => (PieceType::Pawn, san),
+    };
+
+    let (rest, promotion) = match rest.rsplit_once('=') {
+        Some((rest, piece)) => (rest, Some(promotion_piece(piece)?)),
+        None => match rest.chars().last().and_then(|c| promotion_piece(&c.to_string())) {
+            Some(piece) if piece_type == PieceType::Pawn => (&rest[..rest.len() - 1], Some(piece)),
+            _ => (rest, None),
+        },
+    };
+
+    if rest.len() < 2 || !rest.is_char_boundary(rest.len() - 2) {
+        return None;
+    }
+
+    let (disambiguation, to) = rest.split_at(rest.len() - 2);
+    let to = Square::try_from(to).ok()?;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in disambiguation.chars().filter(|&c| c != 'x') {
+        match c {
+            'a'..='h' => from_file = Some(c as u8 - b'a'),
+            '1'..='8' => from_rank = Some(c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    let mut candidates = moves.iter().map(|entry| entry.mv).filter(|&mv| {
+        !mv.is_castling()
+            && mv.to() == to
+            && board.moved_piece(mv).piece_type() == piece_type
+            && from_file.is_none_or(|file| mv.from() as u8 & 7 == file)
+            && from_rank.is_none_or(|rank| mv.from() as u8 >> 3 == rank)
+            && promotion == mv.is_promotion().then(|| mv.promo_piece_type())
+    });
+
+    // Reject ambiguous moves rather than guessing
+    let mv = candidates.next()?;
+    candidates.next().is_none().then_some(mv)
+}
+
+const fn piece_char(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+        _ => 'P',
+    }
+}
+
+const fn file_char(square: Square) -> char {
+    (b'a' + (square as u8 & 7)) as char
+}
+
+const fn rank_char(square: Square) -> char {
+    (b'1' + (square as u8 >> 3)) as char
+}
+
+fn promotion_piece(piece: &str) -> Option<PieceType> {
+    match piece {
+        "N" => Some(PieceType::Knight),
+        "B" => Some(PieceType::Bishop),
+        "R" => Some(PieceType::Rook),
+        "Q" => Some(PieceType::Queen),
+        _ => None,
+    }
+}
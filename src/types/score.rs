@@ -18,7 +18,7 @@ impl Score {
 }
 
 pub fn draw(td: &ThreadData) -> i32 {
-    (td.nodes() % 5) as i32 - 2
+    (td.nodes() % 5) as i32 - 2 + td.contempt[td.board.side_to_move()]
 }
 
 pub const fn mated_in(ply: isize) -> i32 {
@@ -55,7 +55,12 @@ pub const fn is_valid(score: i32) -> bool {
     score != Score::NONE
 }
 
-pub fn normalize_to_cp(score: i32, board: &Board) -> i32 {
+/// Parameters `(a, b)` of the logistic win rate model for the given position. A score
+/// of `a` corresponds to a 50% win rate and depends on the material only, so that the
+/// normalized scores stay comparable across the game. `b` controls the spread of the
+/// curve: it's proportional to `a`, and scaled by the game ply with the shape of the
+/// ply term of Stockfish's earlier model, rather than fitted separately.
+fn win_rate_params(board: &Board) -> (f64, f64) {
     let material = board.pieces(PieceType::Pawn).popcount()
         + 3 * board.pieces(PieceType::Knight).popcount()
         + 3 * board.pieces(PieceType::Bishop).popcount()
@@ -64,7 +69,35 @@ pub fn normalize_to_cp(score: i32, board: &Board) -> i32 {
 
     let v = material.clamp(16, 78) as f64 / 58.0;
 
-    let normalization = -285.1 * v.powi(3) + 642.5 * v.powi(2) - 455.5 * v + 464.8;
+    let a = -285.1 * v.powi(3) + 642.5 * v.powi(2) - 455.5 * v + 464.8;
+
+    let m = board.game_ply().min(240) as f64 / 64.0;
+    let spread = ((-2.294_347_33 * m + 13.276_897_88) * m - 14.268_289_04) * m + 63.453_183_30;
+    let b = a / 5.0 * spread / 63.453_183_30;
+
+    (a, b)
+}
+
+pub fn normalize_to_cp(score: i32, board: &Board) -> i32 {
+    let (a, _) = win_rate_params(board);
+
+    (100.0 * score as f64 / a).round() as i32
+}
+
+/// Expected win, draw and loss probabilities in permille for the side to move.
+pub fn win_draw_loss(score: i32, board: &Board) -> (i32, i32, i32) {
+    if is_win(score) {
+        return (1000, 0, 0);
+    }
+    if is_loss(score) {
+        return (0, 0, 1000);
+    }
+
+    let (a, b) = win_rate_params(board);
+    let win_rate = |score: f64| (1000.0 / (1.0 + ((a - score) / b).exp())).round() as i32;
+
+    let win = win_rate(score as f64);
+    let loss = win_rate(-score as f64);
 
-    (100.0 * score as f64 / normalization).round() as i32
+    (win, 1000 - win - loss, loss)
 }
@@ -1,16 +1,23 @@
-use std::collections::HashMap;
 use std::collections::VecDeque;
-use std::sync::Arc;
+use std::sync::{Arc, atomic::Ordering};
+use std::time::{Duration, Instant};
 
 use crate::{
-    board::{Board, NullBoardObserver},
+    board::{Board, DrawReason, NullBoardObserver},
+    cluster::Cluster,
+    evaluation::EvalCache,
+    nnue::WeightLayout,
+    numa::NumaConfig,
+    opponent::Opponent,
     search::Report,
-    thread::{SharedContext, Status, ThreadData},
+    skill::{Skill, Temperature},
+    thread::{DEFAULT_CURRMOVE_DELAY, SharedContext, Status, ThreadData},
     threadpool::ThreadPool,
-    time::{Limits, TimeManager},
-    tools,
-    transposition::DEFAULT_TT_SIZE,
-    types::{Color, MAX_MOVES, Move, Piece, Score, Square, is_decisive, is_loss, is_win},
+    time::{Limits, TimeManager, VirtualClock, parse_limits},
+    tools::{self, BenchOutput, PrettyWriter},
+    transposition::{DEFAULT_TT_SIZE, Replacement},
+    types::{Color, MAX_MOVES, Move, Piece, Square, parse_san},
+    xboard,
 };
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -23,7 +30,30 @@ struct Settings {
     frc: bool,
     multi_pv: usize,
     move_overhead: u64,
+    latency: Latency,
+    nodes_time: u64,
+    /// The percentage of the time spent pondering that counts against the bounds on `ponderhit`.
+    ponder_factor: u64,
+    virtual_clock: VirtualClock,
+    /// The longest `movestogo` of the game, taken as the period of a repeating time control.
+    period: u64,
     report: Report,
+    /// Whether searches are printed by the [`PrettyWriter`] when the output is a terminal.
+    pretty: bool,
+    limit_strength: bool,
+    elo: i32,
+    contempt: i32,
+    adaptive_contempt: bool,
+    opponent: Option<Opponent>,
+    /// Whether the line being searched is reported, either through `debug on` or the option.
+    debug: bool,
+    show_currline: bool,
+    show_refutations: bool,
+    temperature: Temperature,
+    cluster: Cluster,
+    weight_layout: WeightLayout,
+    /// The network loaded through `EvalFile`, read again if `EvalFileLayout` changes afterwards.
+    eval_file: Option<String>,
 }
 
 impl Default for Settings {
@@ -32,22 +62,86 @@ impl Default for Settings {
             frc: false,
             multi_pv: 1,
             move_overhead: 100,
+            latency: Latency::default(),
+            nodes_time: 0,
+            ponder_factor: 50,
+            virtual_clock: VirtualClock::default(),
+            period: 0,
             report: Report::Full,
+            pretty: false,
+            limit_strength: false,
+            elo: Skill::MAX_ELO,
+            contempt: 0,
+            adaptive_contempt: false,
+            opponent: None,
+            debug: false,
+            show_currline: false,
+            show_refutations: false,
+            temperature: Temperature::default(),
+            cluster: Cluster::default(),
+            weight_layout: WeightLayout::Interleaved,
+            eval_file: None,
         }
     }
 }
 
+/// Measures the delay that the clock of the GUI charges beyond the searches, such as the network
+/// round trip from `bestmove` to the next `position` and `go` of an online bot, to add it to the
+/// move overhead. The time between the two also includes the thinking of the opponent, so the
+/// delay is taken from the remaining time of consecutive searches instead.
+#[derive(Default)]
+struct Latency {
+    enabled: bool,
+    /// Moving average of the delay in milliseconds.
+    average: f64,
+    /// The remaining time expected at the next search if there were no delay.
+    expected: Option<u64>,
+}
+
+impl Latency {
+    const WEIGHT: f64 = 0.25;
+    const MAX_DELAY: u64 = 2000;
+
+    /// Updates the average from the remaining time of a new search and returns it.
+    fn observe(&mut self, limits: &Limits) -> u64 {
+        if let (Some(expected), Limits::Fischer(main, _) | Limits::Cyclic(main, ..)) = (self.expected.take(), limits) {
+            let delay = expected.saturating_sub(*main).min(Self::MAX_DELAY);
+            self.average += Self::WEIGHT * (delay as f64 - self.average);
+        }
+        if self.enabled { self.average.round() as u64 } else { 0 }
+    }
+
+    /// Records a finished search, which took `elapsed` since the `go` command.
+    fn record(&mut self, limits: &Limits, ponder: bool, elapsed: Duration) {
+        self.expected = match *limits {
+            // The clock isn't predictable over pondering or a control that adds a new period
+            _ if ponder => None,
+            Limits::Cyclic(.., 1, _) => None,
+            Limits::Fischer(main, inc) | Limits::Cyclic(main, inc, ..) => {
+                Some((main + inc).saturating_sub(elapsed.as_millis() as u64))
+            }
+            _ => None,
+        };
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn message_loop(mut buffer: VecDeque<String>) {
+    // Standard input is taken over by the connection to the master
+    if let Some(address) = buffer.front().and_then(|command| command.strip_prefix("worker ")) {
+        crate::cluster::serve(address.trim());
+        return;
+    }
+
     let shared = Arc::new(SharedContext::default());
     let mut settings = Settings::default();
     let mut threads = ThreadPool::new(shared.clone());
     let mut board = Board::starting_position();
 
-    let rx = spawn_listener(shared.clone());
-
     let mut mode = if buffer.is_empty() { Mode::Uci } else { Mode::Cli };
 
+    let rx = spawn_listener(shared.clone(), mode);
+
     loop {
         let message = if let Some(cmd) = buffer.pop_front() {
             cmd
@@ -69,10 +163,32 @@ pub fn message_loop(mut buffer: VecDeque<String>) {
 
             ["isready"] => println!("readyok"),
 
-            ["go", tokens @ ..] => go(&mut threads, &settings, &board, &shared, tokens),
-            ["position", tokens @ ..] => position(&mut board, &settings, tokens),
-            ["setoption", tokens @ ..] => set_option(&mut threads, &mut settings, &shared, tokens),
-            ["ucinewgame"] => reset(&mut threads, &shared),
+            ["xboard"] => {
+                xboard::message_loop(&mut threads, &shared, &rx);
+                break;
+            }
+
+            ["cli"] => {
+                tools::repl(&mut threads, &shared, &rx, &mut board);
+                break;
+            }
+
+            ["go", tokens @ ..] => go(&mut threads, &mut settings, &board, &shared, tokens),
+            ["position", tokens @ ..] => {
+                position(&mut board, &settings, tokens);
+                settings.cluster.set_position(&message);
+            }
+            ["setoption", tokens @ ..] => {
+                set_option(&mut threads, &mut settings, &shared, tokens);
+                settings.cluster.forward_option(&message);
+            }
+            ["ucinewgame"] => {
+                reset(&mut threads, &shared);
+                settings.virtual_clock.reset();
+                settings.period = 0;
+                settings.latency.expected = None;
+                settings.cluster.send("ucinewgame");
+            }
 
             ["stop"] => shared.status.set(Status::STOPPED),
             ["quit"] => {
@@ -82,17 +198,76 @@ pub fn message_loop(mut buffer: VecDeque<String>) {
 
             // Non-UCI commands
             ["compiler"] => compiler(),
+            ["eval"] if shared.json.load(Ordering::Relaxed) => eval_json(threads.main_thread(), &board),
             ["eval"] => eval(threads.main_thread(), &board),
+            ["evalbatch"] => tools::evalbatch(&rx),
+            ["nnue", "verify"] => tools::verify_network(),
+            ["nnue", "checksum"] => crate::nnue::print_checksum(),
+            ["export_net", path @ ..] if !path.is_empty() => {
+                let path = path.join(" ");
+                match crate::nnue::export_network(&path) {
+                    Ok(()) => println!("info string Exported network to {path}"),
+                    Err(e) => eprintln!("Failed to export network to '{path}': {e}"),
+                }
+            }
             ["d"] => println!("{board}"),
+            #[cfg(feature = "tt-stats")]
+            ["ttstats"] => println!("info string {}", shared.tt.take_stats()),
+            #[cfg(not(feature = "tt-stats"))]
+            ["ttstats"] => println!("info string Transposition table counters require the tt-stats feature"),
+            ["debug", state @ ("on" | "off")] => {
+                settings.debug = *state == "on";
+                shared.show_currline.store(settings.debug || settings.show_currline, Ordering::Relaxed);
+            }
+            #[cfg(feature = "tree-trace")]
+            ["debug", "tree", "on", path @ ..] if !path.is_empty() => {
+                let path = path.join(" ");
+                println!("info string Tracing the search tree into {path}");
+                *shared.tree_trace.lock().unwrap() = Some(path);
+            }
+            #[cfg(feature = "tree-trace")]
+            ["debug", "tree", "off"] => *shared.tree_trace.lock().unwrap() = None,
+            #[cfg(feature = "tree-trace")]
+            ["debug", "tree", ..] => eprintln!("Usage: debug tree on <file> | debug tree off"),
+            #[cfg(feature = "tree-trace")]
+            ["tree", args @ ..] => crate::trace::tree(args),
+            #[cfg(not(feature = "tree-trace"))]
+            ["debug", "tree", ..] | ["tree", ..] => {
+                println!("info string Search tree tracing requires the tree-trace feature")
+            }
+            #[cfg(feature = "spsa")]
+            ["spsa"] => crate::parameters::print_spsa_inputs(),
+            #[cfg(not(feature = "spsa"))]
+            ["spsa"] => println!("info string Tunable parameters require the spsa feature"),
+            ["bench", "picker"] => tools::bench_picker(),
+            ["bench", "movegen"] => tools::bench_movegen(),
+            ["bench", "nnue"] => tools::bench_nnue(),
+            ["bench", "see"] => tools::bench_see(),
             ["bench", args @ ..] => match mode {
-                Mode::Uci => tools::bench::<true>(args),
-                Mode::Cli => tools::bench::<false>(args),
+                _ if shared.json.load(Ordering::Relaxed) => tools::bench(args, BenchOutput::Json),
+                Mode::Uci => tools::bench(args, BenchOutput::Table),
+                Mode::Cli => tools::bench(args, BenchOutput::Plain),
             },
             ["speedtest", args @ ..] => tools::speedtest(args),
-            ["perft", depth] => tools::perft(depth.parse().unwrap(), &mut board),
-            ["perft"] => eprintln!("Usage: perft <depth>"),
+            ["datagen", args @ ..] => tools::datagen(args),
+            ["rescore", args @ ..] => tools::rescore(args),
+            ["match", args @ ..] => tools::matchplay(args),
+            ["selftest", args @ ..] => tools::selftest(args),
+            ["testsuite", args @ ..] => tools::testsuite(args),
+            ["annotate", args @ ..] => tools::annotate(args),
+            ["perft", depth, hash @ ..] if hash.len() <= 1 => match depth.parse() {
+                Ok(depth @ 1..) => tools::perft(depth, &threads, hash.first().and_then(|v| v.parse().ok()), &board),
+                _ => eprintln!("Usage: perft <depth> [<Hash>]"),
+            },
+            ["perft", ..] => eprintln!("Usage: perft <depth> [<Hash>]"),
             ["simpleperft", depth] => tools::simple_perft(depth.parse().unwrap(), &mut board),
             ["simpleperft"] => eprintln!("Usage: simpleperft <depth>"),
+            ["see", uci_move] => {
+                match board.generate_all_moves().iter().find(|entry| entry.mv.to_uci(&board) == *uci_move) {
+                    Some(entry) => println!("SEE of {uci_move}: {}", board.see_value(entry.mv)),
+                    None => eprintln!("Illegal move: '{uci_move}'"),
+                }
+            }
             ["islegalperft", depth] => tools::is_legal_perft(depth.parse().unwrap(), &mut board),
             ["islegalperft"] => eprintln!("Usage: islegalperft <depth>"),
 
@@ -111,28 +286,48 @@ pub fn message_loop(mut buffer: VecDeque<String>) {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn spawn_listener(shared: Arc<SharedContext>) -> std::sync::mpsc::Receiver<String> {
+fn spawn_listener(shared: Arc<SharedContext>, mode: Mode) -> std::sync::mpsc::Receiver<String> {
     let (tx, rx) = std::sync::mpsc::channel();
 
     std::thread::spawn(move || {
         loop {
             let mut message = String::new();
 
-            if std::io::stdin().read_line(&mut message).unwrap() == 0 {
-                // EOF received
-                if shared.status.get() != Status::RUNNING {
-                    let _ = tx.send("quit".to_string());
+            // EOF or a broken input stream, e.g. because the GUI crashed. Commands given on the
+            // command line must still run to completion, so only a GUI session stops the search.
+            if std::io::stdin().read_line(&mut message).unwrap_or(0) == 0 {
+                if mode == Mode::Uci {
+                    shared.status.set(Status::STOPPED);
                 }
+                let _ = tx.send("quit".to_string());
+                break;
             }
 
             match message.trim_end() {
                 "isready" => println!("readyok"),
                 "stop" => shared.status.set(Status::STOPPED),
+                // The time limits of a ponder search take effect immediately on `ponderhit`
+                "ponderhit" => {
+                    shared.ponderhit.record();
+                    shared.pondering.store(false, Ordering::Relaxed);
+                }
                 "quit" => {
                     shared.status.set(Status::STOPPED);
                     let _ = tx.send("quit".to_string());
                     break;
                 }
+                // CECP expects commands to be processed while the engine is thinking
+                _ if shared.xboard.load(Ordering::Relaxed) => {
+                    if xboard::interrupts_search(&message) {
+                        shared.status.set(Status::STOPPED);
+                    }
+                    let _ = tx.send(message);
+                }
+                // Options may be changed at any time; the main thread picks them up from the
+                // queue once the search in progress has finished.
+                _ if message.starts_with("setoption") => {
+                    let _ = tx.send(message);
+                }
                 _ => {
                     // According to the UCI specs, commands that are unexpected
                     // in the current state should be ignored silently.
@@ -152,12 +347,39 @@ fn uci() {
     println!("id name Reckless {}", env!("ENGINE_VERSION"));
     println!("id author Arseniy Surkov, Shahin M. Shahin, and Styx");
     println!("option name Hash type spin default {DEFAULT_TT_SIZE} min 1 max 262144");
+    println!("option name EvalCache type spin default {} min 0 max 1024", EvalCache::DEFAULT_SIZE);
     println!("option name Threads type spin default 1 min 1 max {}", ThreadPool::available_threads());
     println!("option name MoveOverhead type spin default 100 min 0 max 2000");
+    println!("option name AutoOverhead type check default false");
+    println!("option name nodestime type spin default 0 min 0 max 10000");
     println!("option name Minimal type check default false");
+    println!("option name OutputFormat type combo default uci var uci var json var pretty");
+    println!("option name Ponder type check default false");
+    println!("option name PonderFactor type spin default 50 min 0 max 100");
+    println!("option name ABDADA type check default false");
     println!("option name Clear Hash type button");
+    println!("option name LargePages type check default false");
+    println!("option name TTReplacement type combo default aged var aged var depth var always");
+    println!("option name TTBucketSize type spin default 3 min 1 max 3");
     println!("option name UCI_Chess960 type check default false");
+    println!("option name UCI_ShowWDL type check default false");
+    println!("option name UCI_ShowCurrLine type check default false");
+    println!("option name UCI_ShowRefutations type check default false");
     println!("option name MultiPV type spin default 1 min 1 max {MAX_MOVES}");
+    println!("option name Contempt type spin default 0 min -100 max 100");
+    println!("option name AdaptiveContempt type check default false");
+    println!("option name UCI_Opponent type string default <none>");
+    println!("option name CurrmoveDelay type spin default {DEFAULT_CURRMOVE_DELAY} min 0 max 3600000");
+    println!("option name UCI_LimitStrength type check default false");
+    println!("option name UCI_Elo type spin default {} min {} max {}", Skill::MAX_ELO, Skill::MIN_ELO, Skill::MAX_ELO);
+    println!("option name Temperature type spin default 0 min 0 max {}", Temperature::MAX_TEMPERATURE);
+    println!("option name TemperaturePlies type spin default 0 min 0 max {}", Temperature::MAX_PLIES);
+
+    println!("option name ThreadAffinity type string default auto");
+    println!("option name ClusterWorkers type string default <none>");
+    println!("option name ClusterToken type string default <none>");
+    println!("option name EvalFileLayout type combo default native var native var rowmajor");
+    println!("option name EvalFile type string default <embedded>");
 
     #[cfg(feature = "syzygy")]
     println!("option name SyzygyPath type string default");
@@ -168,6 +390,18 @@ fn uci() {
     println!("uciok");
 }
 
+/// Applies the contempt, adjusted for the rating of the opponent with `AdaptiveContempt`.
+fn update_contempt(settings: &Settings, shared: &SharedContext) {
+    let contempt = match &settings.opponent {
+        Some(opponent) if settings.adaptive_contempt => {
+            let rating = if settings.limit_strength { settings.elo } else { Skill::MAX_ELO };
+            opponent.contempt(settings.contempt, rating)
+        }
+        _ => settings.contempt,
+    };
+    shared.contempt.store(contempt, Ordering::Relaxed);
+}
+
 fn compiler() {
     println!("Compiler Version: {}", env!("COMPILER_VERSION"));
     println!("Compiler Target: {}", env!("COMPILER_TARGET"));
@@ -176,7 +410,7 @@ fn compiler() {
 
 fn reset(threads: &mut ThreadPool, shared: &Arc<SharedContext>) {
     threads.clear();
-    shared.tt.clear(threads.len());
+    threads.clear_hash();
 
     for corrhist in shared.history.all() {
         corrhist.pawn.clear();
@@ -185,71 +419,198 @@ fn reset(threads: &mut ThreadPool, shared: &Arc<SharedContext>) {
     }
 }
 
-fn go(threads: &mut ThreadPool, settings: &Settings, board: &Board, shared: &Arc<SharedContext>, tokens: &[&str]) {
-    let limits = parse_limits(board.side_to_move(), tokens);
-    let time_manager = TimeManager::new(limits, board.fullmove_number(), settings.move_overhead);
+fn go(threads: &mut ThreadPool, settings: &mut Settings, board: &Board, shared: &Arc<SharedContext>, tokens: &[&str]) {
+    let start = Instant::now();
+    let (tokens, search_moves) = parse_search_moves(board, tokens);
 
-    threads.execute_searches(time_manager, settings.report, settings.multi_pv, board, shared);
+    let ponder = tokens.contains(&"ponder");
+    let tokens = tokens.into_iter().filter(|&token| token != "ponder").collect::<Vec<_>>();
 
-    if threads[0].root_moves.is_empty() {
-        println!("bestmove (none)");
-        return;
+    let skill = settings.limit_strength.then(|| Skill::new(settings.elo));
+
+    let mut limits = parse_limits(board.side_to_move(), &tokens);
+    if let Limits::Cyclic(.., moves, period) = &mut limits {
+        settings.period = settings.period.max(*moves);
+        *period = settings.period;
+    }
+    if settings.nodes_time > 0 {
+        limits = settings.virtual_clock.limits(limits);
     }
+    let clock_limits = limits.clone();
+    let latency = settings.latency.observe(&clock_limits);
+    let mut multi_pv = settings.multi_pv;
 
-    let min_score = threads.iter().map(|v| v.root_moves[0].score).min().unwrap();
-    let vote_value = |td: &ThreadData| (td.root_moves[0].score - min_score + 10) * td.completed_depth;
+    if let Some(skill) = &skill {
+        limits = skill.limits(limits);
+        multi_pv = multi_pv.max(Skill::MULTI_PV);
+    }
 
-    let mut votes: HashMap<&Move, i32> = HashMap::new();
-    for result in threads.iter() {
-        *votes.entry(&result.root_moves[0].mv).or_default() += vote_value(result);
+    // The fullmove number of a FEN may be 0, which counts like 1
+    let temperature = (settings.temperature.temperature > 0)
+        .then(|| 2 * board.fullmove_number().saturating_sub(1) + (board.side_to_move() == Color::Black) as usize)
+        .is_some_and(|ply| settings.temperature.is_active(ply))
+        .then_some(settings.temperature);
+    if temperature.is_some() {
+        multi_pv = multi_pv.max(Temperature::MULTI_PV);
     }
 
-    let mut best = 0;
+    // The game is already over, so play any reasonable move instead of using up the clock. A draw
+    // by repetition or the fifty-move rule is only claimable, so those positions are still searched,
+    // as are depth, node and mate searches, and searches that end with the GUI keep waiting for it.
+    let draw = board
+        .draw_reason()
+        .filter(|reason| matches!(reason, DrawReason::Stalemate | DrawReason::InsufficientMaterial))
+        .filter(|_| !ponder && matches!(clock_limits, Limits::Time(_) | Limits::Fischer(..) | Limits::Cyclic(..)));
+    if let Some(reason) = draw {
+        println!("info depth 0 score cp 0");
+        println!("info string Draw by {reason}");
+        limits = Limits::Depth(1);
+    }
 
-    if !matches!(threads[best].time_manager.limits(), Limits::Depth(_)) && threads[0].multi_pv == 1 {
-        for current in 1..threads.len() {
-            let is_better_candidate = || -> bool {
-                let best = &threads[best];
-                let current = &threads[current];
+    let time_manager = TimeManager::new(limits, board.fullmove_number(), settings.move_overhead + latency)
+        .with_nodes_time(settings.nodes_time)
+        .with_ponder_factor(settings.ponder_factor as f32 / 100.0)
+        .with_max_nodes(skill.as_ref().map_or(u64::MAX, Skill::max_nodes));
+    let report = if draw.is_some() { Report::None } else { settings.report };
 
-                if is_win(best.root_moves[0].score) {
-                    return current.root_moves[0].score > best.root_moves[0].score;
-                }
+    let cluster = !settings.cluster.is_empty() && draw.is_none();
+    if cluster {
+        settings.cluster.start(&search_moves.iter().map(|mv| mv.to_uci(board)).collect::<Vec<_>>());
+    }
 
-                if current.root_moves[0].score != -Score::INFINITE
-                    && best.root_moves[0].score != -Score::INFINITE
-                    && is_loss(best.root_moves[0].score)
-                {
-                    return current.root_moves[0].score < best.root_moves[0].score;
-                }
+    // A GUI reading through a pipe still gets UCI lines
+    let pretty = settings.pretty && report != Report::None && PrettyWriter::is_supported();
+    let writer = pretty.then(|| {
+        let writer = PrettyWriter::new(board);
+        writer.print_header();
+        std::mem::replace(&mut threads.main_thread().writer, Box::new(writer))
+    });
 
-                if current.root_moves[0].score != -Score::INFINITE && is_decisive(current.root_moves[0].score) {
-                    return true;
-                }
+    shared.ponderhit.reset();
+    shared.pondering.store(ponder, Ordering::Relaxed);
+    threads.execute_searches(time_manager, report, multi_pv, board, &search_moves, shared);
+    shared.pondering.store(false, Ordering::Relaxed);
 
-                let best_vote = votes[&best.root_moves[0].mv];
-                let current_vote = votes[&current.root_moves[0].mv];
+    if let Some(writer) = writer {
+        threads.main_thread().writer = writer;
+    }
 
-                !is_loss(current.root_moves[0].score)
-                    && (current_vote > best_vote
-                        || (current_vote == best_vote && vote_value(current) > vote_value(best)))
-            };
+    #[cfg(feature = "stats")]
+    println!("info string {}", threads.iter().map(|td| &td.stats).sum::<crate::stats::SearchStats>());
+
+    if let Some(elapsed) = shared.nodes.aggregate().checked_div(settings.nodes_time) {
+        settings.virtual_clock.charge(&clock_limits, elapsed);
+    } else {
+        settings.latency.record(&clock_limits, ponder, start.elapsed());
+    }
 
-            if is_better_candidate() {
-                best = current;
+    let remote = if cluster { settings.cluster.stop() } else { Vec::new() };
+
+    if draw.is_some() {
+        match threads[0].root_moves.first() {
+            Some(root_move) => {
+                let mv = root_move.mv.to_uci(board);
+                let nodes = shared.nodes.aggregate();
+                if shared.json.load(Ordering::Relaxed) {
+                    println!(
+                        r#"{{"type":"info","depth":1,"seldepth":1,"multipv":1,"score":{{"cp":0}},"nodes":{nodes},"pv":["{mv}"]}}"#
+                    );
+                } else {
+                    println!("info depth 1 seldepth 1 multipv 1 score cp 0 nodes {nodes} pv {mv}");
+                }
+                print_bestmove(shared, Some(&mv), None);
+            }
+            None => {
+                threads.main_thread().print_uci_info(0);
+                print_bestmove(shared, None, None);
             }
         }
+        return;
+    }
+
+    if threads[0].root_moves.is_empty() {
+        print_bestmove(shared, None, None);
+        return;
     }
 
+    let best = threads.best_thread();
+
     if best != 0 {
         let depth = threads[best].completed_depth;
+        let writer = pretty.then(|| std::mem::replace(&mut threads[best].writer, Box::new(PrettyWriter::new(board))));
         threads[best].print_uci_info(depth);
+
+        if let Some(writer) = writer {
+            threads[best].writer = writer;
+        }
     }
 
-    println!("bestmove {}", threads[best].root_moves[0].mv.to_uci(board));
+    if settings.show_refutations && report != Report::None {
+        threads[best].print_refutations();
+    }
+
+    if !remote.is_empty() {
+        let nodes = shared.nodes.aggregate() + remote.iter().map(|result| result.nodes).sum::<u64>();
+        println!("info string Cluster searched {nodes} nodes on {} machines", remote.len() + 1);
+
+        // A worker that got deeper than the master is trusted over it
+        if skill.is_none()
+            && temperature.is_none()
+            && let Some(deepest) = remote.iter().max_by_key(|result| result.depth)
+            && deepest.depth > threads[best].completed_depth
+            && let Some((best_move, ponder)) =
+                deepest.bestmove.as_deref().and_then(|line| parse_remote_bestmove(board, line))
+        {
+            if shared.json.load(Ordering::Relaxed) {
+                println!(r#"{{"type":"remoteinfo","uci":"{}"}}"#, json_escape(&deepest.info));
+            } else {
+                println!("{}", deepest.info);
+            }
+
+            print_bestmove(shared, Some(&best_move), ponder.as_deref());
+            return;
+        }
+    }
+
+    let best_move = match (&skill, &temperature) {
+        (Some(skill), _) => {
+            let root_moves = threads[best].root_moves.clone();
+            skill.pick_move(&root_moves, &mut threads.main_thread().rng)
+        }
+        (None, Some(temperature)) => {
+            let root_moves = threads[best].root_moves.clone();
+            temperature.pick_move(&root_moves, &mut threads.main_thread().rng)
+        }
+        (None, None) => threads[best].root_moves[0].mv,
+    };
+
+    let root_move = &threads[best].root_moves[0];
+    match root_move.pv.line().first() {
+        Some(reply) if best_move == root_move.mv => {
+            let mut after = board.clone();
+            after.make_move(best_move, &mut NullBoardObserver);
+            print_bestmove(shared, Some(&best_move.to_uci(board)), Some(&reply.to_uci(&after)));
+        }
+        _ => print_bestmove(shared, Some(&best_move.to_uci(board)), None),
+    }
     crate::misc::dbg_print();
 }
 
+/// Prints the `bestmove` line, where no move stands for a position without legal moves.
+fn print_bestmove(shared: &SharedContext, best: Option<&str>, ponder: Option<&str>) {
+    if shared.json.load(Ordering::Relaxed) {
+        let quote = |mv: Option<&str>| mv.map_or("null".to_string(), |mv| format!(r#""{mv}""#));
+        println!(r#"{{"type":"bestmove","bestmove":{},"ponder":{}}}"#, quote(best), quote(ponder));
+        return;
+    }
+
+    match (best, ponder) {
+        (Some(best), Some(ponder)) => println!("bestmove {best} ponder {ponder}"),
+        (Some(best), None) => println!("bestmove {best}"),
+        (None, _) => println!("bestmove (none)"),
+    }
+}
+
 fn position(board: &mut Board, settings: &Settings, mut tokens: &[&str]) {
     while !tokens.is_empty() {
         match tokens {
@@ -271,18 +632,67 @@ fn position(board: &mut Board, settings: &Settings, mut tokens: &[&str]) {
                 }
                 break;
             }
+            ["san", rest @ ..] => {
+                for san in rest {
+                    match parse_san(board, san) {
+                        Some(mv) => board.make_move(mv, &mut NullBoardObserver),
+                        None => eprintln!("Invalid SAN move: '{san}'"),
+                    }
+                }
+                break;
+            }
             _ => tokens = &tokens[1..],
         }
     }
 }
 
 fn make_uci_move(board: &mut Board, uci_move: &str) {
-    let moves = board.generate_all_moves();
-    if let Some(mv) = moves.iter().map(|entry| entry.mv).find(|mv| mv.to_uci(board) == uci_move) {
+    if let Some(mv) = find_uci_move(board, uci_move) {
         board.make_move(mv, &mut NullBoardObserver);
     }
 }
 
+fn find_uci_move(board: &Board, uci_move: &str) -> Option<Move> {
+    board.generate_all_moves().iter().map(|entry| entry.mv).find(|mv| mv.to_uci(board) == uci_move)
+}
+
+/// The best and ponder moves of a worker's `bestmove` line, if the best move is legal in `board`.
+/// A stale or corrupted line would otherwise become an illegal `bestmove`, and a ponder move that
+/// isn't legal after the best move is left out.
+fn parse_remote_bestmove(board: &Board, line: &str) -> Option<(String, Option<String>)> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let best = match tokens.as_slice() {
+        ["bestmove", best, ..] => find_uci_move(board, best)?,
+        _ => return None,
+    };
+
+    let mut after = board.clone();
+    after.make_move(best, &mut NullBoardObserver);
+
+    let ponder = match tokens.as_slice() {
+        [_, _, "ponder", ponder, ..] => find_uci_move(&after, ponder),
+        _ => None,
+    };
+
+    Some((best.to_uci(board), ponder.map(|mv| mv.to_uci(&after))))
+}
+
+/// Loads the network of `EvalFile`, returning whether it succeeded.
+fn load_eval_file(threads: &mut ThreadPool, shared: &SharedContext, path: &str, layout: WeightLayout) -> bool {
+    match crate::nnue::load_network(path, layout) {
+        Ok(()) => {
+            shared.parameters.refresh();
+            threads.clear();
+            println!("info string Loaded network {path}");
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to load network '{path}': {e}");
+            false
+        }
+    }
+}
+
 fn set_option(threads: &mut ThreadPool, settings: &mut Settings, shared: &Arc<SharedContext>, tokens: &[&str]) {
     match tokens {
         ["name", "Minimal", "value", v] => match *v {
@@ -290,31 +700,209 @@ fn set_option(threads: &mut ThreadPool, settings: &mut Settings, shared: &Arc<Sh
             "false" => settings.report = Report::Full,
             _ => eprintln!("Invalid value: '{v}'"),
         },
+        ["name", "OutputFormat", "value", v @ ("uci" | "json" | "pretty")] => {
+            shared.json.store(*v == "json", Ordering::Relaxed);
+            settings.pretty = *v == "pretty";
+        }
+        ["name", "OutputFormat", "value", v] => eprintln!("Invalid value: '{v}'"),
+        // Pondering is driven entirely by `go ponder` and `ponderhit`
+        ["name", "Ponder", "value", _] => (),
         ["name", "Clear", "Hash"] => {
-            shared.tt.clear(threads.len());
+            threads.clear_hash();
             println!("info string Hash cleared");
         }
+        ["name", "LargePages", "value", v] => {
+            let enabled = v.parse().unwrap_or_default();
+            let huge = shared.tt.set_large_pages(enabled);
+            threads.clear_hash();
+
+            if huge || !enabled {
+                println!("info string set LargePages to {v}");
+            } else {
+                println!("info string LargePages unavailable, using regular pages");
+            }
+        }
         ["name", "Hash", "value", v] => {
-            shared.tt.resize(threads.len(), v.parse().unwrap());
+            shared.tt.resize(v.parse().unwrap());
+            threads.clear_hash();
             println!("info string set Hash to {v} MB");
         }
         ["name", "Threads", "value", v] => {
             threads.set_count(v.parse().unwrap_or(1));
             println!("info string set Threads to {}", threads.len());
+            println!("info string {}", shared.numa_context.describe(threads.len()));
         }
-        ["name", "MoveOverhead", "value", v] => {
+        ["name", "EvalCache", "value", v] => match v.parse() {
+            Ok(megabytes @ 0..=1024) => {
+                shared.eval_cache_size.store(megabytes, Ordering::Relaxed);
+                threads.set_count(threads.len());
+                println!("info string set EvalCache to {v} MB per thread");
+            }
+            _ => eprintln!("Invalid value: '{v}'"),
+        },
+        ["name", "ThreadAffinity", "value", cpus @ ..] => {
+            let cpus = cpus.join(" ");
+            let cfg = match cpus.as_str() {
+                "auto" => Some(NumaConfig::from_system()),
+                _ => NumaConfig::from_system().with_affinity(&cpus),
+            };
+
+            match cfg {
+                Some(cfg) => {
+                    shared.numa_context.set_numa_config(cfg);
+                    threads.set_count(threads.len());
+                    println!("info string {}", shared.numa_context.describe(threads.len()));
+                }
+                None => eprintln!("Invalid value: '{cpus}'"),
+            }
+        }
+        ["name", "ClusterWorkers", "value", addresses @ ..] => {
+            let addresses = addresses.join(" ");
+            if addresses == "<none>" {
+                settings.cluster.disconnect();
+                return;
+            }
+
+            match settings.cluster.connect(&addresses) {
+                Ok(()) => println!("info string Connected to {} cluster workers", settings.cluster.len()),
+                Err(e) => eprintln!("Failed to connect to cluster worker {e}"),
+            }
+        }
+        ["name", "ClusterToken", "value", token] => {
+            let token = (*token != "<none>").then(|| token.to_string());
+            if let Err(e) = settings.cluster.set_token(token) {
+                eprintln!("Failed to connect to cluster worker {e}");
+            }
+        }
+        ["name", "MoveOverhead", "value", v] | ["name", "Move", "Overhead", "value", v] => {
             settings.move_overhead = v.parse().unwrap();
             println!("info string set MoveOverhead to {v} ms");
         }
+        ["name", "PonderFactor", "value", v] => {
+            settings.ponder_factor = v.parse::<u64>().unwrap_or(50).min(100);
+            println!("info string set PonderFactor to {}%", settings.ponder_factor);
+        }
+        ["name", "AutoOverhead", "value", v] => match *v {
+            "true" => settings.latency.enabled = true,
+            "false" => settings.latency.enabled = false,
+            _ => eprintln!("Invalid value: '{v}'"),
+        },
+        ["name", "nodestime", "value", v] => {
+            settings.nodes_time = v.parse().unwrap_or_default();
+            settings.virtual_clock.reset();
+            println!("info string set nodestime to {} nodes per ms", settings.nodes_time);
+        }
         #[cfg(feature = "syzygy")]
         ["name", "SyzygyPath", "value", v] => match crate::tb::initialize(v) {
             Some(size) => println!("info string Loaded Syzygy tablebases with {size} pieces"),
             None => eprintln!("Failed to load Syzygy tablebases"),
         },
+        ["name", "EvalFile", "value", "<embedded>"] => (),
+        ["name", "EvalFile", "value", path @ ..] => {
+            let path = path.join(" ");
+            if load_eval_file(threads, shared, &path, settings.weight_layout) {
+                settings.eval_file = Some(path);
+            }
+        }
+        ["name", "EvalFileLayout", "value", v] => match WeightLayout::from_name(v) {
+            Some(layout) => {
+                // The options may arrive in any order, so a network loaded before is read again
+                match &settings.eval_file {
+                    Some(path) if layout != settings.weight_layout => {
+                        load_eval_file(threads, shared, path, layout);
+                    }
+                    _ => (),
+                }
+
+                settings.weight_layout = layout;
+                println!("info string set EvalFileLayout to {v}");
+            }
+            None => eprintln!("Invalid value: '{v}'"),
+        },
         ["name", "UCI_Chess960", "value", v] => {
             settings.frc = v.parse().unwrap_or_default();
             println!("info string set UCI_Chess960 to {v}");
         }
+        ["name", "TTReplacement", "value", v] => match Replacement::from_name(v) {
+            Some(replacement) => {
+                shared.tt.set_replacement(replacement);
+                println!("info string set TTReplacement to {v}");
+            }
+            None => eprintln!("Invalid value: '{v}'"),
+        },
+        ["name", "TTBucketSize", "value", v] => match v.parse() {
+            Ok(ways @ 1..=3) => {
+                shared.tt.set_bucket_size(ways);
+                threads.clear_hash();
+                println!("info string set TTBucketSize to {v}");
+            }
+            _ => eprintln!("Invalid value: '{v}'"),
+        },
+        // Not advertised until it passes an SPRT, but settable for the test
+        ["name", "LazyEval", "value", v] => {
+            shared.lazy_eval.store(v.parse().unwrap_or_default(), Ordering::Relaxed);
+            println!("info string set LazyEval to {v}");
+        }
+        ["name", "ABDADA", "value", v] => {
+            shared.abdada.store(v.parse().unwrap_or_default(), Ordering::Relaxed);
+            println!("info string set ABDADA to {v}");
+        }
+        ["name", "UCI_ShowWDL", "value", v] => {
+            shared.show_wdl.store(v.parse().unwrap_or_default(), Ordering::Relaxed);
+            println!("info string set UCI_ShowWDL to {v}");
+        }
+        ["name", "UCI_ShowCurrLine", "value", v] => {
+            settings.show_currline = v.parse().unwrap_or_default();
+            shared.show_currline.store(settings.debug || settings.show_currline, Ordering::Relaxed);
+            println!("info string set UCI_ShowCurrLine to {v}");
+        }
+        ["name", "UCI_ShowRefutations", "value", v] => {
+            settings.show_refutations = v.parse().unwrap_or_default();
+            println!("info string set UCI_ShowRefutations to {v}");
+        }
+        ["name", "Contempt", "value", v] => {
+            settings.contempt = v.parse::<i32>().unwrap_or_default().clamp(-100, 100);
+            update_contempt(settings, shared);
+            println!("info string set Contempt to {v}");
+        }
+        ["name", "AdaptiveContempt", "value", v] => {
+            settings.adaptive_contempt = v.parse().unwrap_or_default();
+            update_contempt(settings, shared);
+            println!("info string set AdaptiveContempt to {v}");
+        }
+        ["name", "UCI_Opponent", "value", value @ ..] => {
+            let value = value.join(" ");
+            settings.opponent = Opponent::parse(&value);
+            update_contempt(settings, shared);
+            match &settings.opponent {
+                Some(opponent) => println!("info string set UCI_Opponent to {opponent}"),
+                None if value == "<none>" => (),
+                None => eprintln!("Invalid value: '{value}'"),
+            }
+        }
+        ["name", "CurrmoveDelay", "value", v] => {
+            shared.currmove_delay.store(v.parse().unwrap_or(DEFAULT_CURRMOVE_DELAY), Ordering::Relaxed);
+            println!("info string set CurrmoveDelay to {v} ms");
+        }
+        ["name", "UCI_LimitStrength", "value", v] => {
+            settings.limit_strength = v.parse().unwrap_or_default();
+            update_contempt(settings, shared);
+            println!("info string set UCI_LimitStrength to {v}");
+        }
+        ["name", "UCI_Elo", "value", v] => {
+            settings.elo = v.parse().unwrap_or(Skill::MAX_ELO).clamp(Skill::MIN_ELO, Skill::MAX_ELO);
+            update_contempt(settings, shared);
+            println!("info string set UCI_Elo to {}", settings.elo);
+        }
+        ["name", "Temperature", "value", v] => {
+            settings.temperature.temperature =
+                v.parse::<i32>().unwrap_or_default().clamp(0, Temperature::MAX_TEMPERATURE);
+            println!("info string set Temperature to {}", settings.temperature.temperature);
+        }
+        ["name", "TemperaturePlies", "value", v] => {
+            settings.temperature.plies = v.parse().unwrap_or_default();
+            println!("info string set TemperaturePlies to {v}");
+        }
         ["name", "MultiPV", "value", v] => {
             settings.multi_pv = v.parse().unwrap_or_default();
             println!("info string set MultiPV to {v}");
@@ -385,51 +973,100 @@ fn eval(td: &mut ThreadData, board: &Board) {
     let final_eval = td.nnue.evaluate(board);
     let final_total = (if side == Color::White { final_eval } else { -final_eval }) as f32 / 100.0;
     println!("\nNNUE evaluation        {final_total:+.2} (White's POV)");
-}
 
-fn parse_limits(color: Color, tokens: &[&str]) -> Limits {
-    if let ["infinite"] = tokens {
-        return Limits::Infinite;
-    }
+    let breakdown = td.nnue.breakdown(board);
+    let white = |score: i32| (if side == Color::White { score } else { -score }) as f32 / 100.0;
+    let percent = |count: usize, total: usize| 100.0 * count as f32 / total as f32;
 
-    let mut main = None;
-    let mut inc = None;
-    let mut moves = None;
+    let l1_size = crate::nnue::L1_SIZE;
+    let (active, chunks) = (breakdown.active, breakdown.chunks);
 
-    for chunk in tokens.chunks(2) {
-        if let [name, value] = *chunk {
-            let Ok(value) = value.parse::<u64>() else {
-                continue;
-            };
+    println!("Output bucket          {}", breakdown.bucket);
+    println!("FT active outputs      {active}/{l1_size} ({:.1}%)", percent(active, l1_size));
+    println!("L1 non-zero chunks     {chunks}/{} ({:.1}%)", l1_size / 4, percent(chunks, l1_size / 4));
+    println!("Piece features only    {:+.2} (White's POV)", white(breakdown.without_threats));
+    println!("Threat influence       {:+.2} (White's POV)", white(breakdown.eval - breakdown.without_threats));
+
+    if let Some((win, draw, loss)) = td.nnue.evaluate_wdl(board) {
+        let (win, loss) = if side == Color::White { (win, loss) } else { (loss, win) };
+        println!("NNUE WDL               {win} {draw} {loss} (White's POV, permille)");
+    }
+}
 
-            match name {
-                "depth" if value > 0 => return Limits::Depth(value as i32),
-                "movetime" if value > 0 => return Limits::Time(value),
-                "nodes" if value > 0 => return Limits::Nodes(value),
-                "mate" if value > 0 => return Limits::Mate(value),
+/// Prints the output of `eval` as a single JSON object, with the values in pawns from White's
+/// point of view.
+fn eval_json(td: &mut ThreadData, board: &Board) {
+    td.nnue.full_refresh(board);
+    td.nnue.evaluate(board);
 
-                "wtime" if Color::White == color => main = Some(value),
-                "btime" if Color::Black == color => main = Some(value),
-                "winc" if Color::White == color => inc = Some(value),
-                "binc" if Color::Black == color => inc = Some(value),
-                "movestogo" => moves = Some(value),
+    let side = board.side_to_move();
+    let white = |score: i32| (if side == Color::White { score } else { -score }) as f32 / 100.0;
+
+    let pieces = (0..8)
+        .flat_map(|rank| (0..8).map(move |file| Square::from_rank_file(rank, file)))
+        .filter_map(|sq| {
+            let value = td.nnue.piece_contribution(board, sq)?;
+            Some(format!(r#""{sq}":{:.2}"#, white(value)))
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let buckets = (0..8).map(|bucket| format!("{:.2}", white(td.nnue.eval_with_bucket(board, bucket))));
+    let buckets = buckets.collect::<Vec<_>>().join(",");
+
+    let eval = white(td.nnue.evaluate(board));
+    let breakdown = td.nnue.breakdown(board);
+
+    let wdl = match td.nnue.evaluate_wdl(board) {
+        Some((win, draw, loss)) => {
+            let (win, loss) = if side == Color::White { (win, loss) } else { (loss, win) };
+            format!(r#","wdl":[{win},{draw},{loss}]"#)
+        }
+        None => String::new(),
+    };
+
+    println!(
+        r#"{{"type":"eval","eval":{eval:.2},"bucket":{},"buckets":[{buckets}],"pieces":{{{pieces}}},"without_threats":{:.2}{wdl}}}"#,
+        breakdown.bucket,
+        white(breakdown.without_threats),
+    );
+}
 
-                _ => continue,
-            }
+/// Escapes text for a JSON string, such as a line received from a cluster worker.
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
         }
     }
+    escaped
+}
 
-    if main.is_none() && inc.is_none() {
-        return Limits::Infinite;
-    }
+/// Splits the `searchmoves` restriction off the `go` arguments, returning the remaining
+/// arguments and the legal moves listed after `searchmoves`.
+fn parse_search_moves<'a>(board: &Board, tokens: &[&'a str]) -> (Vec<&'a str>, Vec<Move>) {
+    let Some(start) = tokens.iter().position(|&token| token == "searchmoves") else {
+        return (tokens.to_vec(), Vec::new());
+    };
 
-    let main = main.unwrap_or_default();
-    let inc = inc.unwrap_or_default();
+    let moves = board.generate_all_moves();
+
+    let mut search_moves = Vec::new();
+    let mut end = start + 1;
 
-    match moves {
-        Some(moves) => Limits::Cyclic(main, inc, moves),
-        None => Limits::Fischer(main, inc),
+    while let Some(mv) =
+        tokens.get(end).and_then(|&token| moves.iter().map(|entry| entry.mv).find(|mv| mv.to_uci(board) == token))
+    {
+        search_moves.push(mv);
+        end += 1;
     }
+
+    let remaining = tokens[..start].iter().chain(&tokens[end..]).copied().collect();
+    (remaining, search_moves)
 }
 
 #[cfg(test)]
@@ -452,6 +1089,12 @@ mod tests {
         assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
     }
 
+    #[test]
+    fn test_position_san_moves() {
+        let board = test_position_helper(&["startpos", "san", "e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "O-O"]);
+        assert_eq!(board.to_fen(), "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 4");
+    }
+
     #[test]
     fn test_position_startpos_multiple_moves() {
         let board = test_position_helper(&["moves", "e2e4", "e7e5", "g1f3"]);
@@ -546,4 +1189,105 @@ mod tests {
         let board = test_position_helper(&["moves", "e2e4", "e7e5"]);
         assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
     }
+
+    #[test]
+    fn test_parse_search_moves() {
+        let board = Board::starting_position();
+
+        let (tokens, search_moves) =
+            parse_search_moves(&board, &["wtime", "1000", "searchmoves", "e2e4", "d2d4", "btime", "900"]);
+        assert_eq!(tokens, ["wtime", "1000", "btime", "900"]);
+        assert_eq!(search_moves.iter().map(|mv| mv.to_uci(&board)).collect::<Vec<_>>(), ["e2e4", "d2d4"]);
+
+        let (tokens, search_moves) = parse_search_moves(&board, &["depth", "5"]);
+        assert_eq!(tokens, ["depth", "5"]);
+        assert!(search_moves.is_empty());
+    }
+
+    #[test]
+    fn test_adaptive_contempt_rates_the_engine_by_uci_elo() {
+        let shared = SharedContext::default();
+        let mut settings = Settings {
+            adaptive_contempt: true,
+            opponent: Opponent::parse("none 2000 human Someone"),
+            ..Default::default()
+        };
+
+        update_contempt(&settings, &shared);
+        assert_eq!(shared.contempt.load(Ordering::Relaxed), 50);
+
+        settings.limit_strength = true;
+        settings.elo = 1500;
+        update_contempt(&settings, &shared);
+        assert_eq!(shared.contempt.load(Ordering::Relaxed), -50);
+    }
+
+    fn initialize() {
+        static INITIALIZE: std::sync::Once = std::sync::Once::new();
+        INITIALIZE.call_once(|| {
+            crate::lookup::initialize();
+            crate::nnue::initialize();
+        });
+    }
+
+    #[test]
+    fn test_refutations_have_a_reply() {
+        initialize();
+
+        let shared = Arc::new(SharedContext::default());
+        let mut threads = ThreadPool::new(shared.clone());
+        let board = Board::starting_position();
+
+        let time_manager = TimeManager::new(Limits::Depth(6), 0, 0);
+        threads.execute_searches(time_manager, Report::None, 1, &board, &[], &shared);
+
+        let td = threads.main_thread();
+        td.writer = Box::new(crate::thread::BufferWriter::default());
+        td.print_refutations();
+        let output = td.writer.take();
+
+        assert!(!output.is_empty());
+        for line in output.lines() {
+            let moves = line.strip_prefix("info refutation ").unwrap().split_whitespace().count();
+            assert!(moves >= 2, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_remote_bestmove_is_checked_against_the_board() {
+        initialize();
+
+        let board = Board::starting_position();
+        let parse = |line: &str| parse_remote_bestmove(&board, line);
+
+        assert_eq!(parse("bestmove e2e4 ponder e7e5"), Some(("e2e4".to_string(), Some("e7e5".to_string()))));
+        assert_eq!(parse("bestmove e2e4 ponder e2e4"), Some(("e2e4".to_string(), None)));
+        assert_eq!(parse("bestmove e2e5 ponder e7e5"), None);
+        assert_eq!(parse("info depth 1"), None);
+    }
+
+    #[test]
+    fn test_json_escape() {
+        assert_eq!(json_escape("info string \"a\\b\"\t"), r#"info string \"a\\b\"\u0009"#);
+    }
+
+    #[test]
+    fn test_parse_limits_soft_nodes() {
+        let limits = |tokens: &[&str]| format!("{:?}", parse_limits(Color::White, tokens));
+
+        assert_eq!(limits(&["nodes", "1000"]), "Nodes(1000)");
+        assert_eq!(limits(&["nodes", "5000", "softnodes", "1000"]), "SoftNodes(1000, 5000)");
+        assert_eq!(limits(&["softnodes", "1000", "wtime", "100"]), "SoftNodes(1000, 8000)");
+    }
+
+    #[test]
+    fn test_latency_from_clock() {
+        let mut latency = Latency { enabled: true, ..Latency::default() };
+
+        assert_eq!(latency.observe(&Limits::Fischer(10_000, 100)), 0);
+        latency.record(&Limits::Fischer(10_000, 100), false, Duration::from_millis(500));
+
+        // 9600 ms were expected, so 400 ms got lost on the way
+        assert_eq!(latency.observe(&Limits::Fischer(9_200, 100)), 100);
+    }
 }
@@ -9,7 +9,7 @@ use crate::{
     search::Report,
     thread::SharedContext,
     threadpool::ThreadPool,
-    time::{Limits, TimeManager},
+    time::{Limits, TimeManager, parse_limits},
 };
 
 #[wasm_bindgen]
@@ -73,7 +73,7 @@ impl Engine {
     fn run_search(&mut self, limits: Limits, multi_pv: usize, on_info: Option<JsFunction>) {
         crate::thread::WASM_CALLBACK.with(|c| *c.borrow_mut() = on_info);
         let tm = TimeManager::new(limits, self.board.fullmove_number(), 0);
-        self.threads.execute_searches(tm, Report::Full, multi_pv, &self.board, &self.shared);
+        self.threads.execute_searches(tm, Report::Full, multi_pv, &self.board, &[], &self.shared);
         crate::thread::WASM_CALLBACK.with(|c| *c.borrow_mut() = None);
     }
 
@@ -108,7 +108,63 @@ impl Engine {
 
     pub fn reset(&mut self) {
         self.threads.clear();
-        self.shared.tt.clear(1);
+        self.threads.clear_hash();
+    }
+
+    /// Executes a UCI command and returns the output it produced, so that web GUIs can
+    /// talk to the engine the same way they would to a native one.
+    pub fn command(&mut self, line: &str, on_info: Option<JsFunction>) -> String {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        match tokens.as_slice() {
+            ["uci"] => {
+                self.threads.vector[0].writer.write_line(&format!("id name Reckless {}", env!("ENGINE_VERSION")));
+                self.threads.vector[0].writer.write_line("uciok");
+            }
+            ["isready"] => self.threads.vector[0].writer.write_line("readyok"),
+            ["ucinewgame"] => self.reset(),
+            ["position", rest @ ..] => self.position(rest),
+            ["setoption", "name", "Threads", "value", v] => self.threads.set_count(v.parse().unwrap_or(1)),
+            ["setoption", "name", "Hash", "value", v] => {
+                self.shared.tt.resize(v.parse().unwrap_or(16));
+                self.threads.clear_hash();
+            }
+            ["go", rest @ ..] => {
+                let limits = parse_limits(self.board.side_to_move(), rest);
+                let multi_pv = match rest.iter().position(|&token| token == "multipv") {
+                    Some(index) => rest.get(index + 1).and_then(|v| v.parse::<usize>().ok()).unwrap_or(1).max(1),
+                    None => 1,
+                };
+
+                self.run_search(limits, multi_pv, on_info);
+
+                let best_move = self.last_bestmove();
+                self.threads.vector[0].writer.write_line(&format!("bestmove {best_move}"));
+            }
+            _ => self.threads.vector[0].writer.write_line(&format!("info string Unknown command: '{line}'")),
+        }
+
+        self.take_output()
+    }
+
+    fn position(&mut self, mut tokens: &[&str]) {
+        while let [token, rest @ ..] = tokens {
+            match *token {
+                "startpos" => self.board = Board::starting_position(),
+                "fen" => {
+                    let end = rest.iter().position(|&token| token == "moves").unwrap_or(rest.len());
+                    self.set_position(&rest[..end].join(" "));
+                }
+                "moves" => {
+                    for uci_move in rest {
+                        self.make_move(uci_move);
+                    }
+                    break;
+                }
+                _ => (),
+            }
+            tokens = rest;
+        }
     }
 }
 
@@ -0,0 +1,317 @@
+//! Frontend for the [Chess Engine Communication Protocol][cecp] used by xboard, WinBoard
+//! and many ICS interfaces. It is selected when the GUI sends `xboard` instead of `uci`.
+//!
+//! Unlike UCI, the engine keeps track of the game itself: it receives the opponent's
+//! moves, decides when it's on move, and replies with `move <move>`.
+//!
+//! [cecp]: https://www.gnu.org/software/xboard/engine-intf.html
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, atomic::Ordering, mpsc::Receiver},
+};
+
+use crate::{
+    board::{Board, DrawReason, NullBoardObserver},
+    search::Report,
+    thread::SharedContext,
+    threadpool::ThreadPool,
+    time::{Limits, TimeManager},
+    types::{Color, Move},
+};
+
+const MOVE_OVERHEAD: u64 = 100;
+
+/// The time per move when the GUI sets neither a time control nor a fixed time or depth.
+const DEFAULT_MOVE_TIME: u64 = 5000;
+
+/// Commands that stop the search in progress, either to move now or to change the position.
+const INTERRUPTS: [&str; 15] = [
+    "?", "new", "force", "quit", "result", "undo", "remove", "setboard", "exit", "usermove", "edit", "analyze", "go",
+    "white", "black",
+];
+
+/// Commands after which the engine must not play the move it was thinking about.
+const CANCELS: [&str; 10] = ["new", "force", "quit", "result", "undo", "remove", "setboard", "edit", "white", "black"];
+
+/// Whether the command received while searching should stop the search.
+pub fn interrupts_search(message: &str) -> bool {
+    let command = message.split_whitespace().next().unwrap_or_default();
+    INTERRUPTS.contains(&command)
+}
+
+#[derive(Default)]
+struct Clock {
+    moves_per_session: u64,
+    base: u64,
+    increment: u64,
+    remaining: Option<u64>,
+    seconds_per_move: Option<u64>,
+    depth: Option<i32>,
+}
+
+struct State {
+    board: Board,
+    history: Vec<Move>,
+    engine: Option<Color>,
+    analyze: bool,
+    post: bool,
+    clock: Clock,
+}
+
+enum Flow {
+    Continue,
+    Quit,
+}
+
+pub fn message_loop(threads: &mut ThreadPool, shared: &Arc<SharedContext>, rx: &Receiver<String>) {
+    shared.xboard.store(true, Ordering::Relaxed);
+
+    let mut state = State {
+        board: Board::starting_position(),
+        history: Vec::new(),
+        engine: Some(Color::Black),
+        analyze: false,
+        post: false,
+        clock: Clock::default(),
+    };
+
+    let mut pending = VecDeque::new();
+
+    loop {
+        // Commands that are already queued take priority over starting a new search
+        pending.extend(rx.try_iter());
+
+        if pending.is_empty() && game_result(&state.board).is_none() {
+            if state.analyze {
+                search(threads, shared, &state, Limits::Infinite);
+            } else if state.engine == Some(state.board.side_to_move()) {
+                think(threads, shared, &mut state, rx, &mut pending);
+            }
+        }
+
+        let message = match pending.pop_front() {
+            Some(message) => message,
+            None => match rx.recv() {
+                Ok(message) => message,
+                Err(_) => break,
+            },
+        };
+
+        if let Flow::Quit = execute(threads, shared, &mut state, &message) {
+            break;
+        }
+    }
+
+    shared.xboard.store(false, Ordering::Relaxed);
+}
+
+fn execute(threads: &mut ThreadPool, shared: &Arc<SharedContext>, state: &mut State, message: &str) -> Flow {
+    let tokens = message.split_whitespace().collect::<Vec<_>>();
+
+    match tokens.as_slice() {
+        ["protover", ..] => {
+            println!("feature done=0");
+            println!("feature myname=\"Reckless {}\"", env!("ENGINE_VERSION"));
+            println!("feature ping=1 setboard=1 usermove=1 analyze=1 colors=0 sigint=0 sigterm=0 san=0");
+            println!("feature reuse=1 memory=1 smp=1 debug=1");
+            println!("feature done=1");
+        }
+        ["new"] => {
+            state.board = Board::starting_position();
+            state.history.clear();
+            state.engine = Some(Color::Black);
+            state.clock.seconds_per_move = None;
+            state.clock.depth = None;
+
+            threads.clear();
+            threads.clear_hash();
+        }
+        ["setboard", fen @ ..] => match Board::from_fen(&fen.join(" ")) {
+            Ok(board) => {
+                state.board = board;
+                state.history.clear();
+            }
+            Err(_) => println!("tellusererror Illegal position"),
+        },
+        ["usermove", mv] => user_move(state, mv),
+        // The game is over after `result`, so the engine mustn't move again until it's told to
+        ["force"] | ["result", ..] => state.engine = None,
+        ["go"] => state.engine = Some(state.board.side_to_move()),
+        ["playother"] => state.engine = Some(!state.board.side_to_move()),
+        ["white"] => set_side_to_move(state, Color::White),
+        ["black"] => set_side_to_move(state, Color::Black),
+        ["undo"] => undo(state, 1),
+        ["remove"] => undo(state, 2),
+        ["analyze"] => state.analyze = true,
+        ["exit"] => state.analyze = false,
+        ["post"] => state.post = true,
+        ["nopost"] => state.post = false,
+        ["ping", n] => println!("pong {n}"),
+        ["level", mps, base, inc] => {
+            state.clock.moves_per_session = mps.parse().unwrap_or_default();
+            state.clock.base = parse_base_time(base);
+            state.clock.increment = (inc.parse::<f64>().unwrap_or_default() * 1000.0) as u64;
+            state.clock.remaining = None;
+        }
+        ["st", seconds] => state.clock.seconds_per_move = seconds.parse().ok(),
+        ["sd", depth] => state.clock.depth = depth.parse().ok(),
+        ["time", centiseconds] => state.clock.remaining = centiseconds.parse::<u64>().ok().map(|cs| cs * 10),
+        ["memory", megabytes] => {
+            shared.tt.resize(megabytes.parse().unwrap_or(1).max(1));
+            threads.clear_hash();
+        }
+        ["cores", cores] => threads.set_count(cores.parse().unwrap_or(1)),
+        ["quit"] => return Flow::Quit,
+
+        // Accepted but irrelevant to the engine
+        [
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "draw" | "hint" | "bk"
+            | "otim" | "name" | "rating" | "ics" | "variant" | "." | "?",
+            ..,
+        ] => (),
+
+        [] => (),
+
+        // Protocol version 1 interfaces send moves without the `usermove` prefix
+        [mv] if parse_move(&state.board, mv).is_some() => user_move(state, mv),
+
+        _ => println!("Error (unknown command): {}", message.trim_end()),
+    }
+
+    Flow::Continue
+}
+
+fn user_move(state: &mut State, text: &str) {
+    let Some(mv) = parse_move(&state.board, text) else {
+        println!("Illegal move: {text}");
+        return;
+    };
+
+    make_move(state, mv);
+}
+
+fn make_move(state: &mut State, mv: Move) {
+    state.board.make_move(mv, &mut NullBoardObserver);
+    state.history.push(mv);
+
+    if let Some(result) = game_result(&state.board) {
+        println!("{result}");
+    }
+}
+
+/// Puts `color` on move and makes the engine play the other side, for the `white` and `black`
+/// commands of protocol version 1.
+fn set_side_to_move(state: &mut State, color: Color) {
+    if state.board.side_to_move() != color {
+        let fen = state.board.to_fen();
+        let mut fields = fen.split_whitespace().collect::<Vec<_>>();
+        fields[1] = if color == Color::White { "w" } else { "b" };
+        fields[3] = "-";
+
+        match Board::from_fen(&fields.join(" ")) {
+            Ok(board) => {
+                state.board = board;
+                state.history.clear();
+            }
+            Err(_) => println!("tellusererror Illegal position"),
+        }
+    }
+
+    state.engine = Some(!color);
+}
+
+fn undo(state: &mut State, plies: usize) {
+    for _ in 0..plies {
+        if let Some(mv) = state.history.pop() {
+            state.board.undo_move(mv);
+        }
+    }
+}
+
+/// Searches for the engine's move and plays it, unless a command received in the meantime cancels it.
+fn think(
+    threads: &mut ThreadPool, shared: &Arc<SharedContext>, state: &mut State, rx: &Receiver<String>,
+    pending: &mut VecDeque<String>,
+) {
+    let limits = limits(state);
+    search(threads, shared, state, limits);
+
+    pending.extend(rx.try_iter());
+
+    let cancelled = pending.iter().any(|message| {
+        let command = message.split_whitespace().next().unwrap_or_default();
+        CANCELS.contains(&command)
+    });
+
+    let td = &threads[threads.best_thread()];
+    if cancelled || td.root_moves.is_empty() {
+        return;
+    }
+
+    let mv = td.root_moves[0].mv;
+    println!("move {}", mv.to_uci(&state.board));
+
+    make_move(state, mv);
+}
+
+fn search(threads: &mut ThreadPool, shared: &Arc<SharedContext>, state: &State, limits: Limits) {
+    let report = if state.post { Report::Full } else { Report::None };
+    let time_manager = TimeManager::new(limits, state.board.fullmove_number(), MOVE_OVERHEAD);
+
+    threads.execute_searches(time_manager, report, 1, &state.board, &[], shared);
+}
+
+fn limits(state: &State) -> Limits {
+    let clock = &state.clock;
+
+    if let Some(seconds) = clock.seconds_per_move {
+        return Limits::Time(seconds * 1000);
+    }
+
+    if let Some(depth) = clock.depth {
+        return Limits::Depth(depth);
+    }
+
+    let remaining = clock.remaining.unwrap_or(clock.base);
+    if remaining == 0 && clock.increment == 0 {
+        return Limits::Time(DEFAULT_MOVE_TIME);
+    }
+
+    match clock.moves_per_session {
+        0 => Limits::Fischer(remaining, clock.increment),
+        moves => {
+            let played = (state.board.fullmove_number() as u64).saturating_sub(1);
+            Limits::Cyclic(remaining, clock.increment, moves - played % moves, moves)
+        }
+    }
+}
+
+/// Parses the base time of the `level` command, given as minutes or as `minutes:seconds`.
+fn parse_base_time(base: &str) -> u64 {
+    let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+    let minutes = minutes.parse::<u64>().unwrap_or_default();
+    let seconds = seconds.parse::<u64>().unwrap_or_default();
+
+    (60 * minutes + seconds) * 1000
+}
+
+fn parse_move(board: &Board, text: &str) -> Option<Move> {
+    board.generate_all_moves().iter().map(|entry| entry.mv).find(|mv| mv.to_uci(board) == text)
+}
+
+fn game_result(board: &Board) -> Option<&'static str> {
+    if board.in_check() && !board.has_legal_moves() {
+        return Some(match board.side_to_move() {
+            Color::White => "0-1 {Black mates}",
+            Color::Black => "1-0 {White mates}",
+        });
+    }
+
+    board.draw_reason().map(|reason| match reason {
+        DrawReason::Stalemate => "1/2-1/2 {Stalemate}",
+        DrawReason::InsufficientMaterial => "1/2-1/2 {Insufficient material}",
+        DrawReason::FiftyMoveRule => "1/2-1/2 {Fifty move rule}",
+        DrawReason::ThreefoldRepetition => "1/2-1/2 {Threefold repetition}",
+    })
+}